@@ -0,0 +1,211 @@
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{Read, Write, BufReader, BufWriter};
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+
+// Versioned binary format for matrices in CRS format
+// Header:
+//   magic        8 bytes  "SPMATCRS"
+//   version      u16
+//   endianness   u8       0 = little, 1 = big
+//   index type   u8       type code of the index type
+//   value type   u8       type code of the value type
+//   reserved     3 bytes
+//   n_rows       u64
+//   n_cols       u64
+//   nnz          u64
+// Data:
+//   offset_rows  (n_rows + 1) x index type
+//   columns      nnz x index type
+//   values       nnz x value type
+// All numbers are stored with the endianness recorded in the header,
+// so files written on a machine with different byte order can be read as well
+const MAGIC: &[u8; 8] = b"SPMATCRS";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 40;
+
+// Trait for the element types which can be stored in the binary format
+// The type code encodes the kind of number in the upper and its size in bytes in the lower bits
+pub trait BinaryType
+where Self: Copy {
+    const TYPE_CODE: u8;
+    const SIZE: usize;
+    fn write_bytes(&self, buf: &mut Vec<u8>);
+    fn read_bytes(bytes: &[u8], big_endian: bool) -> Self;
+}
+
+pub(crate) const KIND_UNSIGNED: u8 = 0x10;
+pub(crate) const KIND_SIGNED: u8 = 0x20;
+pub(crate) const KIND_FLOAT: u8 = 0x30;
+
+macro_rules! make_binarytype {
+    ( $t: ty, $kind: expr ) => {
+        impl BinaryType for $t {
+            const TYPE_CODE: u8 = $kind | std::mem::size_of::<$t>() as u8;
+            const SIZE: usize = std::mem::size_of::<$t>();
+
+            fn write_bytes(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_ne_bytes());
+            }
+
+            fn read_bytes(bytes: &[u8], big_endian: bool) -> $t {
+                let bytes = bytes.try_into().unwrap();
+                if big_endian {
+                    <$t>::from_be_bytes(bytes)
+                } else {
+                    <$t>::from_le_bytes(bytes)
+                }
+            }
+        }
+    }
+}
+
+make_binarytype!(u8, KIND_UNSIGNED);
+make_binarytype!(u16, KIND_UNSIGNED);
+make_binarytype!(u32, KIND_UNSIGNED);
+make_binarytype!(u64, KIND_UNSIGNED);
+make_binarytype!(usize, KIND_UNSIGNED);
+make_binarytype!(i8, KIND_SIGNED);
+make_binarytype!(i16, KIND_SIGNED);
+make_binarytype!(i32, KIND_SIGNED);
+make_binarytype!(i64, KIND_SIGNED);
+make_binarytype!(f32, KIND_FLOAT);
+make_binarytype!(f64, KIND_FLOAT);
+
+// Returns the name of the type with the given code for error messages
+pub(crate) fn type_name(code: u8) -> String {
+    let kind = match code & 0xf0 {
+        KIND_UNSIGNED => "u",
+        KIND_SIGNED => "i",
+        KIND_FLOAT => "f",
+        _ => return format!("unknown type {:#04x}", code),
+    };
+    format!("{}{}", kind, 8 * (code & 0x0f) as usize)
+}
+
+// Number of bytes collected before they are passed to the writer
+const CHUNK_SIZE: usize = 1 << 16;
+
+// Writes the elements in chunks of about CHUNK_SIZE bytes, so whole arrays are never buffered
+fn write_chunked<W, B, It>(writer: &mut W, buf: &mut Vec<u8>, elements: It) -> Result<(), SparseMatError>
+where W: Write,
+      B: BinaryType,
+      It: Iterator<Item = B> {
+    buf.clear();
+    for element in elements {
+        element.write_bytes(buf);
+        if buf.len() >= CHUNK_SIZE {
+            writer.write_all(buf)?;
+            buf.clear();
+        }
+    }
+    writer.write_all(buf)?;
+    Ok(())
+}
+
+// Reads n elements of a binary type
+fn read_array<R, B>(reader: &mut R, n: usize, big_endian: bool, what: &str) -> Result<Vec<B>, SparseMatError>
+where R: Read,
+      B: BinaryType {
+    let n_bytes = n.checked_mul(B::SIZE).ok_or_else(|| SparseMatError::new("Invalid number of entries"))?;
+    let mut bytes = Vec::<u8>::new();
+    reader.take(n_bytes as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != n_bytes {
+        return Err(SparseMatError::new(&format!("Truncated file - expected {} bytes of {}, found {}", n_bytes, what, bytes.len())));
+    }
+    Ok(bytes.chunks_exact(B::SIZE).map(|chunk| B::read_bytes(chunk, big_endian)).collect())
+}
+
+// Converts a number from the header to usize
+fn header_usize(val: u64, what: &str) -> Result<usize, SparseMatError> {
+    usize::try_from(val).map_err(|_| SparseMatError::new(&format!("Invalid {} {}", what, val)))
+}
+
+impl<T, I> SparseMatCRS<T, I>
+where T: ValueType + BinaryType,
+      I: IndexType + BinaryType {
+    // Writes the matrix in the binary format
+    pub fn write_binary<W>(&self, writer: &mut W) -> Result<(), SparseMatError>
+    where W: Write {
+        let nnz = self.n_non_zero_entries();
+        let mut buf = Vec::<u8>::with_capacity(HEADER_SIZE);
+        buf.extend_from_slice(MAGIC);
+        VERSION.write_bytes(&mut buf);
+        buf.push(if cfg!(target_endian = "big") { 1 } else { 0 });
+        buf.push(I::TYPE_CODE);
+        buf.push(T::TYPE_CODE);
+        buf.extend_from_slice(&[0u8; 3]);
+        (self.n_rows() as u64).write_bytes(&mut buf);
+        (self.n_cols() as u64).write_bytes(&mut buf);
+        (nnz as u64).write_bytes(&mut buf);
+        writer.write_all(&buf)?;
+        // Offsets of the rows
+        let mut offset = 0;
+        let offsets = (0..self.n_rows()).map(|i| {
+            offset += self.iter_row(i).count();
+            I::as_indextype(offset)
+        });
+        write_chunked(writer, &mut buf, std::iter::once(I::ZERO).chain(offsets))?;
+        // Columns and values are written row by row, which skips any free positions in slack mode
+        let columns = (0..self.n_rows()).flat_map(|i| self.iter_row(i).map(|(&col, _val)| col));
+        write_chunked(writer, &mut buf, columns)?;
+        let values = (0..self.n_rows()).flat_map(|i| self.iter_row(i).map(|(_col, &val)| val));
+        write_chunked(writer, &mut buf, values)?;
+        Ok(())
+    }
+
+    // Reads a matrix in the binary format
+    // The header has to match the index and value type of the matrix
+    // and the structure is validated, so corrupt files result in an error
+    pub fn read_binary<R>(reader: &mut R) -> Result<Self, SparseMatError>
+    where R: Read {
+        let header = read_array::<R, u8>(reader, HEADER_SIZE, false, "header")?;
+        if &header[0..8] != MAGIC {
+            return Err(SparseMatError::new("Invalid file - magic number does not match"));
+        }
+        let big_endian = match header[10] {
+            0 => false,
+            1 => true,
+            flag => return Err(SparseMatError::new(&format!("Invalid endianness flag {}", flag))),
+        };
+        let version = u16::read_bytes(&header[8..10], big_endian);
+        if version != VERSION {
+            return Err(SparseMatError::new(&format!("Unsupported version {} - expected {}", version, VERSION)));
+        }
+        if header[11] != I::TYPE_CODE {
+            return Err(SparseMatError::new(&format!("Index type mismatch - file has {}, expected {}",
+                                                    type_name(header[11]), type_name(I::TYPE_CODE))));
+        }
+        if header[12] != T::TYPE_CODE {
+            return Err(SparseMatError::new(&format!("Value type mismatch - file has {}, expected {}",
+                                                    type_name(header[12]), type_name(T::TYPE_CODE))));
+        }
+        let n_rows = header_usize(u64::read_bytes(&header[16..24], big_endian), "number of rows")?;
+        let n_cols = header_usize(u64::read_bytes(&header[24..32], big_endian), "number of columns")?;
+        let nnz = header_usize(u64::read_bytes(&header[32..40], big_endian), "number of entries")?;
+        if nnz >= I::MAX.as_usize() || n_cols > I::MAX.as_usize() {
+            return Err(SparseMatError::new("Matrix does not fit into the index type"));
+        }
+        let n_offsets = n_rows.checked_add(1).ok_or_else(|| SparseMatError::new("Invalid number of rows"))?;
+        let offset_rows = read_array::<R, I>(reader, n_offsets, big_endian, "row offsets")?;
+        let columns = read_array::<R, I>(reader, nnz, big_endian, "columns")?;
+        let values = read_array::<R, T>(reader, nnz, big_endian, "values")?;
+        Self::try_from_raw(n_rows, n_cols, offset_rows, columns, values)
+    }
+
+    // Saves the matrix to a file in the binary format
+    pub fn save_binary(&self, filename: &str) -> Result<(), SparseMatError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_binary(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Loads a matrix from a file in the binary format
+    pub fn load_binary(filename: &str) -> Result<Self, SparseMatError> {
+        let mut reader = BufReader::new(File::open(filename)?);
+        Self::read_binary(&mut reader)
+    }
+}
@@ -0,0 +1,354 @@
+use crate::types::{ValueType, FloatType};
+use crate::sparsematrix::SparseMatError;
+use crate::densevec::DenseVec;
+use crate::vector::Vector;
+
+// Dense matrix with the values stored row by row
+// Intended for small blocks, Hessenberg matrices of Krylov methods and as reference in tests
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DenseMat<T> {
+    n_rows: usize,
+    n_cols: usize,
+    values: Vec<T>,
+}
+
+impl<T> DenseMat<T>
+where T: ValueType {
+    // Creates a matrix with all entries zero
+    pub fn zeros(n_rows: usize, n_cols: usize) -> Self {
+        Self {
+            n_rows,
+            n_cols,
+            values: vec![T::zero(); n_rows * n_cols],
+        }
+    }
+
+    // Returns the identity matrix with dimension dim
+    pub fn eye(dim: usize) -> Self {
+        let mut ret = Self::zeros(dim, dim);
+        for i in 0..dim {
+            ret.set(i, i, T::one());
+        }
+        ret
+    }
+
+    // Creates a matrix from its values stored row by row
+    pub fn from_vec(n_rows: usize, n_cols: usize, values: Vec<T>) -> Result<Self, SparseMatError> {
+        if n_rows.checked_mul(n_cols) != Some(values.len()) {
+            return Err(SparseMatError::new("Number of values does not match the dimensions"));
+        }
+        Ok(Self {
+            n_rows,
+            n_cols,
+            values,
+        })
+    }
+
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> T {
+        self.values[i * self.n_cols + j]
+    }
+
+    pub fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        &mut self.values[i * self.n_cols + j]
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, val: T) {
+        *self.get_mut(i, j) = val;
+    }
+
+    pub fn add_to(&mut self, i: usize, j: usize, val: T) {
+        *self.get_mut(i, j) += val;
+    }
+
+    // Returns the values of row i
+    pub fn row(&self, i: usize) -> &[T] {
+        &self.values[i * self.n_cols..(i + 1) * self.n_cols]
+    }
+
+    pub fn row_mut(&mut self, i: usize) -> &mut [T] {
+        &mut self.values[i * self.n_cols..(i + 1) * self.n_cols]
+    }
+
+    // Returns all values row by row
+    pub fn as_slice(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    // Returns column j as vector
+    pub fn col(&self, j: usize) -> DenseVec<T> {
+        DenseVec::from_vec((0..self.n_rows).map(|i| self.get(i, j)).collect())
+    }
+
+    pub fn scale(&mut self, rhs: T) {
+        for val in self.values.iter_mut() {
+            *val *= rhs;
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut ret = Self::zeros(self.n_cols, self.n_rows);
+        for i in 0..self.n_rows {
+            for (j, &val) in self.row(i).iter().enumerate() {
+                ret.set(j, i, val);
+            }
+        }
+        ret
+    }
+
+    // Computes the matrix vector product
+    pub fn mvp(&self, rhs: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        if rhs.dim() != self.n_cols {
+            return Err(SparseMatError::new("Dimension mismatch"));
+        }
+        let ret = (0..self.n_rows).map(|i| {
+            self.row(i).iter().zip(rhs.iter_ref()).map(|(&a, &x)| a * x).sum()
+        }).collect();
+        Ok(DenseVec::from_vec(ret))
+    }
+
+    // Computes the matrix product self * rhs
+    // The rows of rhs are accumulated, so both matrices are traversed row by row
+    pub fn prod(&self, rhs: &Self) -> Result<Self, SparseMatError> {
+        if self.n_cols != rhs.n_rows {
+            return Err(SparseMatError::new("Dimension mismatch"));
+        }
+        let mut ret = Self::zeros(self.n_rows, rhs.n_cols);
+        for i in 0..self.n_rows {
+            for (k, &a) in self.row(i).iter().enumerate() {
+                for (r, &b) in ret.row_mut(i).iter_mut().zip(rhs.row(k).iter()) {
+                    *r += a * b;
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl<T> DenseMat<T>
+where T: FloatType {
+    // Returns the largest absolute difference of the entries to another matrix
+    pub fn max_abs_diff(&self, rhs: &Self) -> f64 {
+        if self.n_rows != rhs.n_rows || self.n_cols != rhs.n_cols {
+            return f64::INFINITY;
+        }
+        self.values.iter().zip(rhs.values.iter())
+            .map(|(&a, &b)| (a - b).abs().into())
+            .fold(0.0, f64::max)
+    }
+
+    // Computes the LU decomposition with partial pivoting P * A = L * U
+    pub fn lu(&self) -> Result<DenseLU<T>, SparseMatError> {
+        if self.n_rows != self.n_cols {
+            return Err(SparseMatError::new("Matrix is not square"));
+        }
+        let n = self.n_rows;
+        let mut lu = self.clone();
+        let mut perm = (0..n).collect::<Vec<usize>>();
+        let mut n_swaps = 0;
+        for k in 0..n {
+            // Row with the largest pivot, where NaN compares larger than any number and is reported
+            let p = (k..n).max_by(|&i1, &i2| lu.get(i1, k).abs().into().total_cmp(&lu.get(i2, k).abs().into())).unwrap();
+            if lu.get(p, k).into().is_nan() {
+                return Err(SparseMatError::new(&format!("Matrix contains NaN in column {}", k)));
+            }
+            if lu.get(p, k) == T::zero() {
+                return Err(SparseMatError::new(&format!("Singular matrix - zero pivot in column {}", k)));
+            }
+            if p != k {
+                for j in 0..n {
+                    lu.values.swap(k * n + j, p * n + j);
+                }
+                perm.swap(k, p);
+                n_swaps += 1;
+            }
+            let pivot = lu.get(k, k);
+            for i in k + 1..n {
+                let factor = lu.get(i, k) / pivot;
+                lu.set(i, k, factor);
+                for j in k + 1..n {
+                    let val = lu.get(k, j);
+                    *lu.get_mut(i, j) -= factor * val;
+                }
+            }
+        }
+        Ok(DenseLU {
+            lu,
+            perm,
+            n_swaps,
+        })
+    }
+
+    // Computes the thin QR decomposition A = Q * R with Householder reflections
+    // Q has orthonormal columns and the dimension of A, R is upper triangular
+    pub fn qr(&self) -> Result<DenseQR<T>, SparseMatError> {
+        let (m, n) = (self.n_rows, self.n_cols);
+        if m < n {
+            return Err(SparseMatError::new("QR decomposition requires at least as many rows as columns"));
+        }
+        let mut r = self.clone();
+        let mut reflectors = Vec::<Vec<T>>::with_capacity(n);
+        for k in 0..n {
+            // Householder vector v with (I - 2 v v^T) x = -sign(x_0) |x| e_0
+            let mut v = (k..m).map(|i| r.get(i, k)).collect::<Vec<T>>();
+            let norm: f64 = v.iter().map(|&x| x * x).sum::<T>().into().sqrt();
+            let alpha = if v[0] > T::zero() { -T::from_f64(norm) } else { T::from_f64(norm) };
+            v[0] -= alpha;
+            let v_norm: f64 = v.iter().map(|&x| x * x).sum::<T>().into().sqrt();
+            if v_norm > 0.0 {
+                for x in v.iter_mut() {
+                    *x = *x / T::from_f64(v_norm);
+                }
+                for j in k..n {
+                    let dot: T = (k..m).map(|i| v[i - k] * r.get(i, j)).sum();
+                    for i in k..m {
+                        *r.get_mut(i, j) -= T::from_f64(2.0) * v[i - k] * dot;
+                    }
+                }
+            }
+            reflectors.push(v);
+        }
+        // Accumulate Q by applying the reflections to the first n columns of the identity
+        let mut q = Self::zeros(m, n);
+        for j in 0..n {
+            q.set(j, j, T::one());
+        }
+        for (k, v) in reflectors.iter().enumerate().rev() {
+            for j in 0..n {
+                let dot: T = (k..m).map(|i| v[i - k] * q.get(i, j)).sum();
+                for i in k..m {
+                    *q.get_mut(i, j) -= T::from_f64(2.0) * v[i - k] * dot;
+                }
+            }
+        }
+        let mut r_upper = Self::zeros(n, n);
+        for i in 0..n {
+            for j in i..n {
+                r_upper.set(i, j, r.get(i, j));
+            }
+        }
+        Ok(DenseQR {
+            q,
+            r: r_upper,
+        })
+    }
+}
+
+// LU decomposition P * A = L * U with L having a unit diagonal
+// Both factors are stored in a single matrix
+#[derive(Clone, Debug)]
+pub struct DenseLU<T> {
+    lu: DenseMat<T>,
+    perm: Vec<usize>,
+    n_swaps: usize,
+}
+
+impl<T> DenseLU<T>
+where T: FloatType {
+    // Returns the lower triangular factor with unit diagonal
+    pub fn l(&self) -> DenseMat<T> {
+        let n = self.lu.n_rows();
+        let mut ret = DenseMat::<T>::eye(n);
+        for i in 0..n {
+            for j in 0..i {
+                ret.set(i, j, self.lu.get(i, j));
+            }
+        }
+        ret
+    }
+
+    // Returns the upper triangular factor
+    pub fn u(&self) -> DenseMat<T> {
+        let n = self.lu.n_rows();
+        let mut ret = DenseMat::<T>::zeros(n, n);
+        for i in 0..n {
+            for j in i..n {
+                ret.set(i, j, self.lu.get(i, j));
+            }
+        }
+        ret
+    }
+
+    // Returns the row of A at each row of L * U
+    pub fn permutation(&self) -> &[usize] {
+        &self.perm
+    }
+
+    pub fn determinant(&self) -> T {
+        let det = (0..self.lu.n_rows()).fold(T::one(), |det, i| det * self.lu.get(i, i));
+        if self.n_swaps % 2 == 1 { -det } else { det }
+    }
+
+    // Solves A * x = b by forward and backward substitution
+    pub fn solve(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        let n = self.lu.n_rows();
+        if b.dim() != n {
+            return Err(SparseMatError::new("Dimension mismatch"));
+        }
+        let mut x = self.perm.iter().map(|&p| b.get(p)).collect::<Vec<T>>();
+        for i in 0..n {
+            for j in 0..i {
+                let val = self.lu.get(i, j) * x[j];
+                x[i] -= val;
+            }
+        }
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                let val = self.lu.get(i, j) * x[j];
+                x[i] -= val;
+            }
+            x[i] = x[i] / self.lu.get(i, i);
+        }
+        Ok(DenseVec::from_vec(x))
+    }
+}
+
+// Thin QR decomposition A = Q * R
+#[derive(Clone, Debug)]
+pub struct DenseQR<T> {
+    q: DenseMat<T>,
+    r: DenseMat<T>,
+}
+
+impl<T> DenseQR<T>
+where T: FloatType {
+    pub fn q(&self) -> &DenseMat<T> {
+        &self.q
+    }
+
+    pub fn r(&self) -> &DenseMat<T> {
+        &self.r
+    }
+
+    // Solves the least squares problem min |A * x - b| by R * x = Q^T * b
+    pub fn solve(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        let (m, n) = (self.q.n_rows(), self.q.n_cols());
+        if b.dim() != m {
+            return Err(SparseMatError::new("Dimension mismatch"));
+        }
+        let mut x = (0..n).map(|j| (0..m).map(|i| self.q.get(i, j) * b.get(i)).sum()).collect::<Vec<T>>();
+        for i in (0..n).rev() {
+            if self.r.get(i, i) == T::zero() {
+                return Err(SparseMatError::new(&format!("Rank deficient matrix - zero diagonal in column {}", i)));
+            }
+            for j in i + 1..n {
+                let val = self.r.get(i, j) * x[j];
+                x[i] -= val;
+            }
+            x[i] = x[i] / self.r.get(i, i);
+        }
+        Ok(DenseVec::from_vec(x))
+    }
+}
@@ -2,6 +2,7 @@ use crate::vector::*;
 use crate::types::ValueType;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DenseVec<T> {
     values: Vec<T>,
 }
@@ -73,6 +74,14 @@ where T: 'a + ValueType {
     }
 }
 
+// y = y + a * x
+pub(crate) fn axpy<T>(y: &mut DenseVec<T>, a: T, x: &DenseVec<T>)
+where T: ValueType {
+    for i in 0..x.dim() {
+        *y.get_mut(i) += a * x.get(i);
+    }
+}
+
 impl<T> std::ops::AddAssign for DenseVec<T>
 where T: ValueType {
     fn add_assign(&mut self, rhs: Self) {
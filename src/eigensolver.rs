@@ -0,0 +1,907 @@
+use crate::types::FloatType;
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::*;
+use crate::vector::*;
+use crate::densevec::*;
+use crate::linearsolver::*;
+
+// Part of the spectrum targeted by the eigensolvers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Spectrum {
+    LargestMagnitude,
+    SmallestMagnitude,
+    LargestReal,
+    SmallestReal,
+}
+
+// Real eigenvalue with its normalized eigenvector
+// The residual is the norm of A * v - value * v
+#[derive(Clone, Debug)]
+pub struct Eigenpair<T> {
+    pub value: T,
+    pub vector: DenseVec<T>,
+    pub residual: f64,
+}
+
+// Complex eigenvalue re + i * im with the normalized eigenvector vector_re + i * vector_im
+// For real eigenvalues im is zero and vector_im contains zeroes
+#[derive(Clone, Debug)]
+pub struct ComplexEigenpair<T> {
+    pub re: T,
+    pub im: T,
+    pub vector_re: DenseVec<T>,
+    pub vector_im: DenseVec<T>,
+    pub residual: f64,
+}
+
+// Implicitly restarted Lanczos method for symmetric matrices
+pub struct Lanczos {
+    n_eigen: usize,
+    n_basis: usize,
+    which: Spectrum,
+    tol: f64,
+    iter_max: usize,
+}
+
+// Implicitly restarted Arnoldi method for nonsymmetric matrices
+pub struct Arnoldi {
+    n_eigen: usize,
+    n_basis: usize,
+    which: Spectrum,
+    tol: f64,
+    iter_max: usize,
+}
+
+macro_rules! krylov_eigensolver_params {
+    ($Name: ident) => {
+        impl $Name {
+            // Computes n_eigen eigenpairs from the given part of the spectrum
+            pub fn new(n_eigen: usize, which: Spectrum) -> Self {
+                Self {
+                    n_eigen,
+                    n_basis: std::cmp::max(2 * n_eigen + 1, 20),
+                    which,
+                    tol: 1e-10f64,
+                    iter_max: 300,
+                }
+            }
+
+            // Sets the maximum dimension of the Krylov subspace
+            pub fn with_basis(mut self, n_basis: usize) -> Self {
+                self.n_basis = n_basis;
+                self
+            }
+
+            // Sets the relative tolerance of the residuals and the maximum number of restarts
+            pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+                self.tol = tol;
+                self.iter_max = iter_max;
+                self
+            }
+
+            // Returns the dimension of the subspace for a matrix of dimension n
+            fn check_dimension(&self, n_rows: usize, n_cols: usize) -> Result<usize, SparseMatError> {
+                if n_rows != n_cols {
+                    return Err(SparseMatError::new("Matrix is not square"));
+                }
+                if self.n_eigen == 0 || self.n_eigen >= n_rows {
+                    return Err(SparseMatError::new("Number of eigenvalues has to be smaller than the dimension"));
+                }
+                Ok(std::cmp::min(std::cmp::max(self.n_basis, self.n_eigen + 2), n_rows))
+            }
+        }
+    }
+}
+
+krylov_eigensolver_params!(Lanczos);
+krylov_eigensolver_params!(Arnoldi);
+
+impl Lanczos {
+    pub fn solve<'a, M>(&self, mat: &'a M) -> Result<Vec<Eigenpair<M::Value>>, SparseMatError>
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        let m = self.check_dimension(mat.n_rows(), mat.n_cols())?;
+        let fact = implicit_restart(mat, self.n_eigen, m, self.which, self.tol, self.iter_max, true)?;
+        let ritz = fact.ritz(self.which)?;
+        let ret = ritz.iter().take(self.n_eigen).map(|r| {
+            let value = M::Value::from_f64(r.re);
+            let vector = fact.combine(&r.y_re);
+            // r = A * v - value * v
+            let mut res = mat.mvp(&vector);
+            axpy(&mut res, -value, &vector);
+            let residual = res.norm();
+            Eigenpair {
+                value,
+                vector,
+                residual,
+            }
+        }).collect();
+        Ok(ret)
+    }
+}
+
+impl Arnoldi {
+    pub fn solve<'a, M>(&self, mat: &'a M) -> Result<Vec<ComplexEigenpair<M::Value>>, SparseMatError>
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        let m = self.check_dimension(mat.n_rows(), mat.n_cols())?;
+        let fact = implicit_restart(mat, self.n_eigen, m, self.which, self.tol, self.iter_max, false)?;
+        let ritz = fact.ritz(self.which)?;
+        let ret = ritz.iter().take(self.n_eigen).map(|r| {
+            let re = M::Value::from_f64(r.re);
+            let im = M::Value::from_f64(r.im);
+            let vector_re = fact.combine(&r.y_re);
+            let vector_im = fact.combine(&r.y_im);
+            // Real and imaginary part of A * v - (re + i * im) * v
+            let mut res_re = mat.mvp(&vector_re);
+            axpy(&mut res_re, -re, &vector_re);
+            axpy(&mut res_re, im, &vector_im);
+            let mut res_im = mat.mvp(&vector_im);
+            axpy(&mut res_im, -re, &vector_im);
+            axpy(&mut res_im, -im, &vector_re);
+            let residual = f64::sqrt((res_re.norm_squared() + res_im.norm_squared()).into());
+            ComplexEigenpair {
+                re,
+                im,
+                vector_re,
+                vector_im,
+                residual,
+            }
+        }).collect();
+        Ok(ret)
+    }
+}
+
+// Power iteration converging to the eigenvalue of largest magnitude
+pub struct PowerIteration {
+    tol: f64,
+    iter_max: usize,
+}
+
+// Inverse iteration converging to the eigenvalue closest to the shift
+// The shifted systems are solved with any linear solver
+pub struct InverseIteration {
+    shift: f64,
+    tol: f64,
+    iter_max: usize,
+}
+
+// Rayleigh quotient iteration updating the shift in each step
+// Converges cubically for symmetric matrices once close to an eigenpair
+pub struct RayleighQuotientIteration {
+    shift: Option<f64>,
+    tol: f64,
+    iter_max: usize,
+}
+
+impl Default for PowerIteration {
+    fn default() -> Self {
+        Self {
+            tol: 1e-10f64,
+            iter_max: 10_000,
+        }
+    }
+}
+
+impl Default for RayleighQuotientIteration {
+    fn default() -> Self {
+        Self {
+            shift: None,
+            tol: 1e-10f64,
+            iter_max: 100,
+        }
+    }
+}
+
+impl PowerIteration {
+    // Sets the relative tolerance of the residual and the maximum number of iterations
+    pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+        self.tol = tol;
+        self.iter_max = iter_max;
+        self
+    }
+
+    pub fn solve<'a, M>(&self, mat: &'a M) -> Result<Eigenpair<M::Value>, SparseMatError>
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        check_square(mat)?;
+        let mut x = normalized(random_vector::<M::Value>(mat.n_rows(), 0));
+        let mut ret = rayleigh_quotient(mat, &x);
+        for _k in 0..self.iter_max {
+            if ret.residual <= self.tol * f64::abs(ret.value.into()).max(f64::EPSILON) {
+                break;
+            }
+            x = normalized(mat.mvp(&x));
+            ret = rayleigh_quotient(mat, &x);
+        }
+        Ok(ret)
+    }
+}
+
+impl InverseIteration {
+    pub fn new(shift: f64) -> Self {
+        Self {
+            shift,
+            tol: 1e-10f64,
+            iter_max: 1_000,
+        }
+    }
+
+    // Sets the relative tolerance of the residual and the maximum number of iterations
+    pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+        self.tol = tol;
+        self.iter_max = iter_max;
+        self
+    }
+
+    pub fn solve<'a, M, S>(&self, mat: &'a M, solver: &S) -> Result<Eigenpair<M::Value>, SparseMatError>
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType,
+          S: LinearSolver {
+        check_square(mat)?;
+        let shifted = shifted_matrix(mat, M::Value::from_f64(self.shift));
+        let mut x = normalized(random_vector::<M::Value>(mat.n_rows(), 0));
+        let mut ret = rayleigh_quotient(mat, &x);
+        for _k in 0..self.iter_max {
+            if ret.residual <= self.tol * f64::abs(ret.value.into()).max(f64::EPSILON) {
+                break;
+            }
+            // Solve (A - shift * I) * y = x
+            let mut y = x.clone();
+            solver.solve(&shifted, &x, &mut y);
+            x = normalized(y);
+            ret = rayleigh_quotient(mat, &x);
+        }
+        Ok(ret)
+    }
+}
+
+impl RayleighQuotientIteration {
+    // Uses the given initial shift instead of the Rayleigh quotient of the start vector
+    pub fn with_shift(mut self, shift: f64) -> Self {
+        self.shift = Some(shift);
+        self
+    }
+
+    // Sets the relative tolerance of the residual and the maximum number of iterations
+    pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+        self.tol = tol;
+        self.iter_max = iter_max;
+        self
+    }
+
+    pub fn solve<'a, M, S>(&self, mat: &'a M, solver: &S) -> Result<Eigenpair<M::Value>, SparseMatError>
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType,
+          S: LinearSolver {
+        check_square(mat)?;
+        let mut x = normalized(random_vector::<M::Value>(mat.n_rows(), 0));
+        let mut ret = rayleigh_quotient(mat, &x);
+        let mut shift = match self.shift {
+            Some(shift) => M::Value::from_f64(shift),
+            None => ret.value,
+        };
+        for _k in 0..self.iter_max {
+            if ret.residual <= self.tol * f64::abs(ret.value.into()).max(f64::EPSILON) {
+                break;
+            }
+            // Solve (A - shift * I) * y = x
+            // The shifted matrix gets singular once the shift hits an eigenvalue
+            // A slightly perturbed shift then yields the eigenvector in a single step
+            let mut y = x.clone();
+            if solver.try_solve(&shifted_matrix(mat, shift), &x, &mut y).is_err() {
+                let perturbed = shift + M::Value::from_f64(f64::EPSILON.sqrt() * f64::abs(shift.into()).max(1.0));
+                y = x.clone();
+                if solver.try_solve(&shifted_matrix(mat, perturbed), &x, &mut y).is_err() {
+                    break;
+                }
+            }
+            if !y.norm().is_finite() {
+                break;
+            }
+            x = normalized(y);
+            ret = rayleigh_quotient(mat, &x);
+            shift = ret.value;
+        }
+        Ok(ret)
+    }
+}
+
+fn check_square<'a, M>(mat: &'a M) -> Result<(), SparseMatError>
+where M: SparseMatrix<'a> {
+    if mat.n_rows() != mat.n_cols() {
+        return Err(SparseMatError::new("Matrix is not square"));
+    }
+    Ok(())
+}
+
+// Returns the matrix A - shift * I in CRS format
+fn shifted_matrix<'a, M>(mat: &'a M, shift: M::Value) -> SparseMatCRS<M::Value, M::Index>
+where M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    let mut ret = SparseMatCRS::from_sparsemat(mat);
+    for i in 0..mat.n_rows() {
+        *ret.get_mut(i, i) -= shift;
+    }
+    ret
+}
+
+fn normalized<T>(mut x: DenseVec<T>) -> DenseVec<T>
+where T: FloatType {
+    let norm = x.norm();
+    if norm > 0.0 {
+        x.scale(T::from_f64(1.0 / norm));
+    }
+    x
+}
+
+// Returns the Rayleigh quotient x^T * A * x of the normalized vector x as eigenpair
+fn rayleigh_quotient<'a, M>(mat: &'a M, x: &DenseVec<M::Value>) -> Eigenpair<M::Value>
+where M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    let mut res = mat.mvp(x);
+    let value = x.inner_prod(&res);
+    axpy(&mut res, -value, x);
+    Eigenpair {
+        value,
+        vector: x.clone(),
+        residual: res.norm(),
+    }
+}
+
+// Returns a reproducible pseudo-random vector with entries in [-1, 1]
+pub(crate) fn random_vector<T>(n: usize, seed: u64) -> DenseVec<T>
+where T: FloatType {
+    let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    let values = (0..n).map(|_i| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        T::from_f64(((state >> 11) as f64 / (1u64 << 53) as f64) * 2.0 - 1.0)
+    }).collect();
+    DenseVec::from_vec(values)
+}
+
+// Ritz value re + i * im with the coefficients of the Ritz vector in the Krylov basis
+struct Ritz {
+    re: f64,
+    im: f64,
+    y_re: Vec<f64>,
+    y_im: Vec<f64>,
+    residual: f64,
+}
+
+// Krylov factorization A * V_m = V_m * H_m + f * e_m^T
+// The projected matrix H is stored row-major in f64
+struct KrylovFactorization<T> {
+    basis: Vec<DenseVec<T>>,
+    h: Vec<f64>,
+    f: DenseVec<T>,
+    m: usize,
+    symmetric: bool,
+    n_random: u64,
+}
+
+impl<T> KrylovFactorization<T>
+where T: FloatType {
+    fn new(n: usize, m: usize, symmetric: bool) -> Self {
+        Self {
+            basis: Vec::<DenseVec<T>>::with_capacity(m),
+            h: vec![0.0; m * m],
+            f: random_vector(n, 0),
+            m,
+            symmetric,
+            n_random: 1,
+        }
+    }
+
+    // Orthogonalizes w against the basis returning the coefficients
+    fn orthogonalize(&self, w: &mut DenseVec<T>) -> Vec<f64> {
+        let mut coeffs = vec![0.0; self.basis.len()];
+        // Gram-Schmidt applied twice is sufficient for numerical orthogonality
+        for _pass in 0..2 {
+            for (c, v) in coeffs.iter_mut().zip(self.basis.iter()) {
+                let dot = v.inner_prod(w);
+                axpy(w, -dot, v);
+                *c += dot.into();
+            }
+        }
+        coeffs
+    }
+
+    // Extends the factorization from k to m basis vectors
+    fn extend<'a, M>(&mut self, mat: &'a M, k: usize)
+    where M: SparseMatrix<'a, Value = T>,
+          T: 'a {
+        let m = self.m;
+        for j in k..m {
+            let mut beta = self.f.norm();
+            if beta < 1e-12 * self.h_norm().max(1.0) {
+                // The Krylov subspace is invariant - continue with a new random direction
+                let mut w = random_vector::<T>(self.f.dim(), self.n_random);
+                self.n_random += 1;
+                self.orthogonalize(&mut w);
+                self.f = w;
+                if j > 0 {
+                    self.h[j * m + j - 1] = 0.0;
+                }
+                beta = 0.0;
+            } else if j > 0 {
+                self.h[j * m + j - 1] = beta;
+            }
+            let norm = self.f.norm();
+            let mut v = self.f.clone();
+            v.scale(T::from_f64(1.0 / norm));
+            self.basis.push(v);
+            let mut w = mat.mvp(&self.basis[j]);
+            let coeffs = self.orthogonalize(&mut w);
+            if self.symmetric {
+                // Only the tridiagonal part is kept, the other coefficients are rounding errors
+                self.h[j * m + j] = coeffs[j];
+                if j > 0 {
+                    self.h[(j - 1) * m + j] = beta;
+                }
+            } else {
+                for (i, &c) in coeffs.iter().enumerate() {
+                    self.h[i * m + j] = c;
+                }
+            }
+            self.f = w;
+        }
+    }
+
+    fn h_norm(&self) -> f64 {
+        self.h.iter().map(|x| x.abs()).fold(0.0, f64::max)
+    }
+
+    // Applies shifted QR steps to H and compresses the factorization to k vectors
+    // A shift (re, im) with im != 0 stands for the complex conjugate pair re +- i * im
+    fn restart(&mut self, k: usize, shifts: &[(f64, f64)]) {
+        let m = self.m;
+        let mut q = vec![0.0; m * m];
+        for i in 0..m {
+            q[i * m + i] = 1.0;
+        }
+        for &(re, im) in shifts.iter() {
+            if im == 0.0 {
+                shifted_qr_step(&mut self.h, &mut q, m, re);
+            } else {
+                double_shift_qr_step(&mut self.h, &mut q, m, 2.0 * re, re * re + im * im);
+            }
+        }
+        // f = v_k * h_{k, k-1} + f * q_{m-1, k-1}
+        let mut f = self.f.clone();
+        f.scale(T::from_f64(q[(m - 1) * m + k - 1]));
+        axpy(&mut f, T::from_f64(self.h[k * m + k - 1]), &self.combine(&(0..m).map(|i| q[i * m + k]).collect::<Vec<f64>>()));
+        // V_k = V_m * Q[:, 0..k]
+        let basis = (0..k).map(|j| self.combine(&(0..m).map(|i| q[i * m + j]).collect::<Vec<f64>>())).collect();
+        self.basis = basis;
+        self.f = f;
+        for i in 0..m {
+            for j in 0..m {
+                if i >= k || j >= k {
+                    self.h[i * m + j] = 0.0;
+                }
+            }
+        }
+    }
+
+    // Returns the linear combination of the basis vectors with coefficients y
+    fn combine(&self, y: &[f64]) -> DenseVec<T> {
+        let mut ret = DenseVec::from_vec(vec![T::zero(); self.f.dim()]);
+        for (v, &c) in self.basis.iter().zip(y.iter()) {
+            if c != 0.0 {
+                axpy(&mut ret, T::from_f64(c), v);
+            }
+        }
+        ret
+    }
+
+    // Returns the Ritz pairs sorted by the targeted part of the spectrum
+    fn ritz(&self, which: Spectrum) -> Result<Vec<Ritz>, SparseMatError> {
+        let m = self.m;
+        let beta = self.f.norm();
+        let mut ret = if self.symmetric {
+            let (values, vectors) = symmetric_eigen(&self.h, m);
+            values.iter().enumerate().map(|(j, &value)| {
+                let y = (0..m).map(|i| vectors[i * m + j]).collect::<Vec<f64>>();
+                Ritz {
+                    re: value,
+                    im: 0.0,
+                    residual: beta * y[m - 1].abs(),
+                    y_re: y,
+                    y_im: vec![0.0; m],
+                }
+            }).collect::<Vec<Ritz>>()
+        } else {
+            hessenberg_eigenvalues(&self.h, m)?.iter().map(|&(re, im)| {
+                let (y_re, y_im) = hessenberg_eigenvector(&self.h, m, re, im);
+                Ritz {
+                    re,
+                    im,
+                    residual: beta * f64::sqrt(y_re[m - 1] * y_re[m - 1] + y_im[m - 1] * y_im[m - 1]),
+                    y_re,
+                    y_im,
+                }
+            }).collect::<Vec<Ritz>>()
+        };
+        let key = |r: &Ritz| match which {
+            Spectrum::LargestMagnitude => -f64::sqrt(r.re * r.re + r.im * r.im),
+            Spectrum::SmallestMagnitude => f64::sqrt(r.re * r.re + r.im * r.im),
+            Spectrum::LargestReal => -r.re,
+            Spectrum::SmallestReal => r.re,
+        };
+        // Complex conjugate pairs are kept together with the positive imaginary part first
+        ret.sort_by(|r1, r2| key(r1).partial_cmp(&key(r2)).unwrap().then(r2.im.partial_cmp(&r1.im).unwrap()));
+        Ok(ret)
+    }
+}
+
+// Runs the implicitly restarted Arnoldi or Lanczos iteration
+// Unwanted Ritz values are used as shifts until the wanted ones have converged
+fn implicit_restart<'a, M>(mat: &'a M, n_eigen: usize, m: usize, which: Spectrum, tol: f64, iter_max: usize, symmetric: bool) -> Result<KrylovFactorization<M::Value>, SparseMatError>
+where M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    let mut fact = KrylovFactorization::<M::Value>::new(mat.n_rows(), m, symmetric);
+    fact.extend(mat, 0);
+    for _iter in 0..iter_max {
+        let ritz = fact.ritz(which)?;
+        let converged = ritz.iter().take(n_eigen)
+            .all(|r| r.residual <= tol * f64::sqrt(r.re * r.re + r.im * r.im).max(f64::EPSILON));
+        if converged || m == mat.n_rows() {
+            break;
+        }
+        // Do not split complex conjugate pairs
+        let mut k = n_eigen;
+        if ritz[k - 1].im != 0.0 && ritz[k - 1].im == -ritz[k].im && k + 1 < m {
+            k += 1;
+        }
+        // Complex conjugate pairs are applied together as a real double shift
+        let mut shifts = Vec::<(f64, f64)>::with_capacity(m - k);
+        let mut i = k;
+        while i < ritz.len() {
+            if ritz[i].im > 0.0 && i + 1 < ritz.len() && ritz[i + 1].im == -ritz[i].im {
+                shifts.push((ritz[i].re, ritz[i].im));
+                i += 2;
+            } else {
+                shifts.push((ritz[i].re, 0.0));
+                i += 1;
+            }
+        }
+        fact.restart(k, &shifts);
+        fact.extend(mat, k);
+    }
+    Ok(fact)
+}
+
+// Performs a QR step H - mu * I = Q * R, H = R * Q + mu * I on the upper Hessenberg matrix
+// using Givens rotations and accumulates the orthogonal transformation in q
+fn shifted_qr_step(h: &mut [f64], q: &mut [f64], m: usize, mu: f64) {
+    for i in 0..m {
+        h[i * m + i] -= mu;
+    }
+    let mut rotations = Vec::<(f64, f64)>::with_capacity(m);
+    for i in 0..(m - 1) {
+        let a = h[i * m + i];
+        let b = h[(i + 1) * m + i];
+        let r = f64::hypot(a, b);
+        let (c, s) = if r == 0.0 { (1.0, 0.0) } else { (a / r, b / r) };
+        for col in 0..m {
+            let t1 = h[i * m + col];
+            let t2 = h[(i + 1) * m + col];
+            h[i * m + col] = c * t1 + s * t2;
+            h[(i + 1) * m + col] = -s * t1 + c * t2;
+        }
+        rotations.push((c, s));
+    }
+    for (i, &(c, s)) in rotations.iter().enumerate() {
+        for row in 0..m {
+            let t1 = h[row * m + i];
+            let t2 = h[row * m + i + 1];
+            h[row * m + i] = c * t1 + s * t2;
+            h[row * m + i + 1] = -s * t1 + c * t2;
+            let t1 = q[row * m + i];
+            let t2 = q[row * m + i + 1];
+            q[row * m + i] = c * t1 + s * t2;
+            q[row * m + i + 1] = -s * t1 + c * t2;
+        }
+    }
+    for i in 0..m {
+        h[i * m + i] += mu;
+    }
+}
+
+// Performs a Francis double shift step on the upper Hessenberg matrix with the shifts
+// given by their sum and product and accumulates the orthogonal transformation in q
+// This is equivalent to two QR steps with a complex conjugate pair of shifts in real arithmetic
+fn double_shift_qr_step(h: &mut [f64], q: &mut [f64], m: usize, sum: f64, product: f64) {
+    if m < 2 {
+        return;
+    }
+    // First column of H^2 - sum * H + product * I
+    let (h00, h01, h10, h11) = (h[0], h[1], h[m], h[m + 1]);
+    let mut x = h00 * h00 + h01 * h10 - sum * h00 + product;
+    let mut y = h10 * (h00 + h11 - sum);
+    let mut z = if m > 2 { h10 * h[2 * m + 1] } else { 0.0 };
+    for k in 0..(m.saturating_sub(2)) {
+        if let Some(u) = householder(&[x, y, z]) {
+            let first_col = k.saturating_sub(1);
+            reflect_rows(h, m, &u, k, first_col..m);
+            reflect_cols(h, m, &u, k, 0..std::cmp::min(k + 4, m));
+            reflect_cols(q, m, &u, k, 0..m);
+            if k > 0 {
+                h[(k + 1) * m + k - 1] = 0.0;
+                h[(k + 2) * m + k - 1] = 0.0;
+            }
+        }
+        x = h[(k + 1) * m + k];
+        y = h[(k + 2) * m + k];
+        if k + 3 < m {
+            z = h[(k + 3) * m + k];
+        }
+    }
+    // The last reflection only acts on two rows
+    if let Some(u) = householder(&[x, y]) {
+        reflect_rows(h, m, &u, m - 2, (m.saturating_sub(3))..m);
+        reflect_cols(h, m, &u, m - 2, 0..m);
+        reflect_cols(q, m, &u, m - 2, 0..m);
+        if m > 2 {
+            h[(m - 1) * m + m - 3] = 0.0;
+        }
+    }
+}
+
+// Computes eigenvalues and eigenvectors of the symmetric part of a small dense matrix
+// with the cyclic Jacobi method - the eigenvectors are stored in the columns
+fn symmetric_eigen(mat: &[f64], n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut a = vec![0.0; n * n];
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..n {
+            a[i * n + j] = 0.5 * (mat[i * n + j] + mat[j * n + i]);
+        }
+        v[i * n + i] = 1.0;
+    }
+    for _sweep in 0..100 {
+        let off = (0..n).map(|i| (0..n).filter(|&j| j != i).map(|j| a[i * n + j] * a[i * n + j]).sum::<f64>()).sum::<f64>();
+        let diag = (0..n).map(|i| a[i * n + i] * a[i * n + i]).sum::<f64>();
+        if off <= 1e-30 * diag || off == 0.0 {
+            break;
+        }
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let a_pq = a[p * n + q];
+                if a_pq == 0.0 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * a_pq);
+                let t = theta.signum() / (theta.abs() + f64::sqrt(theta * theta + 1.0));
+                let c = 1.0 / f64::sqrt(t * t + 1.0);
+                let s = t * c;
+                for k in 0..n {
+                    let a_kp = a[k * n + p];
+                    let a_kq = a[k * n + q];
+                    a[k * n + p] = c * a_kp - s * a_kq;
+                    a[k * n + q] = s * a_kp + c * a_kq;
+                }
+                for k in 0..n {
+                    let a_pk = a[p * n + k];
+                    let a_qk = a[q * n + k];
+                    a[p * n + k] = c * a_pk - s * a_qk;
+                    a[q * n + k] = s * a_pk + c * a_qk;
+                }
+                for k in 0..n {
+                    let v_kp = v[k * n + p];
+                    let v_kq = v[k * n + q];
+                    v[k * n + p] = c * v_kp - s * v_kq;
+                    v[k * n + q] = s * v_kp + c * v_kq;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
+
+// Computes all eigenvalues of a small upper Hessenberg matrix
+// with the implicit double shift QR algorithm of Francis (Golub and Van Loan, Section 7.5)
+// Each step chases a bulge of size three down the unreduced trailing block using Householder reflections,
+// converged 1x1 and 2x2 blocks are deflated from the bottom
+fn hessenberg_eigenvalues(mat: &[f64], n: usize) -> Result<Vec<(f64, f64)>, SparseMatError> {
+    let mut h = mat.to_vec();
+    let norm = h.iter().map(|val| val.abs()).sum::<f64>();
+    let mut eigenvalues = vec![(0.0, 0.0); n];
+    // The active block consists of the rows and columns lo..hi
+    let mut hi = n;
+    let mut n_steps = 0;
+    while hi > 0 {
+        // Find the start of the unreduced block by a negligible subdiagonal entry
+        let mut lo = hi - 1;
+        while lo > 0 {
+            let mut scale = h[(lo - 1) * n + lo - 1].abs() + h[lo * n + lo].abs();
+            if scale == 0.0 {
+                scale = norm;
+            }
+            if h[lo * n + lo - 1].abs() <= f64::EPSILON * scale {
+                h[lo * n + lo - 1] = 0.0;
+                break;
+            }
+            lo -= 1;
+        }
+        if hi - lo == 1 {
+            eigenvalues[hi - 1] = (h[(hi - 1) * n + hi - 1], 0.0);
+            hi -= 1;
+            n_steps = 0;
+            continue;
+        }
+        if hi - lo == 2 {
+            let (first, second) = block_eigenvalues(h[lo * n + lo], h[lo * n + lo + 1], h[(lo + 1) * n + lo], h[(lo + 1) * n + lo + 1]);
+            eigenvalues[lo] = first;
+            eigenvalues[lo + 1] = second;
+            hi -= 2;
+            n_steps = 0;
+            continue;
+        }
+        if n_steps == 100 {
+            return Err(SparseMatError::new("Hessenberg QR iteration did not converge"));
+        }
+        n_steps += 1;
+        // The shifts are the eigenvalues of the trailing 2x2 block given by their sum and product
+        let (a, b) = (h[(hi - 2) * n + hi - 2], h[(hi - 2) * n + hi - 1]);
+        let (c, d) = (h[(hi - 1) * n + hi - 2], h[(hi - 1) * n + hi - 1]);
+        let (sum, product) = if n_steps % 10 == 0 {
+            // Break cycles by an ad hoc complex pair of shifts d + w +- i * w
+            let w = h[(hi - 1) * n + hi - 2].abs() + h[(hi - 2) * n + hi - 3].abs();
+            (2.0 * (d + w), (d + w) * (d + w) + w * w)
+        } else {
+            (a + d, a * d - b * c)
+        };
+        // First column of (H - mu_1 * I) * (H - mu_2 * I) = H^2 - sum * H + product * I
+        let (h00, h01, h10, h11) = (h[lo * n + lo], h[lo * n + lo + 1], h[(lo + 1) * n + lo], h[(lo + 1) * n + lo + 1]);
+        let mut x = h00 * h00 + h01 * h10 - sum * h00 + product;
+        let mut y = h10 * (h00 + h11 - sum);
+        let mut z = h10 * h[(lo + 2) * n + lo + 1];
+        for k in lo..(hi - 2) {
+            if let Some(u) = householder(&[x, y, z]) {
+                let first_col = if k > lo { k - 1 } else { lo };
+                reflect_rows(&mut h, n, &u, k, first_col..hi);
+                reflect_cols(&mut h, n, &u, k, lo..std::cmp::min(k + 4, hi));
+                if k > lo {
+                    h[(k + 1) * n + k - 1] = 0.0;
+                    h[(k + 2) * n + k - 1] = 0.0;
+                }
+            }
+            x = h[(k + 1) * n + k];
+            y = h[(k + 2) * n + k];
+            if k + 3 < hi {
+                z = h[(k + 3) * n + k];
+            }
+        }
+        // The last reflection only acts on two rows
+        if let Some(u) = householder(&[x, y]) {
+            reflect_rows(&mut h, n, &u, hi - 2, (hi - 3)..hi);
+            reflect_cols(&mut h, n, &u, hi - 2, lo..hi);
+            h[(hi - 1) * n + hi - 3] = 0.0;
+        }
+    }
+    Ok(eigenvalues)
+}
+
+// Returns the eigenvalues of the 2x2 matrix [[a, b], [c, d]]
+// A complex pair is returned with the positive imaginary part first
+fn block_eigenvalues(a: f64, b: f64, c: f64, d: f64) -> ((f64, f64), (f64, f64)) {
+    let mean = 0.5 * (a + d);
+    let half_diff = 0.5 * (a - d);
+    let discriminant = half_diff * half_diff + b * c;
+    if discriminant < 0.0 {
+        let im = f64::sqrt(-discriminant);
+        return ((mean, im), (mean, -im));
+    }
+    // The root of larger magnitude avoids cancellation, the other one follows from the determinant
+    let larger = mean + f64::sqrt(discriminant).copysign(mean);
+    let smaller = if larger != 0.0 { (a * d - b * c) / larger } else { 0.0 };
+    ((larger, 0.0), (smaller, 0.0))
+}
+
+// Returns the unit vector u of the Householder reflection I - 2 * u * u^T mapping v onto a multiple of e_0
+// or None if v is zero
+fn householder(v: &[f64]) -> Option<Vec<f64>> {
+    let norm = f64::sqrt(v.iter().map(|x| x * x).sum::<f64>());
+    if norm == 0.0 {
+        return None;
+    }
+    let mut u = v.to_vec();
+    u[0] += norm.copysign(v[0]);
+    let u_norm = f64::sqrt(u.iter().map(|x| x * x).sum::<f64>());
+    u.iter_mut().for_each(|x| *x /= u_norm);
+    Some(u)
+}
+
+// Applies the reflection from the left to the rows first_row.. and the given columns of a
+fn reflect_rows(a: &mut [f64], n: usize, u: &[f64], first_row: usize, cols: std::ops::Range<usize>) {
+    for j in cols {
+        let dot = u.iter().enumerate().map(|(i, &u_i)| u_i * a[(first_row + i) * n + j]).sum::<f64>();
+        for (i, &u_i) in u.iter().enumerate() {
+            a[(first_row + i) * n + j] -= 2.0 * u_i * dot;
+        }
+    }
+}
+
+// Applies the reflection from the right to the columns first_col.. and the given rows of a
+fn reflect_cols(a: &mut [f64], n: usize, u: &[f64], first_col: usize, rows: std::ops::Range<usize>) {
+    for i in rows {
+        let row = &mut a[i * n + first_col..i * n + first_col + u.len()];
+        let dot = u.iter().zip(row.iter()).map(|(&u_j, &a_ij)| u_j * a_ij).sum::<f64>();
+        for (a_ij, &u_j) in row.iter_mut().zip(u.iter()) {
+            *a_ij -= 2.0 * u_j * dot;
+        }
+    }
+}
+
+// Complex arithmetic on pairs of real and imaginary part
+fn cmul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn cdiv(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let d = b.0 * b.0 + b.1 * b.1;
+    ((a.0 * b.0 + a.1 * b.1) / d, (a.1 * b.0 - a.0 * b.1) / d)
+}
+
+fn cabs(a: (f64, f64)) -> f64 {
+    f64::hypot(a.0, a.1)
+}
+
+// Computes the normalized eigenvector of a small dense matrix for the eigenvalue re + i * im
+// with inverse iteration using a complex LU factorization with partial pivoting
+fn hessenberg_eigenvector(mat: &[f64], n: usize, re: f64, im: f64) -> (Vec<f64>, Vec<f64>) {
+    let norm = mat.iter().map(|x| x.abs()).fold(0.0, f64::max).max(1.0);
+    // Perturb the shift slightly to keep the system regular
+    let shift = (re + 1e-10 * norm, im);
+    let mut lu = mat.iter().map(|&x| (x, 0.0)).collect::<Vec<(f64, f64)>>();
+    for i in 0..n {
+        lu[i * n + i] = (lu[i * n + i].0 - shift.0, lu[i * n + i].1 - shift.1);
+    }
+    let mut perm = (0..n).collect::<Vec<usize>>();
+    for k in 0..n {
+        let pivot = (k..n).fold(k, |p, i| if cabs(lu[i * n + k]) > cabs(lu[p * n + k]) { i } else { p });
+        if pivot != k {
+            for j in 0..n {
+                lu.swap(k * n + j, pivot * n + j);
+            }
+            perm.swap(k, pivot);
+        }
+        if cabs(lu[k * n + k]) == 0.0 {
+            lu[k * n + k] = (1e-14 * norm, 0.0);
+        }
+        for i in (k + 1)..n {
+            let factor = cdiv(lu[i * n + k], lu[k * n + k]);
+            lu[i * n + k] = factor;
+            for j in (k + 1)..n {
+                let prod = cmul(factor, lu[k * n + j]);
+                lu[i * n + j] = (lu[i * n + j].0 - prod.0, lu[i * n + j].1 - prod.1);
+            }
+        }
+    }
+    let mut y = vec![(1.0, 0.0); n];
+    for _iter in 0..3 {
+        let mut x = perm.iter().map(|&p| y[p]).collect::<Vec<(f64, f64)>>();
+        for i in 0..n {
+            for j in 0..i {
+                let prod = cmul(lu[i * n + j], x[j]);
+                x[i] = (x[i].0 - prod.0, x[i].1 - prod.1);
+            }
+        }
+        for i in (0..n).rev() {
+            for j in (i + 1)..n {
+                let prod = cmul(lu[i * n + j], x[j]);
+                x[i] = (x[i].0 - prod.0, x[i].1 - prod.1);
+            }
+            x[i] = cdiv(x[i], lu[i * n + i]);
+        }
+        let norm_x = f64::sqrt(x.iter().map(|&c| c.0 * c.0 + c.1 * c.1).sum::<f64>());
+        y = x.iter().map(|&c| (c.0 / norm_x, c.1 / norm_x)).collect();
+    }
+    // Rotate the phase such that the largest entry is real
+    let largest = y.iter().fold((0.0, 0.0), |p, &c| if cabs(c) > cabs(p) { c } else { p });
+    let phase = (largest.0 / cabs(largest), -largest.1 / cabs(largest));
+    let y = y.iter().map(|&c| cmul(c, phase)).collect::<Vec<(f64, f64)>>();
+    (y.iter().map(|c| c.0).collect(), y.iter().map(|c| c.1).collect())
+}
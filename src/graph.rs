@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::{Read, Write, BufReader, BufWriter};
+use crate::types::{IndexType, FloatType};
+use crate::sparsematrix::*;
+use crate::sparsemat_indexlist::SparseMatIndexList;
+
+// Interpretation of a matrix as adjacency matrix of a graph
+// Each entry a_ij is an edge from node i to node j with the value as weight
+
+// Writes the matrix as graph in the DOT language of Graphviz
+// Symmetric matrices are written as undirected graph with each edge only once,
+// otherwise a digraph is written - the values are shown as labels of the edges
+pub fn write_dot<'a, M, W>(mat: &'a M, writer: &mut W) -> Result<(), SparseMatError>
+where M: SparseMatrix<'a>,
+      W: Write {
+    let undirected = mat.n_rows() == mat.n_cols() && mat.is_symmetric();
+    let (keyword, edge) = if undirected { ("graph", "--") } else { ("digraph", "->") };
+    let mut buf = format!("{} {{\n", keyword);
+    // Declare all nodes, so nodes without edges are shown as well
+    for i in 0..std::cmp::max(mat.n_rows(), mat.n_cols()) {
+        buf += &format!("  {};\n", i);
+    }
+    for i in 0..mat.n_rows() {
+        for (col, val) in mat.iter_row(i) {
+            let j = col.as_usize();
+            if undirected && j < i {
+                continue;
+            }
+            buf += &format!("  {} {} {} [label=\"{}\"];\n", i, edge, j, val);
+        }
+    }
+    buf += "}\n";
+    writer.write_all(buf.as_bytes())?;
+    Ok(())
+}
+
+// Returns the error for a malformed line of an edge list
+fn edge_list_error(line_number: usize, what: &str) -> SparseMatError {
+    SparseMatError::new(&format!("Invalid edge list in line {} - {}", line_number, what))
+}
+
+impl<T, I> SparseMatIndexList<T, I>
+where T: FloatType,
+      I: IndexType {
+    // Reads a graph from a whitespace-separated edge list with one edge "i j [weight]" per line
+    // Edges without weight get the weight one and weights of repeated edges are added
+    // Empty lines and comments starting with # or % are skipped
+    // With one_based the nodes are numbered from one like in Matlab or Matrix Market files
+    pub fn read_edge_list<R>(reader: &mut R, one_based: bool) -> Result<Self, SparseMatError>
+    where R: Read {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut mat = Self::new();
+        let first = if one_based { 1 } else { 0 };
+        for (k, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('%') {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            if fields.len() < 2 || fields.len() > 3 {
+                return Err(edge_list_error(k + 1, "expected two nodes and an optional weight"));
+            }
+            let mut nodes = [0usize; 2];
+            for (node, field) in nodes.iter_mut().zip(fields.iter()) {
+                *node = match field.parse::<usize>() {
+                    Ok(index) if index >= first && index - first < I::MAX.as_usize() => index - first,
+                    _ => return Err(edge_list_error(k + 1, &format!("invalid node {}", field))),
+                };
+            }
+            let weight = match fields.get(2) {
+                Some(field) => field.parse::<f64>().map_err(|_| edge_list_error(k + 1, &format!("invalid weight {}", field)))?,
+                None => 1.0,
+            };
+            mat.add_to(nodes[0], nodes[1], T::from_f64(weight));
+        }
+        Ok(mat)
+    }
+
+    // Writes the matrix as edge list with one line "i j weight" per entry
+    pub fn write_edge_list<W>(&self, writer: &mut W, one_based: bool) -> Result<(), SparseMatError>
+    where W: Write {
+        let first = if one_based { 1 } else { 0 };
+        let mut buf = String::new();
+        for i in 0..self.n_rows() {
+            for (col, val) in self.iter_row(i) {
+                buf += &format!("{} {} {}\n", i + first, col.as_usize() + first, val);
+            }
+        }
+        writer.write_all(buf.as_bytes())?;
+        Ok(())
+    }
+
+    // Loads a graph from an edge list file
+    pub fn load_edge_list(filename: &str, one_based: bool) -> Result<Self, SparseMatError> {
+        let mut reader = BufReader::new(File::open(filename)?);
+        Self::read_edge_list(&mut reader, one_based)
+    }
+
+    // Saves the matrix to an edge list file
+    pub fn save_edge_list(&self, filename: &str, one_based: bool) -> Result<(), SparseMatError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_edge_list(&mut writer, one_based)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::io::Read;
+use crate::types::{IndexType, FloatType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+
+// Reader for sparse matrices in the Harwell-Boeing and Rutherford-Boeing format
+// Both are fixed-width Fortran formats storing the matrix column-wise:
+//   line 1   title (A72), key (A8)
+//   line 2   number of lines in total, for pointers, indices, values (and right-hand sides) (I14 each)
+//   line 3   matrix type (A3), 11 blanks, n_rows, n_cols, nnz, number of elemental entries (I14 each)
+//   line 4   Fortran formats of pointers (A16), indices (A16), values (A20) (and right-hand sides (A20))
+//   line 5   only Harwell-Boeing with right-hand sides - their type and count
+// followed by the column pointers, the row indices and the values, each starting on a new line
+// The matrix type consists of three letters:
+//   R real, P pattern or I integer values
+//   U unsymmetric, R rectangular, S symmetric, H hermitian or Z skew-symmetric
+//   A assembled - elemental matrices (E) are not supported
+// Only the lower triangle of symmetric matrices is stored and it is mirrored when reading
+
+// Symmetry of the stored matrix
+#[derive(Clone, Copy, PartialEq)]
+enum Symmetry {
+    General,
+    Symmetric,
+    SkewSymmetric,
+}
+
+fn header_error(what: &str) -> SparseMatError {
+    SparseMatError::new(&format!("Invalid Harwell-Boeing header - {}", what))
+}
+
+// Returns the fixed-width field at the given columns of a line
+// Lines may be shorter than the format, since trailing blanks are often stripped
+fn fixed_field(line: &str, start: usize, width: usize) -> &str {
+    let end = std::cmp::min(start + width, line.len());
+    line.get(start..end).unwrap_or("").trim()
+}
+
+// Parses the I14 field starting at the given column like the line counts and dimensions
+// Returns None for an empty field, since trailing fields are optional
+fn header_integer(line: &str, start: usize, what: &str) -> Result<Option<usize>, SparseMatError> {
+    match fixed_field(line, start, 14) {
+        "" => Ok(None),
+        field => field.parse::<usize>().map(Some).map_err(|_| header_error(&format!("invalid {} {}", what, field))),
+    }
+}
+
+// Returns the number of fields per line and their width of a Fortran format
+// like (16I5), (10I8), (1P,4E20.12), (1P4D26.18) or (5E16.8)
+fn parse_format(format: &str) -> Result<(usize, usize), SparseMatError> {
+    let invalid = || header_error(&format!("unsupported Fortran format {}", format));
+    let spec = format.trim().trim_start_matches('(').trim_end_matches(')').to_uppercase().replace(' ', "");
+    // A scale factor like 1P only affects writing
+    let spec = spec.rsplit(',').next().ok_or_else(invalid)?;
+    let spec = match spec.find('P') {
+        Some(pos) => &spec[pos + 1..],
+        None => spec,
+    };
+    let pos = spec.find(|c: char| c.is_ascii_alphabetic()).ok_or_else(invalid)?;
+    let count = match &spec[..pos] {
+        "" => 1,
+        count => count.parse::<usize>().map_err(|_| invalid())?,
+    };
+    if !"IEDFG".contains(&spec[pos..pos + 1]) {
+        return Err(invalid());
+    }
+    let width = spec[pos + 1..].split('.').next().unwrap_or("");
+    let width = width.parse::<usize>().map_err(|_| invalid())?;
+    if count == 0 || width == 0 {
+        return Err(invalid());
+    }
+    Ok((count, width))
+}
+
+// Splits the lines of a section into the fields of a Fortran format
+// and checks that the expected number of fields is found
+// The count comes from the header, so nothing is allocated for it before it is checked
+fn read_fields<'l>(lines: &[&'l str], format: &str, n: usize, what: &str) -> Result<Vec<&'l str>, SparseMatError> {
+    let (per_line, width) = parse_format(format)?;
+    let mut fields = Vec::<&str>::new();
+    for line in lines.iter() {
+        for k in 0..per_line {
+            let field = fixed_field(line, k * width, width);
+            if !field.is_empty() {
+                fields.push(field);
+            }
+        }
+    }
+    if fields.len() != n {
+        return Err(SparseMatError::new(&format!("Invalid Harwell-Boeing file - expected {} {}, found {}",
+                                                n, what, fields.len())));
+    }
+    Ok(fields)
+}
+
+// Parses a Fortran real, which may use D as exponent or omit the exponent letter like 1.5-10
+fn parse_real(field: &str) -> Result<f64, SparseMatError> {
+    let mut text = field.replace(['D', 'd'], "E");
+    if text.parse::<f64>().is_err() {
+        if let Some(pos) = text.rfind(['+', '-']) {
+            if pos > 0 && !text[..pos].ends_with(['E', 'e']) {
+                text.insert(pos, 'E');
+            }
+        }
+    }
+    text.parse::<f64>().map_err(|_| SparseMatError::new(&format!("Invalid Harwell-Boeing file - invalid value {}", field)))
+}
+
+// Parses 1-based indices and converts them to 0-based
+fn parse_indices(fields: &[&str], what: &str) -> Result<Vec<usize>, SparseMatError> {
+    fields.iter().map(|field| {
+        match field.parse::<usize>() {
+            Ok(index) if index > 0 => Ok(index - 1),
+            _ => Err(SparseMatError::new(&format!("Invalid Harwell-Boeing file - invalid {} {}", what, field))),
+        }
+    }).collect()
+}
+
+impl<T, I> SparseMatCRS<T, I>
+where T: FloatType,
+      I: IndexType {
+    // Reads a real or pattern matrix in the Harwell-Boeing or Rutherford-Boeing format
+    // The values of pattern matrices are set to one
+    pub fn read_harwell_boeing<R>(reader: &mut R) -> Result<Self, SparseMatError>
+    where R: Read {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let lines = text.lines().collect::<Vec<&str>>();
+        if lines.len() < 4 {
+            return Err(header_error("file too short"));
+        }
+        // Line counts of the sections
+        let line_count = |k: usize| header_integer(lines[1], 14 * k, "line count");
+        let missing_count = || header_error("missing line counts");
+        let ptr_lines = line_count(1)?.ok_or_else(missing_count)?;
+        let ind_lines = line_count(2)?.ok_or_else(missing_count)?;
+        let val_lines = line_count(3)?.ok_or_else(missing_count)?;
+        let rhs_lines = line_count(4)?.unwrap_or(0);
+        // Matrix type and dimensions
+        let mxtype = lines[2].get(0..3).ok_or_else(|| header_error("missing matrix type"))?.to_uppercase();
+        let mut mxtype_chars = mxtype.chars();
+        let (value_type, structure, assembled) = match (mxtype_chars.next(), mxtype_chars.next(), mxtype_chars.next()) {
+            (Some(v), Some(s), Some(a)) => (v, s, a),
+            _ => return Err(header_error("missing matrix type")),
+        };
+        let pattern = match value_type {
+            'R' | 'I' => false,
+            'P' => true,
+            'C' => return Err(SparseMatError::new("Complex Harwell-Boeing matrices are not supported")),
+            _ => return Err(header_error(&format!("invalid matrix type {}", mxtype))),
+        };
+        let symmetry = match structure {
+            'U' | 'R' => Symmetry::General,
+            'S' | 'H' => Symmetry::Symmetric,
+            'Z' => Symmetry::SkewSymmetric,
+            _ => return Err(header_error(&format!("invalid matrix type {}", mxtype))),
+        };
+        match assembled {
+            'A' => (),
+            'E' => return Err(SparseMatError::new("Elemental Harwell-Boeing matrices are not supported")),
+            _ => return Err(header_error(&format!("invalid matrix type {}", mxtype))),
+        }
+        let dim = |k: usize| header_integer(lines[2], 14 * k, "dimension")?.ok_or_else(|| header_error("missing dimensions"));
+        let (n_rows, n_cols, nnz) = (dim(1)?, dim(2)?, dim(3)?);
+        if symmetry != Symmetry::General && n_rows != n_cols {
+            return Err(header_error("symmetric matrix is not square"));
+        }
+        if n_rows >= I::MAX.as_usize() || n_cols >= I::MAX.as_usize() {
+            return Err(SparseMatError::new("Matrix does not fit into the index type"));
+        }
+        // Formats of the sections
+        let ptr_format = fixed_field(lines[3], 0, 16);
+        let ind_format = fixed_field(lines[3], 16, 16);
+        let val_format = fixed_field(lines[3], 32, 20);
+        // Harwell-Boeing files with right-hand sides have an additional header line
+        let start = if rhs_lines > 0 { 5 } else { 4 };
+        let n_lines = start + ptr_lines + ind_lines + if pattern { 0 } else { val_lines };
+        if lines.len() < n_lines {
+            return Err(SparseMatError::new(&format!("Invalid Harwell-Boeing file - expected {} lines, found {}",
+                                                    n_lines, lines.len())));
+        }
+        let ptr_section = &lines[start..start + ptr_lines];
+        let ind_section = &lines[start + ptr_lines..start + ptr_lines + ind_lines];
+        let offset_cols = parse_indices(&read_fields(ptr_section, ptr_format, n_cols + 1, "column pointers")?, "column pointer")?;
+        let rows = parse_indices(&read_fields(ind_section, ind_format, nnz, "row indices")?, "row index")?;
+        let values = if pattern {
+            vec![T::one(); nnz]
+        } else {
+            let val_section = &lines[start + ptr_lines + ind_lines..start + ptr_lines + ind_lines + val_lines];
+            read_fields(val_section, val_format, nnz, "values")?.iter()
+                .map(|field| parse_real(field).map(T::from_f64))
+                .collect::<Result<Vec<T>, SparseMatError>>()?
+        };
+        if offset_cols[0] != 0 || offset_cols[n_cols] != nnz || offset_cols.windows(2).any(|offsets| offsets[0] > offsets[1]) {
+            return Err(SparseMatError::new("Invalid Harwell-Boeing file - inconsistent column pointers"));
+        }
+        // Coordinates of all entries including the mirrored ones of symmetric matrices
+        // nnz is checked by now, since as many row indices have been read
+        let mut coo_rows = Vec::<usize>::with_capacity(nnz);
+        let mut coo_cols = Vec::<I>::with_capacity(nnz);
+        let mut coo_values = Vec::<T>::with_capacity(nnz);
+        for j in 0..n_cols {
+            for k in offset_cols[j]..offset_cols[j + 1] {
+                let (i, val) = (rows[k], values[k]);
+                if i >= n_rows {
+                    return Err(SparseMatError::new(&format!("Invalid Harwell-Boeing file - row index {} out of bounds", i + 1)));
+                }
+                coo_rows.push(i);
+                coo_cols.push(I::as_indextype(j));
+                coo_values.push(val);
+                if i != j && symmetry != Symmetry::General {
+                    coo_rows.push(j);
+                    coo_cols.push(I::as_indextype(i));
+                    coo_values.push(if symmetry == Symmetry::SkewSymmetric { -val } else { val });
+                }
+            }
+        }
+        let mut mat = Self::try_from_triplets(n_rows, n_cols, &coo_rows, &coo_cols, &coo_values)?;
+        mat.sort();
+        Ok(mat)
+    }
+
+    // Loads a matrix from a Harwell-Boeing (.rua, .rsa, ...) or Rutherford-Boeing (.rb) file
+    pub fn load_harwell_boeing(filename: &str) -> Result<Self, SparseMatError> {
+        let mut file = File::open(filename)?;
+        Self::read_harwell_boeing(&mut file)
+    }
+}
@@ -22,9 +22,11 @@ use crate::types::IndexType;
 //                                |       |
 //                                ---->----
 // So the entries for the fourth row are stored at position 4 and 5 in the data vec
+// The vec pos_end contains the last position for each row, so appending takes O(1)
 #[derive(Clone, Debug)]
 pub(crate) struct IndexList<I> {
     pos_start: Vec<I>,
+    pos_end: Vec<I>,
     index_list: Vec<I>,
 }
 
@@ -36,6 +38,7 @@ where I: IndexType {
     pub(crate) fn new() -> Self {
         Self {
             pos_start: Vec::<I>::new(),
+            pos_end: Vec::<I>::new(),
             index_list: Vec::<I>::new(),
         }
     }
@@ -44,10 +47,40 @@ where I: IndexType {
     pub(crate) fn with_capacity(cap: usize) -> Self {
         Self {
             pos_start: Vec::<I>::with_capacity(cap),
+            pos_end: Vec::<I>::with_capacity(cap),
             index_list: Vec::<I>::with_capacity(cap),
         }
     }
 
+    // Creates an index list where the entries of each row are stored contiguously
+    // in the order of the rows with the given number of entries per row
+    pub(crate) fn from_row_lengths(lengths: &[usize]) -> Self {
+        let mut ret = Self::new();
+        ret.set_row_lengths(lengths);
+        ret
+    }
+
+    // Resets the index list to contiguous rows reusing the allocated storage
+    pub(crate) fn set_row_lengths(&mut self, lengths: &[usize]) {
+        self.pos_start.clear();
+        self.pos_end.clear();
+        self.index_list.clear();
+        for &len in lengths.iter() {
+            let start = self.index_list.len();
+            if len == 0 {
+                self.pos_start.push(Self::UNSET);
+                self.pos_end.push(Self::UNSET);
+                continue;
+            }
+            self.pos_start.push(I::as_indextype(start));
+            self.pos_end.push(I::as_indextype(start + len - 1));
+            for k in (start + 1)..(start + len) {
+                self.index_list.push(I::as_indextype(k));
+            }
+            self.index_list.push(Self::UNSET);
+        }
+    }
+
     // Returns the number of entries in the index list
     pub(crate) fn n_entries(&self) -> usize {
         self.index_list.len()
@@ -58,11 +91,17 @@ where I: IndexType {
         self.pos_start.len()
     }
 
+    // Adds empty rows up to n_rows
+    pub(crate) fn extend_rows(&mut self, n_rows: usize) {
+        if n_rows > self.pos_start.len() {
+            self.pos_start.resize(n_rows, Self::UNSET);
+            self.pos_end.resize(n_rows, Self::UNSET);
+        }
+    }
+
     // Appends a new entry for row and returns its index in array
     pub(crate) fn push(&mut self, row: usize) -> usize {
-        if row >= self.pos_start.len() {
-            self.pos_start.resize(row + 1, Self::UNSET);
-        }
+        self.extend_rows(row + 1);
         let index = I::as_indextype(self.n_entries());
         // Check if the maximum number of entries has been reached - This should never happen
         assert_ne!(index, Self::UNSET);
@@ -71,21 +110,24 @@ where I: IndexType {
             // This is the first entry in this row
             self.pos_start[row] = index;
         } else {
-            // Iterate over the list of indices
-            let mut iter = self.pos_start[row].as_usize();
-            while self.index_list[iter] != Self::UNSET {
-                iter = self.index_list[iter].as_usize();
-            }
             // Append the new index to the last entry in the list
-            self.index_list[iter] = index;
+            let last = self.pos_end[row].as_usize();
+            self.index_list[last] = index;
         }
+        self.pos_end[row] = index;
         index.as_usize()
     }
 
-    pub(crate) fn iter_row(&self, row: usize) -> IterRow<I> {
+    // Returns an iterator over the indices of row or an empty one if the row does not exist
+    pub(crate) fn iter_row(&self, row: usize) -> IterRow<'_, I> {
+        let pos = if row < self.n_rows() {
+            self.pos_start[row]
+        } else {
+            Self::UNSET
+        };
         IterRow::<I> {
             list: self,
-            pos: self.pos_start[row],
+            pos,
         }
     }
 }
@@ -0,0 +1,256 @@
+use crate::sparsematrix::SparseMatError;
+
+// Decompression of raw deflate streams (RFC 1951) as used by zip archives
+// The stream consists of blocks which are either stored, or compressed with
+// the fixed or with dynamic Huffman codes given at the start of the block
+// A compressed block is a sequence of literal bytes and back references (length, distance)
+// into the output, which is terminated by the end of block symbol 256
+
+// Base values and number of extra bits of the length symbols 257..285
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+                                35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+                                3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+// Base values and number of extra bits of the distance symbols 0..29
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+                                  257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+                                  7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// Order in which the code lengths of the code length alphabet are stored in a dynamic block
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const MAX_CODE_LENGTH: usize = 15;
+
+fn inflate_error(what: &str) -> SparseMatError {
+    SparseMatError::new(&format!("Invalid deflate stream - {}", what))
+}
+
+// Reads the bits of the stream starting with the least significant bit of each byte
+struct BitReader<'d> {
+    data: &'d [u8],
+    pos: usize,
+    buf: u32,
+    n_bits: u32,
+}
+
+impl<'d> BitReader<'d> {
+    fn new(data: &'d [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buf: 0,
+            n_bits: 0,
+        }
+    }
+
+    // Returns the next n <= 16 bits as number with the first bit as least significant one
+    // Bytes are only loaded as needed, so less than 8 bits remain in the buffer afterwards
+    fn bits(&mut self, n: u32) -> Result<u32, SparseMatError> {
+        while self.n_bits < n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| inflate_error("unexpected end of data"))?;
+            self.buf |= (byte as u32) << self.n_bits;
+            self.n_bits += 8;
+            self.pos += 1;
+        }
+        let ret = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.n_bits -= n;
+        Ok(ret)
+    }
+
+    // Skips the remaining bits of the current byte
+    fn align(&mut self) {
+        self.buf = 0;
+        self.n_bits = 0;
+    }
+
+    // Returns the next n bytes after aligning to a byte boundary
+    fn bytes(&mut self, n: usize) -> Result<&'d [u8], SparseMatError> {
+        self.align();
+        let ret = self.data.get(self.pos..self.pos + n).ok_or_else(|| inflate_error("unexpected end of data"))?;
+        self.pos += n;
+        Ok(ret)
+    }
+}
+
+// Canonical Huffman code given by the code length of each symbol
+// Codes of the same length are consecutive numbers assigned in the order of the symbols,
+// and the first code of each length follows the last code of the previous length
+struct Huffman {
+    // Number of codes of each length
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    // Symbols sorted by their code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    // Builds the code from the code lengths, where symbols with length zero do not occur
+    // Codes using more than the available bit patterns are rejected, incomplete codes are allowed
+    fn new(lengths: &[u8]) -> Result<Self, SparseMatError> {
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        for &len in lengths.iter() {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut available = 1i32;
+        for &count in counts.iter().skip(1) {
+            available = 2 * available - count as i32;
+            if available < 0 {
+                return Err(inflate_error("over-subscribed Huffman code"));
+            }
+        }
+        // Position of the first symbol of each length in the sorted symbols
+        let mut next = [0usize; MAX_CODE_LENGTH + 2];
+        for len in 1..=MAX_CODE_LENGTH {
+            next[len + 1] = next[len] + counts[len] as usize;
+        }
+        let mut symbols = vec![0u16; next[MAX_CODE_LENGTH + 1]];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len > 0 {
+                symbols[next[len as usize]] = symbol as u16;
+                next[len as usize] += 1;
+            }
+        }
+        Ok(Self {
+            counts,
+            symbols,
+        })
+    }
+
+    // Decodes the next symbol reading the code bit by bit
+    // The code is compared to the range of codes with the current length
+    fn decode(&self, reader: &mut BitReader) -> Result<usize, SparseMatError> {
+        let mut code = 0usize;
+        let mut first_code = 0usize;
+        let mut first_symbol = 0usize;
+        for &count in self.counts.iter().skip(1) {
+            code |= reader.bits(1)? as usize;
+            let count = count as usize;
+            if code < first_code + count {
+                return Ok(self.symbols[first_symbol + code - first_code] as usize);
+            }
+            first_symbol += count;
+            first_code = (first_code + count) << 1;
+            code <<= 1;
+        }
+        Err(inflate_error("invalid Huffman code"))
+    }
+}
+
+// Returns the fixed codes for the literals and lengths and for the distances
+fn fixed_codes() -> Result<(Huffman, Huffman), SparseMatError> {
+    let mut lengths = [0u8; 288];
+    for (symbol, len) in lengths.iter_mut().enumerate() {
+        *len = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+// Reads the code lengths of a dynamic block, which are Huffman coded themselves
+// with run lengths for repeated lengths (16) and zeros (17, 18)
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), SparseMatError> {
+    let n_literals = reader.bits(5)? as usize + 257;
+    let n_distances = reader.bits(5)? as usize + 1;
+    let n_code_lengths = reader.bits(4)? as usize + 4;
+    if n_literals > 286 || n_distances > 30 {
+        return Err(inflate_error("too many codes in dynamic block"));
+    }
+    let mut code_lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(n_code_lengths) {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+    let mut lengths = Vec::<u8>::with_capacity(n_literals + n_distances);
+    while lengths.len() < n_literals + n_distances {
+        let (len, repeat) = match code_length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths.last().ok_or_else(|| inflate_error("repeated length without previous length"))?;
+                (prev, 3 + reader.bits(2)? as usize)
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if lengths.len() + repeat > n_literals + n_distances {
+            return Err(inflate_error("too many code lengths"));
+        }
+        lengths.resize(lengths.len() + repeat, len);
+    }
+    if lengths[256] == 0 {
+        return Err(inflate_error("missing end of block code"));
+    }
+    Ok((Huffman::new(&lengths[..n_literals])?, Huffman::new(&lengths[n_literals..])?))
+}
+
+// Decodes a compressed block appending to the output
+fn inflate_block(reader: &mut BitReader, literals: &Huffman, distances: &Huffman, out: &mut Vec<u8>, max_len: usize) -> Result<(), SparseMatError> {
+    loop {
+        let symbol = literals.decode(reader)?;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let k = symbol - 257;
+                let length = LENGTH_BASE[k] as usize + reader.bits(LENGTH_EXTRA[k] as u32)? as usize;
+                let k = distances.decode(reader)?;
+                if k >= DISTANCE_BASE.len() {
+                    return Err(inflate_error("invalid distance symbol"));
+                }
+                let distance = DISTANCE_BASE[k] as usize + reader.bits(DISTANCE_EXTRA[k] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(inflate_error("distance too far back"));
+                }
+                // The referenced bytes may overlap the copied ones, so they are copied one by one
+                let start = out.len() - distance;
+                for pos in start..start + length {
+                    out.push(out[pos]);
+                }
+            },
+            _ => return Err(inflate_error("invalid length symbol")),
+        }
+        if out.len() > max_len {
+            return Err(inflate_error("output exceeds the expected size"));
+        }
+    }
+}
+
+// Decompresses a raw deflate stream, failing if the output gets longer than max_len
+pub(crate) fn inflate(data: &[u8], max_len: usize) -> Result<Vec<u8>, SparseMatError> {
+    let mut reader = BitReader::new(data);
+    let mut out = Vec::<u8>::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                let header = reader.bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(inflate_error("corrupt length of stored block"));
+                }
+                if out.len() + len as usize > max_len {
+                    return Err(inflate_error("output exceeds the expected size"));
+                }
+                out.extend_from_slice(reader.bytes(len as usize)?);
+            },
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                inflate_block(&mut reader, &literals, &distances, &mut out, max_len)?;
+            },
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &literals, &distances, &mut out, max_len)?;
+            },
+            _ => return Err(inflate_error("invalid block type")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::types::{ValueType, FloatType};
+use crate::sparsematrix::*;
+use crate::vector::*;
+use crate::densevec::*;
+use crate::linearsolver::*;
+
+// LSQR method by Paige and Saunders for least-squares problems
+// Minimizes ||A * x - b||^2 + damp^2 * ||x||^2 for rectangular matrices
+pub struct Lsqr {
+    damp: f64,
+    tol: f64,
+    iter_max: usize,
+}
+
+// LSMR method by Fong and Saunders for least-squares problems
+// Minimizes the same functional as LSQR, but the norm of A^T * r decreases monotonically
+pub struct Lsmr {
+    damp: f64,
+    tol: f64,
+    iter_max: usize,
+}
+
+macro_rules! leastsquares_params {
+    ($Name: ident) => {
+        impl $Name {
+            pub fn with_damping(mut self, damp: f64) -> Self {
+                self.damp = damp;
+                self
+            }
+
+            // Sets the relative tolerance for the residual and the normal equations
+            pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+                self.tol = tol;
+                self.iter_max = iter_max;
+                self
+            }
+        }
+
+        impl Default for $Name {
+            fn default() -> Self {
+                Self {
+                    damp: 0.0,
+                    tol: 1e-10f64,
+                    iter_max: 10_000,
+                }
+            }
+        }
+    }
+}
+
+leastsquares_params!(Lsqr);
+leastsquares_params!(Lsmr);
+
+// Returns (c, s, r) of the plane rotation with [c s; -s c] * [a; b] = [r; 0]
+fn sym_ortho(a: f64, b: f64) -> (f64, f64, f64) {
+    if b == 0.0 {
+        (a.signum(), 0.0, a.abs())
+    } else if a == 0.0 {
+        (0.0, b.signum(), b.abs())
+    } else {
+        let r = f64::hypot(a, b);
+        (a / r, b / r, r)
+    }
+}
+
+fn check_dimensions_rect<'a, M>(mat: &'a M, b: &DenseVec<M::Value>, x: &DenseVec<M::Value>)
+where M: SparseMatrix<'a> {
+    if mat.n_rows() != b.dim()
+        || mat.n_cols() != x.dim() {
+        panic!("Matrix and vector size mismatch");
+    }
+}
+
+// Scales x by 1 / norm if the norm is positive and returns the norm
+fn normalize<T>(x: &mut DenseVec<T>) -> f64
+where T: FloatType {
+    let norm = x.norm();
+    if norm > 0.0 {
+        x.scale(T::from_f64(1.0 / norm));
+    }
+    norm
+}
+
+impl LinearSolver for Lsqr {
+    fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        check_dimensions_rect(mat, b, x);
+        let from = M::Value::from_f64;
+        let b_norm = b.norm();
+        // Golub-Kahan bidiagonalization starting with u = b - A * x
+        let mut u = b.clone() - mat.mvp(x);
+        let mut beta = normalize(&mut u);
+        let mut v = mat.mvp_transpose(&u);
+        let mut alpha = normalize(&mut v);
+        if alpha * beta == 0.0 {
+            return;
+        }
+        let mut w = v.clone();
+        let mut phi_bar = beta;
+        let mut rho_bar = alpha;
+        let mut a_norm_squared = 0.0;
+        let mut res_damp = 0.0;
+        for _k in 0..self.iter_max {
+            // u = A * v - alpha * u
+            let mut u_next = mat.mvp(&v);
+            axpy(&mut u_next, from(-alpha), &u);
+            u = u_next;
+            beta = normalize(&mut u);
+            a_norm_squared += alpha * alpha + beta * beta + self.damp * self.damp;
+            // v = A^T * u - beta * v
+            let mut v_next = mat.mvp_transpose(&u);
+            axpy(&mut v_next, from(-beta), &v);
+            v = v_next;
+            alpha = normalize(&mut v);
+            // Eliminate the damping parameter with a plane rotation
+            let rho_bar_damp = f64::hypot(rho_bar, self.damp);
+            let psi = self.damp / rho_bar_damp * phi_bar;
+            phi_bar *= rho_bar / rho_bar_damp;
+            res_damp += psi * psi;
+            // Eliminate the subdiagonal entry beta
+            let (c, s, rho) = sym_ortho(rho_bar_damp, beta);
+            let theta = s * alpha;
+            rho_bar = -c * alpha;
+            let phi = c * phi_bar;
+            phi_bar *= s;
+            // x = x + phi / rho * w, w = v - theta / rho * w
+            axpy(x, from(phi / rho), &w);
+            w.scale(from(-theta / rho));
+            w.add(&v);
+            // Estimates of ||r|| and ||A^T * r||
+            let r_norm = f64::sqrt(phi_bar * phi_bar + res_damp);
+            let ar_norm = alpha * (s * phi).abs();
+            if r_norm <= self.tol * b_norm
+                || ar_norm <= self.tol * f64::sqrt(a_norm_squared) * r_norm {
+                break;
+            }
+        }
+    }
+}
+
+impl LinearSolver for Lsmr {
+    fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        check_dimensions_rect(mat, b, x);
+        let from = M::Value::from_f64;
+        let b_norm = b.norm();
+        // Golub-Kahan bidiagonalization starting with u = b - A * x
+        let mut u = b.clone() - mat.mvp(x);
+        let mut beta = normalize(&mut u);
+        let mut v = mat.mvp_transpose(&u);
+        let mut alpha = normalize(&mut v);
+        if alpha * beta == 0.0 {
+            return;
+        }
+        let mut zeta_bar = alpha * beta;
+        let mut alpha_bar = alpha;
+        let mut rho = 1.0;
+        let mut rho_bar = 1.0;
+        let mut c_bar = 1.0;
+        let mut s_bar = 0.0;
+        let mut h = v.clone();
+        let mut h_bar = DenseVec::from_vec(vec![M::Value::zero(); x.dim()]);
+        // Variables for estimating ||r||
+        let mut beta_dd = beta;
+        let mut beta_d = 0.0;
+        let mut rho_d_old = 1.0;
+        let mut tau_tilde_old = 0.0;
+        let mut theta_tilde = 0.0;
+        let mut zeta = 0.0;
+        let mut d = 0.0;
+        let mut a_norm_squared = alpha * alpha;
+        for _k in 0..self.iter_max {
+            // u = A * v - alpha * u, v = A^T * u - beta * v
+            let mut u_next = mat.mvp(&v);
+            axpy(&mut u_next, from(-alpha), &u);
+            u = u_next;
+            beta = normalize(&mut u);
+            if beta > 0.0 {
+                let mut v_next = mat.mvp_transpose(&u);
+                axpy(&mut v_next, from(-beta), &v);
+                v = v_next;
+                alpha = normalize(&mut v);
+            }
+            // Rotation eliminating the damping parameter
+            let (c_hat, s_hat, alpha_hat) = sym_ortho(alpha_bar, self.damp);
+            // Rotation turning B into R
+            let rho_old = rho;
+            let (c, s, rho_new) = sym_ortho(alpha_hat, beta);
+            rho = rho_new;
+            let theta_new = s * alpha;
+            alpha_bar = c * alpha;
+            // Rotation turning R^T into R_bar
+            let rho_bar_old = rho_bar;
+            let zeta_old = zeta;
+            let theta_bar = s_bar * rho;
+            let (c_bar_new, s_bar_new, rho_bar_new) = sym_ortho(c_bar * rho, theta_new);
+            c_bar = c_bar_new;
+            s_bar = s_bar_new;
+            rho_bar = rho_bar_new;
+            zeta = c_bar * zeta_bar;
+            zeta_bar *= -s_bar;
+            // h_bar = h - theta_bar * rho / (rho_old * rho_bar_old) * h_bar
+            h_bar.scale(from(-theta_bar * rho / (rho_old * rho_bar_old)));
+            h_bar.add(&h);
+            // x = x + zeta / (rho * rho_bar) * h_bar
+            axpy(x, from(zeta / (rho * rho_bar)), &h_bar);
+            // h = v - theta_new / rho * h
+            h.scale(from(-theta_new / rho));
+            h.add(&v);
+            // Estimate ||r||
+            let beta_acute = c_hat * beta_dd;
+            let beta_check = -s_hat * beta_dd;
+            let beta_hat = c * beta_acute;
+            beta_dd = -s * beta_acute;
+            let theta_tilde_old = theta_tilde;
+            let (c_tilde_old, s_tilde_old, rho_tilde_old) = sym_ortho(rho_d_old, theta_bar);
+            theta_tilde = s_tilde_old * rho_bar;
+            rho_d_old = c_tilde_old * rho_bar;
+            beta_d = -s_tilde_old * beta_d + c_tilde_old * beta_hat;
+            tau_tilde_old = (zeta_old - theta_tilde_old * tau_tilde_old) / rho_tilde_old;
+            let tau_d = (zeta - theta_tilde * tau_tilde_old) / rho_d_old;
+            d += beta_check * beta_check;
+            let r_norm = f64::sqrt(d + (beta_d - tau_d) * (beta_d - tau_d) + beta_dd * beta_dd);
+            // Estimate ||A||
+            a_norm_squared += beta * beta;
+            let a_norm = f64::sqrt(a_norm_squared);
+            a_norm_squared += alpha * alpha;
+            // ||A^T * r|| = |zeta_bar|
+            if r_norm <= self.tol * b_norm
+                || zeta_bar.abs() <= self.tol * a_norm * r_norm {
+                break;
+            }
+        }
+    }
+}
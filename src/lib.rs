@@ -6,19 +6,48 @@ pub mod sparsemat_indexlist;
 pub mod sparsemat_crs;
 pub mod sparsemat_rowvec;
 pub mod sparsemat_par;
+pub mod sparsemat_dok;
+pub mod sparsemat_ell;
+pub mod sparsemat_sell;
+pub mod sparsemat_bsr;
+pub mod sparsemat_dia;
+pub mod sparsemat_sym;
+pub mod sparsemat_skyline;
 #[macro_use]
 pub mod vector;
 pub mod densevec;
+pub mod densemat;
 pub mod sparsevec;
 pub mod linearsolver;
+pub mod multigrid;
+pub mod eigensolver;
+pub mod leastsquares;
+pub mod ordering;
+pub mod binary;
+pub mod npz;
+mod inflate;
+pub mod harwellboeing;
+pub mod spyplot;
+pub mod graph;
 
 pub use self::sparsematrix::*;
+// IterCol of the index list and CRS share the name and stay reachable through their modules
+#[allow(ambiguous_glob_reexports)]
 pub use self::sparsemat_indexlist::*;
 pub use self::sparsemat_crs::*;
 pub use self::sparsemat_rowvec::*;
+pub use self::sparsemat_dok::*;
+pub use self::sparsemat_ell::*;
+pub use self::sparsemat_sell::*;
+pub use self::sparsemat_bsr::SparseMatBSR;
+pub use self::sparsemat_bsr::BlockJacobi;
+pub use self::sparsemat_dia::SparseMatDIA;
+pub use self::sparsemat_sym::SparseMatSym;
+pub use self::sparsemat_skyline::SparseMatSkyline;
 pub use self::sparsevec::*;
 pub use self::vector::*;
 pub use self::densevec::*;
+pub use self::densemat::*;
 
 #[cfg(test)]
 mod tests {
@@ -27,14 +56,28 @@ mod tests {
     use crate::sparsemat_crs::*;
     use crate::sparsemat_rowvec::*;
     use crate::sparsemat_par::*;
+    use crate::sparsemat_dok::*;
+    use crate::sparsemat_ell::*;
+    use crate::sparsemat_sell::*;
+    use crate::sparsemat_bsr::*;
+    use crate::sparsemat_dia::*;
+    use crate::sparsemat_sym::*;
+    use crate::sparsemat_skyline::*;
     use crate::indexlist::*;
     use crate::sparsevec::*;
     use crate::vector::*;
     use crate::densevec::*;
+    use crate::densemat::*;
     use crate::linearsolver::*;
+    use crate::multigrid::*;
+    use crate::eigensolver::*;
+    use crate::leastsquares::*;
+    use crate::ordering::*;
+    use crate::binary::*;
+    use crate::npz::*;
 
     #[test]
-    fn check_cg<'a>() {
+    fn check_cg() {
         let mut mat = SparseMatIndexList::<f64, u32>::new();
         mat.set(0, 0, 4.0);
         mat.set(0, 1, 1.0);
@@ -51,6 +94,241 @@ mod tests {
         assert_eq!((x.get(0) * 10000f64).floor() / 10000f64, 0.0909);
     }
 
+    #[test]
+    fn check_stationary_solvers() {
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        for i in 0..3 {
+            mat.set(i, i, 4.0);
+            if i > 0 {
+                mat.set(i, i - 1, 1.0);
+                mat.set(i - 1, i, 1.0);
+            }
+        }
+        let b = DenseVec::from_vec(vec![6.0, 12.0, 14.0]);
+        let check = |x: &DenseVec<f64>| {
+            for (i, val) in x.iter().enumerate() {
+                assert!((val - (i + 1) as f64).abs() < 1e-10);
+            }
+        };
+        let mut x = DenseVec::from_vec(vec![0.0; 3]);
+        Jacobi::new(0.8).solve(&mat, &b, &mut x);
+        check(&x);
+        for &direction in [SweepDirection::Forward, SweepDirection::Backward, SweepDirection::Symmetric].iter() {
+            let mut x = DenseVec::from_vec(vec![0.0; 3]);
+            GaussSeidel::new(direction).solve(&mat, &b, &mut x);
+            check(&x);
+        }
+        let mut x = DenseVec::from_vec(vec![0.0; 3]);
+        SuccessiveOverRelaxation::symmetric(1.2).solve(&mat, &b, &mut x);
+        check(&x);
+
+        // A fixed number of sweeps reduces the residual
+        let mut x = DenseVec::from_vec(vec![0.0; 3]);
+        GaussSeidel::default().smooth(&mat, &b, &mut x, 2);
+        let r = b.clone() - mat.mvp(&x);
+        assert!(r.norm() < 0.1 * b.norm());
+
+        // A reused solver picks up a diagonal changed in place
+        let smoother = Jacobi::new(0.8).with_tolerance(1e-12, 10_000);
+        let mut mat_scaled = mat.clone();
+        let mut x = DenseVec::from_vec(vec![0.0; 3]);
+        smoother.solve(&mat_scaled, &b, &mut x);
+        check(&x);
+        mat_scaled.scale(2.0);
+        let b_scaled = DenseVec::from_vec(vec![12.0, 24.0, 28.0]);
+        let mut x = DenseVec::from_vec(vec![0.0; 3]);
+        smoother.solve(&mat_scaled, &b_scaled, &mut x);
+        check(&x);
+        // The solvers hold no state and can be shared between threads
+        fn assert_send_sync<S: Send + Sync>(_solver: &S) {}
+        assert_send_sync(&smoother);
+        assert_send_sync(&SuccessiveOverRelaxation::symmetric(1.2));
+        assert_send_sync(&poisson_2d(3).amg_hierarchy(&AlgebraicMultigrid::default()));
+    }
+
+    // Assembles the 5-point stencil of the Poisson equation on a n x n grid
+    fn poisson_2d(n: usize) -> SparseMatCRS<f64, u32> {
+        let mut mat = SparseMatIndexList::<f64, u32>::with_capacity(5 * n * n);
+        for i in 0..n {
+            for j in 0..n {
+                let row = i * n + j;
+                mat.set(row, row, 4.0);
+                if i > 0 { mat.set(row, row - n, -1.0); }
+                if i + 1 < n { mat.set(row, row + n, -1.0); }
+                if j > 0 { mat.set(row, row - 1, -1.0); }
+                if j + 1 < n { mat.set(row, row + 1, -1.0); }
+            }
+        }
+        mat.to_crs()
+    }
+
+    #[test]
+    fn check_amg() {
+        let mat = poisson_2d(20);
+        let b = DenseVec::from_vec(vec![1.0; 400]);
+        let hierarchy = mat.amg_hierarchy(&AlgebraicMultigrid::default());
+        assert!(hierarchy.n_levels() > 1);
+        assert!(hierarchy.operator_complexity() < 2.0);
+
+        for &cycle in [Cycle::V, Cycle::W].iter() {
+            let mut x = DenseVec::from_vec(vec![0.0; 400]);
+            AlgebraicMultigrid::new(cycle).with_tolerance(1e-8, 50).solve(&mat, &b, &mut x);
+            assert!((b.clone() - mat.mvp(&x)).norm() < 1e-8);
+        }
+
+        // AMG as preconditioner for CG
+        let mut x = DenseVec::from_vec(vec![0.0; 400]);
+        ConjugateGradient::default().solve_preconditioned(&mat, &b, &mut x, &hierarchy);
+        assert!((b.clone() - mat.mvp(&x)).norm() < 1e-10);
+    }
+
+    #[test]
+    fn check_eigensolvers() {
+        // 1D Laplacian with eigenvalues 2 - 2 * cos(k * pi / (n + 1))
+        let n = 50;
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        for i in 0..n {
+            mat.set(i, i, 2.0);
+            if i > 0 {
+                mat.set(i, i - 1, -1.0);
+                mat.set(i - 1, i, -1.0);
+            }
+        }
+        let exact = |k: usize| 2.0 - 2.0 * f64::cos(k as f64 * std::f64::consts::PI / (n + 1) as f64);
+        let eigen = Lanczos::new(3, Spectrum::LargestReal).solve(&mat).unwrap();
+        for (k, pair) in eigen.iter().enumerate() {
+            assert!((pair.value - exact(n - k)).abs() < 1e-8);
+            assert!(pair.residual < 1e-6);
+        }
+        let eigen = Lanczos::new(2, Spectrum::SmallestReal).solve(&mat).unwrap();
+        assert!((eigen[0].value - exact(1)).abs() < 1e-8);
+
+        // Block diagonal matrix with the complex pair 5 +- 3i and real eigenvalues 0.1 * i
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        mat.set(0, 0, 5.0);
+        mat.set(0, 1, 3.0);
+        mat.set(1, 0, -3.0);
+        mat.set(1, 1, 5.0);
+        for i in 2..40 {
+            mat.set(i, i, 0.1 * i as f64);
+            mat.set(i - 1, i, 0.05);
+        }
+        let eigen = Arnoldi::new(3, Spectrum::LargestMagnitude).solve(&mat).unwrap();
+        assert!((eigen[0].re - 5.0).abs() < 1e-8 && (eigen[0].im - 3.0).abs() < 1e-8);
+        assert!((eigen[1].re - 5.0).abs() < 1e-8 && (eigen[1].im + 3.0).abs() < 1e-8);
+        assert!((eigen[2].re - 3.9).abs() < 1e-8 && eigen[2].im == 0.0);
+        assert!(eigen.iter().all(|pair| pair.residual < 1e-6));
+
+        // Unwanted complex pairs with a large imaginary part need double shifts in the restarts
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        for j in 0..20 {
+            mat.set(2 * j, 2 * j, 0.1 * j as f64);
+            mat.set(2 * j, 2 * j + 1, 5.0);
+            mat.set(2 * j + 1, 2 * j, -5.0);
+            mat.set(2 * j + 1, 2 * j + 1, 0.1 * j as f64);
+        }
+        mat.set(40, 40, 2.1);
+        mat.set(41, 41, 2.0);
+        mat.set(40, 41, 0.5);
+        for i in 0..41 {
+            mat.set(i, i + 1, mat.get(i, i + 1) + 0.1);
+        }
+        let eigen = Arnoldi::new(2, Spectrum::LargestReal).with_tolerance(1e-10, 20).solve(&mat).unwrap();
+        assert!((eigen[0].re - 2.1).abs() < 1e-8 && (eigen[1].re - 2.0).abs() < 1e-8);
+        assert!(eigen.iter().all(|pair| pair.im == 0.0 && pair.residual < 1e-6));
+    }
+
+    #[test]
+    fn check_spectral_estimates() {
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        for i in 0..10 {
+            mat.set(i, i, (i + 1) as f64);
+            if i > 0 {
+                mat.set(i, i - 1, 0.1);
+                mat.set(i - 1, i, 0.1);
+            }
+        }
+        let (lower, upper) = mat.gershgorin_bounds();
+        assert_eq!(mat.gershgorin_discs()[0], (1.0, 0.1));
+        assert!((lower - 0.9).abs() < 1e-12 && (upper - 10.1).abs() < 1e-12);
+
+        let largest = PowerIteration::default().solve(&mat).unwrap();
+        assert!(largest.residual < 1e-8);
+        assert!(largest.value > 10.0 && largest.value < upper);
+        let smallest = InverseIteration::new(0.5).solve(&mat, &ConjugateGradient::default()).unwrap();
+        assert!(smallest.residual < 1e-8);
+        assert!(smallest.value > lower && smallest.value < 1.0);
+        // The shifted systems are indefinite, so they are solved directly
+        let pair = RayleighQuotientIteration::default().with_tolerance(1e-14, 100).with_shift(5.2).solve(&mat, &DenseDirect).unwrap();
+        let mut res = mat.mvp(&pair.vector);
+        res -= pair.vector.clone() * pair.value;
+        assert!((pair.vector.norm() - 1.0).abs() < 1e-12);
+        // For a symmetric matrix the residual bounds the distance to the nearest eigenvalue
+        assert!(res.norm() < 1e-12 && (pair.residual - res.norm()).abs() < 1e-14);
+        assert!((pair.value - 5.0).abs() < 0.1);
+        let mut dense = mat.to_dense();
+        for i in 0..10 {
+            dense.add_to(i, i, -pair.value);
+        }
+        assert!(dense.lu().unwrap().determinant().abs() < 1e-10);
+        // A shift hitting an eigenvalue exactly makes the shifted matrix singular
+        let diag = SparseMatCRS::<f64, u32>::from_dense(&DenseMat::from_vec(3, 3, vec![1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 3.0]).unwrap(), 0.0);
+        let pair = RayleighQuotientIteration::default().with_tolerance(1e-14, 100).with_shift(2.0).solve(&diag, &DenseDirect).unwrap();
+        assert!((pair.value - 2.0).abs() < 1e-14 && pair.residual < 1e-14);
+        let mut singular = diag.clone();
+        singular.set(1, 1, 0.0);
+        let mut x = DenseVec::from_vec(vec![0.0; 3]);
+        assert!(DenseDirect.try_solve(&singular, &DenseVec::from_vec(vec![1.0; 3]), &mut x).is_err());
+        DenseDirect.solve(&singular, &DenseVec::from_vec(vec![1.0; 3]), &mut x);
+        assert_eq!(x.norm(), 0.0);
+    }
+
+    #[test]
+    fn check_leastsquares() {
+        // Fit a line through the points (0, 1), (1, 3), (2, 4), (3, 4)
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        for i in 0..4 {
+            mat.set(i, 0, 1.0);
+            if i > 0 {
+                mat.set(i, 1, i as f64);
+            }
+        }
+        let b = DenseVec::from_vec(vec![1.0, 3.0, 4.0, 4.0]);
+        let at_b = mat.mvp_transpose(&b);
+        assert_eq!(at_b.iter().collect::<Vec<f64>>(), vec![12.0, 23.0]);
+        let check = |x: &DenseVec<f64>, expected: &[f64]| {
+            for (val, exp) in x.iter().zip(expected.iter()) {
+                assert!((val - exp).abs() < 1e-8);
+            }
+        };
+        let mut x = DenseVec::from_vec(vec![0.0; 2]);
+        Lsqr::default().solve(&mat, &b, &mut x);
+        check(&x, &[1.5, 1.0]);
+        let mut x = DenseVec::from_vec(vec![0.0; 2]);
+        Lsmr::default().solve(&mat, &b, &mut x);
+        check(&x, &[1.5, 1.0]);
+
+        // Damped problem (A^T * A + I) * x = A^T * b
+        let mut x = DenseVec::from_vec(vec![0.0; 2]);
+        Lsqr::default().with_damping(1.0).solve(&mat, &b, &mut x);
+        check(&x, &[42.0 / 39.0, 43.0 / 39.0]);
+        let mut x = DenseVec::from_vec(vec![0.0; 2]);
+        Lsmr::default().with_damping(1.0).solve(&mat, &b, &mut x);
+        check(&x, &[42.0 / 39.0, 43.0 / 39.0]);
+
+        // Underdetermined system returns the minimum norm solution
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        mat.set(0, 0, 1.0);
+        mat.set(0, 1, 1.0);
+        let b = DenseVec::from_vec(vec![2.0]);
+        let mut x = DenseVec::from_vec(vec![0.0; 2]);
+        Lsqr::default().solve(&mat, &b, &mut x);
+        check(&x, &[1.0, 1.0]);
+        let mut x = DenseVec::from_vec(vec![0.0; 2]);
+        Lsmr::default().solve(&mat, &b, &mut x);
+        check(&x, &[1.0, 1.0]);
+    }
+
     #[test]
     fn check_sparsemat_indexlist() {
         //check_mat::<SparseMatIndexList<f32, u32>>();
@@ -108,7 +386,8 @@ mod tests {
 
         sp.sort();
         sp.sort_row(1);
-        sp.to_pbm("test.pbm".to_string());
+        let spy = sp.spy_plot(100).unwrap();
+        assert_eq!((spy.width(), spy.height()), (sp.n_cols(), sp.n_rows()));
     }
 
     #[test]
@@ -201,6 +480,860 @@ mod tests {
         assert_eq!(mat_par.density(), 6.0 / 9.0);
     }
 
+    #[test]
+    fn check_triangular_solve() {
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        mat.set(0, 0, 2.0);
+        mat.set(0, 2, 7.0);
+        mat.set(1, 0, 1.0);
+        mat.set(1, 1, 4.0);
+        mat.set(2, 1, 3.0);
+        mat.set(2, 2, 5.0);
+        let x = mat.solve_lower(&DenseVec::from_vec(vec![2.0, 9.0, 21.0])).unwrap();
+        assert_eq!(x.iter().collect::<Vec<f64>>(), vec![1.0, 2.0, 3.0]);
+        let x = mat.solve_upper(&DenseVec::from_vec(vec![23.0, 8.0, 15.0])).unwrap();
+        assert_eq!(x.iter().collect::<Vec<f64>>(), vec![1.0, 2.0, 3.0]);
+        let x = mat.solve_lower_transpose(&DenseVec::from_vec(vec![4.0, 17.0, 15.0])).unwrap();
+        assert_eq!(x.iter().collect::<Vec<f64>>(), vec![1.0, 2.0, 3.0]);
+        let x = mat.solve_lower_unit(&DenseVec::from_vec(vec![1.0, 3.0, 9.0])).unwrap();
+        assert_eq!(x.iter().collect::<Vec<f64>>(), vec![1.0, 2.0, 3.0]);
+
+        // Sparse right hand side only touches the reachable entries
+        let mut b = SparseVec::<f64, u32>::new();
+        b.set(1, 4.0);
+        assert!(mat.solve_lower_sparse(&b).is_err());
+        mat.assemble_column_info();
+        let x = mat.solve_lower_sparse(&b).unwrap();
+        let mut iter = x.iter_sparse();
+        assert_eq!(iter.next(), Some((&1, &1.0)));
+        assert_eq!(iter.next(), Some((&2, &-0.6)));
+        assert_eq!(iter.next(), None);
+
+        let mut singular = SparseMatCRS::<f64, u32>::new();
+        singular.set(0, 0, 1.0);
+        singular.set(1, 0, 1.0);
+        assert!(singular.solve_lower(&DenseVec::from_vec(vec![1.0, 1.0])).is_err());
+    }
+
+    #[test]
+    fn check_mvp_transpose() {
+        let mut mat = SparseMatIndexList::<f64, u32>::new();
+        mat.set(0, 1, 2.0);
+        mat.set(1, 0, 1.0);
+        mat.set(1, 2, 3.0);
+        mat.set(3, 3, 4.0);
+        let mut mat_par = SparseMatPar::<SparseMatIndexList::<f64, u32>>::with_sub_matrices(2, 4);
+        for (i, j, &val) in mat.iter() {
+            mat_par.set(i, j, val);
+        }
+        let mat_crs = mat.to_crs();
+        let x = DenseVec::from_vec(vec![1.0, 2.0, 3.0, 4.0]);
+        let expected = vec![2.0, 2.0, 6.0, 16.0];
+        assert_eq!(mat.mvp_transpose(&x).iter().collect::<Vec<f64>>(), expected);
+        assert_eq!(mat_crs.mvp_transpose(&x).iter().collect::<Vec<f64>>(), expected);
+        assert_eq!(mat_par.mvp_transpose(&x).iter().collect::<Vec<f64>>(), expected);
+        assert_eq!(mat_crs.mvp_transpose(&x).iter().collect::<Vec<f64>>(),
+                   mat_crs.transpose().mvp(&x).iter().collect::<Vec<f64>>());
+
+        // y = 2 * A * x - y
+        let expected = vec![7.0, 19.0, -1.0, 31.0];
+        let mut y = DenseVec::from_vec(vec![1.0; 4]);
+        mat.mvp_add(2.0, &x, -1.0, &mut y);
+        assert_eq!(y.iter().collect::<Vec<f64>>(), expected);
+        let mut y = DenseVec::from_vec(vec![1.0; 4]);
+        mat_crs.mvp_add(2.0, &x, -1.0, &mut y);
+        assert_eq!(y.iter().collect::<Vec<f64>>(), expected);
+        let mut y = DenseVec::from_vec(vec![1.0; 4]);
+        mat_par.mvp_add(2.0, &x, -1.0, &mut y);
+        assert_eq!(y.iter().collect::<Vec<f64>>(), expected);
+        // y = 2 * A * x ignores NaN in y
+        let expected = vec![8.0, 20.0, 0.0, 32.0];
+        let mut y = DenseVec::from_vec(vec![f64::NAN; 4]);
+        mat.mvp_add(2.0, &x, 0.0, &mut y);
+        assert_eq!(y.iter().collect::<Vec<f64>>(), expected);
+        let mut y = DenseVec::from_vec(vec![f64::NAN; 4]);
+        mat_crs.mvp_add(2.0, &x, 0.0, &mut y);
+        assert_eq!(y.iter().collect::<Vec<f64>>(), expected);
+        let mut y = DenseVec::from_vec(vec![f64::INFINITY; 4]);
+        mat_par.mvp_add(2.0, &x, 0.0, &mut y);
+        assert_eq!(y.iter().collect::<Vec<f64>>(), expected);
+    }
+
+    #[test]
+    fn check_transpose() {
+        let mut mat_il = SparseMatIndexList::<f64, u32>::new();
+        mat_il.add_to(2, 1, 4.0);
+        mat_il.add_to(0, 3, 1.0);
+        mat_il.add_to(1, 0, -2.0);
+        mat_il.add_to(0, 0, 3.0);
+        mat_il.add_to(2, 3, 5.0);
+        mat_il.add_to(3, 2, 7.0);
+        let mut mat_crs = SparseMatCRS::from_sparsemat(&mat_il);
+        let trans_il = mat_il.transpose();
+        let trans_crs = mat_crs.transpose();
+        for (i, j, val) in [(0, 0, 3.0), (3, 0, 1.0), (0, 1, -2.0), (1, 2, 4.0), (3, 2, 5.0), (2, 3, 7.0)] {
+            assert_eq!(trans_il.get(i, j), val);
+            assert_eq!(trans_crs.get(i, j), val);
+        }
+        assert_eq!(trans_crs.n_non_zero_entries(), 6);
+        assert_eq!(trans_crs.iter_row(3).map(|(&j, _)| j).collect::<Vec<u32>>(), vec![0, 2]);
+        mat_crs.transpose_in_place();
+        mat_il.transpose_in_place();
+        for i in 0..4 {
+            assert!(mat_crs.iter_row(i).eq(trans_crs.iter_row(i)));
+            assert!(mat_il.iter_row(i).eq(trans_crs.iter_row(i)));
+        }
+        mat_il.add_to(1, 3, 2.0);
+        assert_eq!(mat_il.get(1, 3), 2.0);
+        assert_eq!(mat_il.get(1, 2), 4.0);
+    }
+
+    #[test]
+    fn check_sorted_rows() {
+        let mut mat_crs = SparseMatCRS::<f64, u32>::new();
+        let mut mat_rv = SparseMatRowVec::<f64, u32>::new();
+        for (i, j, val) in [(0, 4, 1.0), (0, 1, 2.0), (1, 3, 3.0), (0, 2, 4.0), (1, 0, 5.0)] {
+            mat_crs.add_to(i, j, val);
+            mat_rv.add_to(i, j, val);
+        }
+        assert!(!mat_crs.rows_sorted());
+        assert!(!mat_rv.rows_sorted());
+        mat_crs.sort();
+        mat_rv.sort();
+        assert!(mat_crs.rows_sorted());
+        assert!(mat_rv.rows_sorted());
+        // New entries keep the rows sorted
+        for (i, j, val) in [(0, 3, 6.0), (0, 0, 7.0), (1, 5, 8.0), (3, 1, 9.0), (0, 4, 1.0)] {
+            mat_crs.add_to(i, j, val);
+            mat_rv.add_to(i, j, val);
+        }
+        assert!(mat_crs.is_sorted());
+        assert!(mat_rv.is_sorted());
+        let expected = vec![(0, 0, 7.0), (0, 1, 2.0), (0, 2, 4.0), (0, 3, 6.0), (0, 4, 2.0),
+                            (1, 0, 5.0), (1, 3, 3.0), (1, 5, 8.0), (3, 1, 9.0)];
+        assert_eq!(mat_crs.iter().map(|(i, j, &val)| (i, j, val)).collect::<Vec<_>>(), expected);
+        assert_eq!(mat_rv.iter().map(|(i, j, &val)| (i, j, val)).collect::<Vec<_>>(), expected);
+        assert_eq!(mat_crs.get(1, 4), 0.0);
+        assert_eq!(mat_rv.get(1, 5), 8.0);
+        assert!(mat_crs.transpose().rows_sorted());
+    }
+
+    #[test]
+    fn check_crs_slack() {
+        let mut mat = SparseMatCRS::<f64, u32>::new();
+        for i in 0..20 {
+            mat.add_to(i, i, 2.0);
+            mat.add_to(i, (i + 1) % 20, -1.0);
+        }
+        let mut expected = mat.clone();
+        mat.sort();
+        mat.reserve_slack(2);
+        assert!(mat.has_slack());
+        // Run rows full to trigger rebuilding the storage
+        for i in 0..20 {
+            for j in [(i + 5) % 20, (i + 9) % 20, (i + 13) % 20, (i + 7) % 20] {
+                mat.add_to(i, j, 0.5);
+                expected.add_to(i, j, 0.5);
+            }
+        }
+        mat.add_to(23, 4, 1.5);
+        expected.add_to(23, 4, 1.5);
+        assert!(mat.is_sorted());
+        assert_eq!(mat.n_rows(), 24);
+        assert_eq!(mat.n_non_zero_entries(), expected.n_non_zero_entries());
+        let x = DenseVec::from_vec((0..24).map(|k| k as f64).collect::<Vec<f64>>());
+        assert_eq!(mat.mvp(&x).iter().collect::<Vec<f64>>(), expected.mvp(&x).iter().collect::<Vec<f64>>());
+        assert_eq!(mat.transpose().get(4, 23), 1.5);
+        mat.finalize();
+        assert!(!mat.has_slack());
+        for i in 0..24 {
+            for j in 0..20 {
+                assert_eq!(mat.get(i, j), expected.get(i, j));
+            }
+        }
+        mat.assemble_column_info();
+        assert_eq!(mat.iter_col(4).unwrap().count(), 7);
+
+        // Many insertions into single rows only grow these rows
+        let mut mat = poisson_2d(5);
+        let mut expected = mat.clone();
+        mat.sort();
+        mat.reserve_slack(1);
+        for j in (0..300).rev() {
+            for &i in [7, 24].iter() {
+                mat.add_to(i, j, j as f64);
+                expected.add_to(i, j, j as f64);
+            }
+        }
+        assert!(mat.is_sorted());
+        assert_eq!(mat.n_non_zero_entries(), expected.n_non_zero_entries());
+        mat.finalize();
+        for i in 0..25 {
+            for j in 0..300 {
+                assert_eq!(mat.get(i, j), expected.get(i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn check_defragment() {
+        let mut mat = SparseMatIndexList::<f64, u32>::new();
+        for k in 0..30 {
+            mat.add_to((7 * k) % 5, (3 * k) % 11, k as f64);
+        }
+        let expected = mat.iter().map(|(i, j, &val)| (i, j, val)).collect::<Vec<_>>();
+        mat.assemble_column_info();
+        mat.defragment();
+        assert_eq!(mat.iter().map(|(i, j, &val)| (i, j, val)).collect::<Vec<_>>(), expected);
+        assert_eq!(mat.iter_col(0).unwrap().count(), 3);
+        mat.add_to(4, 2, 1.0);
+        assert_eq!(mat.get(4, 2), 1.0);
+        assert_eq!(mat.iter_row(4).last(), Some((&2, &1.0)));
+    }
+
+    #[test]
+    fn check_sparsemat_dok() {
+        let mut mat = SparseMatDOK::<f64, u32>::new();
+        for k in 0..50 {
+            mat.add_to((13 * k) % 7, (5 * k) % 9, 1.0);
+        }
+        assert_eq!(mat.n_rows(), 7);
+        assert_eq!(mat.n_cols(), 9);
+        assert_eq!(mat.n_non_zero_entries(), 50);
+        mat.add_to(3, 8, 2.0);
+        assert_eq!(mat.n_non_zero_entries(), 50);
+        assert_eq!(mat.get(3, 8), 3.0);
+        assert_eq!(mat.remove(3, 8), Some(3.0));
+        assert_eq!(mat.get(3, 8), 0.0);
+        assert_eq!(mat.n_non_zero_entries(), 49);
+        let mat_crs = mat.to_crs();
+        assert!(mat_crs.rows_sorted());
+        assert!(mat_crs.is_sorted());
+        assert_eq!(mat_crs.n_non_zero_entries(), 49);
+        for i in 0..7 {
+            for j in 0..9 {
+                assert_eq!(mat_crs.get(i, j), mat.get(i, j));
+            }
+        }
+        let x = DenseVec::from_vec((0..9).map(|k| k as f64).collect::<Vec<f64>>());
+        assert_eq!(mat.mvp(&x).iter().collect::<Vec<f64>>(), mat_crs.mvp(&x).iter().collect::<Vec<f64>>());
+    }
+
+    #[test]
+    fn check_ellpack() {
+        let mut mat = poisson_2d(5);
+        mat.add_to(3, 17, 0.5);
+        let mat_ell = SparseMatELL::from_crs(&mat);
+        let mat_sell = SparseMatSELL::from_crs(&mat, 4, 8);
+        assert_eq!(mat_ell.width(), 5);
+        assert_eq!(mat_ell.n_non_zero_entries(), mat.n_non_zero_entries());
+        assert_eq!(mat_sell.n_non_zero_entries(), mat.n_non_zero_entries());
+        assert!(mat_sell.padding_overhead() < mat_ell.padding_overhead());
+        let x = DenseVec::from_vec((0..25).map(|k| (k % 7) as f64).collect::<Vec<f64>>());
+        let y = mat.mvp(&x).iter().collect::<Vec<f64>>();
+        assert_eq!(mat_ell.mvp(&x).iter().collect::<Vec<f64>>(), y);
+        assert_eq!(mat_sell.mvp(&x).iter().collect::<Vec<f64>>(), y);
+        for i in 0..25 {
+            assert!(mat_ell.iter_row(i).eq(mat.iter_row(i)));
+            assert!(mat_sell.iter_row(i).eq(mat.iter_row(i)));
+        }
+        // Inserting entries with and without rebuilding the storage
+        let mut mat_ell = mat_ell;
+        let mut mat_sell = mat_sell;
+        for (i, j, val) in [(0, 24, 1.0), (3, 0, 2.0), (26, 1, 3.0)] {
+            mat.add_to(i, j, val);
+            mat_ell.add_to(i, j, val);
+            mat_sell.add_to(i, j, val);
+        }
+        let x = DenseVec::from_vec((0..25).map(|k| k as f64).collect::<Vec<f64>>());
+        let y = mat.mvp(&x).iter().collect::<Vec<f64>>();
+        assert_eq!(mat_ell.mvp(&x).iter().collect::<Vec<f64>>(), y);
+        assert_eq!(mat_sell.mvp(&x).iter().collect::<Vec<f64>>(), y);
+        assert_eq!(mat_sell.to_crs().get(26, 1), 3.0);
+    }
+
+    #[test]
+    fn check_sparsemat_bsr() {
+        // Two unknowns per node coupled on a chain of nodes
+        let n_nodes = 10;
+        let mut mat = SparseMatBSR::<f64, u32>::with_block_size(2);
+        for node in 0..n_nodes {
+            mat.get_block_mut(node, node).copy_from_slice(&[4.0, 1.0, 1.0, 3.0]);
+            if node + 1 < n_nodes {
+                mat.get_block_mut(node, node + 1).copy_from_slice(&[-1.0, 0.0, 0.0, -1.0]);
+                mat.get_block_mut(node + 1, node).copy_from_slice(&[-1.0, 0.0, 0.0, -1.0]);
+            }
+        }
+        assert_eq!(mat.n_rows(), 20);
+        assert_eq!(mat.n_blocks(), 28);
+        assert_eq!(mat.get(3, 2), 1.0);
+        assert_eq!(mat.get(3, 5), -1.0);
+        assert_eq!(mat.get(3, 9), 0.0);
+        assert!(mat.is_sorted());
+        let mat_crs = mat.to_crs();
+        assert_eq!(mat_crs.n_non_zero_entries(), 4 * n_nodes + 4 * (n_nodes - 1));
+        let mat_bsr = SparseMatBSR::from_crs(&mat_crs, 2);
+        assert_eq!(mat_bsr.n_blocks(), 28);
+        let x = DenseVec::from_vec((0..20).map(|k| k as f64).collect::<Vec<f64>>());
+        let y = mat_crs.mvp(&x).iter().collect::<Vec<f64>>();
+        assert_eq!(mat.mvp(&x).iter().collect::<Vec<f64>>(), y);
+        assert_eq!(mat_bsr.mvp(&x).iter().collect::<Vec<f64>>(), y);
+
+        // Preconditioned CG with the inverses of the diagonal blocks
+        let precond = BlockJacobi::new(&mat).unwrap();
+        let z = precond.apply(&DenseVec::from_vec(vec![5.0; 20]));
+        assert!((z.get(0) - 10.0 / 11.0).abs() < 1e-12);
+        assert!((z.get(1) - 15.0 / 11.0).abs() < 1e-12);
+        let b = DenseVec::from_vec(vec![1.0; 20]);
+        let mut x = DenseVec::from_vec(vec![0.0; 20]);
+        ConjugateGradient::default().solve_preconditioned(&mat, &b, &mut x, &precond);
+        let res = mat.mvp(&x).iter().zip(b.iter()).map(|(ax, b)| (ax - b).abs()).fold(0.0, f64::max);
+        assert!(res < 1e-8);
+        assert!(BlockJacobi::new(&SparseMatBSR::from_crs(&mat_crs, 4)).is_ok());
+        let mut singular = mat.clone();
+        singular.get_block_mut(4, 4).copy_from_slice(&[1.0, 2.0, 2.0, 4.0]);
+        assert!(BlockJacobi::new(&singular).is_err());
+
+        // Dimensions which are not multiples of the block size pad the edge blocks
+        let mat_crs = poisson_2d(3);
+        let mat = SparseMatBSR::from_crs(&mat_crs, 2);
+        assert_eq!((mat.n_rows(), mat.n_cols()), (9, 9));
+        assert_eq!((mat.n_block_rows(), mat.n_block_cols()), (5, 5));
+        assert_eq!(mat.iter_row(8).count(), 5);
+        let x = DenseVec::from_vec((0..9).map(|k| k as f64).collect::<Vec<f64>>());
+        assert_eq!(mat.mvp(&x).iter().collect::<Vec<f64>>(), mat_crs.mvp(&x).iter().collect::<Vec<f64>>());
+        assert_eq!(mat.to_crs().n_non_zero_entries(), mat_crs.n_non_zero_entries());
+        assert!(mat.n_non_zero_entries() >= mat_crs.n_non_zero_entries());
+        assert_eq!(mat.n_non_zero_entries(), (0..9).map(|i| mat.iter_row(i).count()).sum::<usize>());
+        let precond = BlockJacobi::new(&mat).unwrap();
+        assert_eq!(precond.apply(&DenseVec::from_vec(vec![4.0; 9])).get(8), 1.0);
+        let b = DenseVec::from_vec(vec![1.0; 9]);
+        let mut x = DenseVec::from_vec(vec![0.0; 9]);
+        ConjugateGradient::default().solve_preconditioned(&mat, &b, &mut x, &precond);
+        let res = mat.mvp(&x).iter().zip(b.iter()).map(|(ax, b)| (ax - b).abs()).fold(0.0, f64::max);
+        assert!(res < 1e-8);
+        let mut mat = SparseMatBSR::<f64, u32>::with_block_size(2);
+        mat.set(4, 2, 1.0);
+        assert_eq!((mat.n_rows(), mat.n_cols()), (5, 3));
+    }
+
+    #[test]
+    fn check_sparsemat_dia() {
+        let mut mat = poisson_2d(6);
+        mat.sort();
+        assert!(SparseMatDIA::is_beneficial(&mat));
+        let mut mat_dia = SparseMatDIA::from_crs(&mat);
+        assert_eq!(mat_dia.offsets(), &[-6, -1, 0, 1, 6]);
+        for i in 0..36 {
+            assert!(mat_dia.iter_row(i).filter(|(_col, &val)| val != 0.0).eq(mat.iter_row(i)));
+        }
+        let x = DenseVec::from_vec((0..36).map(|k| (k % 5) as f64).collect::<Vec<f64>>());
+        assert_eq!(mat_dia.mvp(&x).iter().collect::<Vec<f64>>(), mat.mvp(&x).iter().collect::<Vec<f64>>());
+        assert_eq!(mat_dia.to_crs().n_non_zero_entries(), mat.n_non_zero_entries());
+
+        // Inserting a new diagonal and a new row
+        mat_dia.add_to(2, 30, 1.5);
+        mat_dia.add_to(36, 35, -2.0);
+        assert_eq!(mat_dia.n_rows(), 37);
+        assert_eq!(mat_dia.offsets().len(), 6);
+        assert_eq!(mat_dia.get(2, 30), 1.5);
+        assert_eq!(mat_dia.get(36, 35), -2.0);
+        assert_eq!(mat_dia.get(35, 35), 4.0);
+
+        let mut random = SparseMatIndexList::<f64, u32>::new();
+        for k in 0..40 {
+            random.add_to((7 * k) % 40, (13 * k + 5) % 40, 1.0);
+        }
+        assert!(!SparseMatDIA::is_beneficial(&random.to_crs()));
+
+        let empty = SparseMatCRS::<f64, u32>::new();
+        assert!(SparseMatDIA::diagonal_offsets(&empty).is_empty());
+        let mat_dia = SparseMatDIA::from_crs(&empty);
+        assert_eq!((mat_dia.n_rows(), mat_dia.n_non_zero_entries()), (0, 0));
+        assert_eq!(mat_dia.to_crs().n_rows(), 0);
+    }
+
+    #[test]
+    fn check_sparsemat_sym() {
+        let mat = poisson_2d(8);
+        let mut mat_sym = SparseMatSym::from_upper(&mat);
+        assert_eq!(mat_sym.n_rows(), 64);
+        assert_eq!(mat_sym.n_non_zero_entries(), mat.n_non_zero_entries());
+        assert_eq!(mat_sym.n_stored_entries(), (mat.n_non_zero_entries() + 64) / 2);
+        assert!(mat_sym.is_symmetric());
+        for i in 0..64 {
+            for j in 0..64 {
+                assert_eq!(mat_sym.get(i, j), mat.get(i, j));
+            }
+            let mut row = mat_sym.iter_row(i).map(|(&j, &val)| (j, val)).collect::<Vec<(u32, f64)>>();
+            let mut expected = mat.iter_row(i).map(|(&j, &val)| (j, val)).collect::<Vec<(u32, f64)>>();
+            row.sort_by_key(|&(j, _val)| j);
+            expected.sort_by_key(|&(j, _val)| j);
+            assert_eq!(row, expected);
+        }
+        let x = DenseVec::from_vec((0..64).map(|k| (k % 9) as f64).collect::<Vec<f64>>());
+        assert_eq!(mat_sym.mvp(&x).iter().collect::<Vec<f64>>(), mat.mvp(&x).iter().collect::<Vec<f64>>());
+
+        // CG works directly on the symmetric storage
+        let b = DenseVec::from_vec(vec![1.0; 64]);
+        let mut x_sym = DenseVec::from_vec(vec![0.0; 64]);
+        let mut x_crs = DenseVec::from_vec(vec![0.0; 64]);
+        ConjugateGradient::default().solve(&mat_sym, &b, &mut x_sym);
+        ConjugateGradient::default().solve(&mat, &b, &mut x_crs);
+        assert!(x_sym.iter().zip(x_crs.iter()).all(|(x1, x2)| (x1 - x2).abs() < 1e-10));
+        let res = mat_sym.mvp(&x_sym).iter().zip(b.iter()).map(|(ax, b)| (ax - b).abs()).fold(0.0, f64::max);
+        assert!(res < 1e-10);
+
+        // The LDL^T factorization of the skyline storage solves the system directly
+        let mut skyline = mat_sym.to_skyline();
+        assert_eq!(skyline.profile(), mat.profile());
+        skyline.factorize().unwrap();
+        let x_ldl = skyline.solve(&b).unwrap();
+        assert!(x_ldl.iter().zip(x_sym.iter()).all(|(x1, x2)| (x1 - x2).abs() < 1e-10));
+
+        // Setting an entry below the diagonal changes its mirror as well
+        mat_sym.set(10, 3, 0.5);
+        assert_eq!(mat_sym.get(3, 10), 0.5);
+        assert_eq!(mat_sym.to_crs().get(10, 3), 0.5);
+        mat_sym.set(70, 2, 1.5);
+        assert_eq!(mat_sym.n_rows(), 71);
+        assert_eq!(mat_sym.iter_row(70).map(|(&j, &val)| (j, val)).collect::<Vec<(u32, f64)>>(), vec![(2, 1.5)]);
+        assert_eq!(mat_sym.iter_row(3).map(|(&j, &val)| (j, val)).collect::<Vec<(u32, f64)>>(),
+                   vec![(2, -1.0), (3, 4.0), (4, -1.0), (10, 0.5), (11, -1.0)]);
+    }
+
+    #[test]
+    fn check_skyline() {
+        // Poisson problem with a random numbering of the unknowns
+        let n = 10;
+        let perm_random = (0..n * n).map(|k| (37 * k + 11) % (n * n)).collect::<Vec<usize>>();
+        let mat = poisson_2d(n).permute(&perm_random);
+        let perm = mat.rcm_ordering();
+        assert_eq!(inverse_permutation(&perm).len(), n * n);
+        let mat_rcm = mat.permute(&perm);
+        assert!(mat_rcm.profile() < mat.profile() / 2);
+        assert!(mat_rcm.bandwidth() < mat.bandwidth());
+        let empty = SparseMatCRS::<f64, u32>::new();
+        assert!(empty.rcm_ordering().is_empty());
+        assert_eq!((empty.bandwidth(), empty.profile()), (0, 0));
+
+        let mut skyline = SparseMatSkyline::from_sparsemat(&mat_rcm);
+        assert_eq!(skyline.profile(), mat_rcm.profile());
+        for i in 0..n * n {
+            for j in 0..n * n {
+                assert_eq!(skyline.get(i, j), mat_rcm.get(i, j));
+            }
+        }
+        let x = DenseVec::from_vec((0..n * n).map(|k| (k % 4) as f64).collect::<Vec<f64>>());
+        let y = mat_rcm.mvp(&x);
+        let y_skyline = skyline.mvp(&x);
+        assert!(y.iter().zip(y_skyline.iter()).all(|(y1, y2)| y1 == y2));
+
+        // Solve the original system with the reordered factorization
+        let b = DenseVec::from_vec((0..n * n).map(|k| 1.0 + (k % 3) as f64).collect::<Vec<f64>>());
+        assert!(skyline.solve(&b).is_err());
+        skyline.factorize().unwrap();
+        assert!(skyline.is_factorized());
+        let x = skyline.solve(&b.permute(&perm)).unwrap().permute_inverse(&perm);
+        let res = mat.mvp(&x).iter().zip(b.iter()).map(|(ax, b)| (ax - b).abs()).fold(0.0, f64::max);
+        assert!(res < 1e-10);
+        // The factorization is kept apart from the matrix and dropped when the matrix changes
+        assert_eq!(skyline.get(5, 5), mat_rcm.get(5, 5));
+        assert!(skyline.mvp(&x).iter().zip(mat_rcm.mvp(&x).iter()).all(|(y1, y2)| y1 == y2));
+        skyline.add_to(5, 5, 1.0);
+        assert!(!skyline.is_factorized());
+        assert!(skyline.solve(&b).is_err());
+        skyline.factorize().unwrap();
+        let mut mat_changed = mat_rcm.clone();
+        mat_changed.add_to(5, 5, 1.0);
+        let x = skyline.solve(&b).unwrap();
+        let res = mat_changed.mvp(&x).iter().zip(b.iter()).map(|(ax, b)| (ax - b).abs()).fold(0.0, f64::max);
+        assert!(res < 1e-10);
+        skyline.scale(2.0);
+        assert!(!skyline.is_factorized());
+
+        // Assembly extending the profile
+        let mut skyline = SparseMatSkyline::<f64, u32>::new();
+        skyline.set(0, 0, 2.0);
+        skyline.set(2, 2, 2.0);
+        skyline.set(1, 1, 2.0);
+        skyline.set(0, 2, 1.0);
+        assert_eq!(skyline.profile(), 2);
+        assert_eq!(skyline.get(2, 0), 1.0);
+        assert_eq!(skyline.iter_row(0).map(|(&j, &val)| (j, val)).collect::<Vec<(u32, f64)>>(), vec![(0, 2.0), (2, 1.0)]);
+        skyline.set(1, 1, 0.0);
+        assert!(skyline.factorize().is_err());
+    }
+
+    #[test]
+    fn check_binary() {
+        let mut mat = poisson_2d(4);
+        mat.reserve_slack(1);
+        mat.add_to(2, 15, 0.25);
+        let mut buf = Vec::<u8>::new();
+        mat.write_binary(&mut buf).unwrap();
+        assert_eq!(buf.len(), 40 + 17 * 4 + mat.n_non_zero_entries() * 12);
+        let mat_read = SparseMatCRS::<f64, u32>::read_binary(&mut buf.as_slice()).unwrap();
+        assert_eq!(mat_read.n_rows(), 16);
+        assert_eq!(mat_read.n_non_zero_entries(), mat.n_non_zero_entries());
+        for i in 0..16 {
+            assert!(mat_read.iter_row(i).eq(mat.iter_row(i)));
+        }
+        // Type mismatch, truncation and corrupt data
+        let err = SparseMatCRS::<f32, u32>::read_binary(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "Value type mismatch - file has f64, expected f32");
+        assert!(SparseMatCRS::<f64, u64>::read_binary(&mut buf.as_slice()).is_err());
+        assert!(SparseMatCRS::<f64, u32>::read_binary(&mut &buf[..buf.len() - 1]).is_err());
+        assert!(SparseMatCRS::<f64, u32>::read_binary(&mut &buf[..20]).is_err());
+        let mut corrupt = buf.clone();
+        corrupt[40 + 17 * 4] = 200;
+        assert!(SparseMatCRS::<f64, u32>::read_binary(&mut corrupt.as_slice()).is_err());
+        corrupt[0] = b'X';
+        assert!(SparseMatCRS::<f64, u32>::read_binary(&mut corrupt.as_slice()).is_err());
+        assert_eq!(u32::TYPE_CODE, 0x14);
+
+        // Arrays larger than the chunks of the writer
+        let mat = poisson_2d(60);
+        let mut buf = Vec::<u8>::new();
+        mat.write_binary(&mut buf).unwrap();
+        assert_eq!(buf.len(), 40 + 3601 * 4 + mat.n_non_zero_entries() * 12);
+        let mat_read = SparseMatCRS::<f64, u32>::read_binary(&mut buf.as_slice()).unwrap();
+        assert!((0..3600).all(|i| mat_read.iter_row(i).eq(mat.iter_row(i))));
+    }
+
+    #[test]
+    fn check_npz() {
+        let mut mat = poisson_2d(3);
+        mat.add_to(0, 8, 0.5);
+        for format in [NpzFormat::CSR, NpzFormat::CSC, NpzFormat::COO].iter() {
+            let mut buf = Vec::<u8>::new();
+            mat.write_npz(&mut buf, *format).unwrap();
+            assert_eq!(&buf[0..4], b"PK\x03\x04");
+            let mat_read = SparseMatCRS::<f64, u32>::read_npz(&mut buf.as_slice()).unwrap();
+            assert_eq!(mat_read.n_rows(), 9);
+            assert_eq!(mat_read.n_cols(), 9);
+            for (i, j, val) in mat.iter() {
+                assert_eq!(mat_read.get(i, j), *val);
+            }
+            assert_eq!(mat_read.n_non_zero_entries(), mat.n_non_zero_entries());
+        }
+        // Other index and value types
+        let mut mat_f32 = SparseMatCRS::<f32, u64>::new();
+        mat_f32.set(1, 3, 2.5);
+        mat_f32.set(4, 0, -1.0);
+        let mut buf = Vec::<u8>::new();
+        mat_f32.write_npz(&mut buf, NpzFormat::CSR).unwrap();
+        let mat_read = SparseMatCRS::<f32, u64>::read_npz(&mut buf.as_slice()).unwrap();
+        assert_eq!(mat_read.get(1, 3), 2.5);
+        assert_eq!(mat_read.get(4, 0), -1.0);
+        let err = SparseMatCRS::<f64, u64>::read_npz(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "Type mismatch of data - file has f32, expected f64");
+        // Deflated entries - the archive is rebuilt with each entry wrapped in a stored deflate block
+        let u16_at = |b: &[u8], pos: usize| u16::from_le_bytes([b[pos], b[pos + 1]]) as usize;
+        let u32_at = |b: &[u8], pos: usize| u32::from_le_bytes([b[pos], b[pos + 1], b[pos + 2], b[pos + 3]]) as usize;
+        let end = buf.len() - 22;
+        let mut central = u32_at(&buf, end + 16);
+        let mut deflated = Vec::<u8>::new();
+        let mut directory = Vec::<u8>::new();
+        for _k in 0..u16_at(&buf, end + 10) {
+            let header_len = 46 + u16_at(&buf, central + 28) + u16_at(&buf, central + 30) + u16_at(&buf, central + 32);
+            let mut header = buf[central..central + header_len].to_vec();
+            let offset = u32_at(&header, 42);
+            let size = u32_at(&header, 20);
+            let start = offset + 30 + u16_at(&buf, offset + 26) + u16_at(&buf, offset + 28);
+            let mut local = buf[offset..start].to_vec();
+            let len = (size as u16).to_le_bytes();
+            let stream = [&[1, len[0], len[1], !len[0], !len[1]], &buf[start..start + size]].concat();
+            for (bytes, pos) in [(&mut header, 10), (&mut local, 8)].iter_mut() {
+                bytes[*pos] = 8;
+                bytes[*pos + 10..*pos + 14].copy_from_slice(&(stream.len() as u32).to_le_bytes());
+            }
+            header[42..46].copy_from_slice(&(deflated.len() as u32).to_le_bytes());
+            deflated.extend_from_slice(&local);
+            deflated.extend_from_slice(&stream);
+            directory.extend_from_slice(&header);
+            central += header_len;
+        }
+        let mut end_record = buf[end..].to_vec();
+        end_record[12..16].copy_from_slice(&(directory.len() as u32).to_le_bytes());
+        end_record[16..20].copy_from_slice(&(deflated.len() as u32).to_le_bytes());
+        deflated.extend_from_slice(&directory);
+        deflated.extend_from_slice(&end_record);
+        let mat_read = SparseMatCRS::<f32, u64>::read_npz(&mut deflated.as_slice()).unwrap();
+        assert_eq!(mat_read.get(1, 3), 2.5);
+        assert_eq!(mat_read.get(4, 0), -1.0);
+        // Fixed and dynamic Huffman blocks created with zlib
+        let fixed = [75, 76, 74, 78, 68, 69, 10, 25, 169, 57, 57, 249, 0];
+        assert_eq!(crate::inflate::inflate(&fixed, 100).unwrap(), b"abcabcabcabcabcabc hello");
+        let dynamic = [29, 140, 201, 13, 4, 65, 8, 196, 18, 242, 163, 185, 33, 255, 196, 182, 118, 36, 132, 56, 236, 122, 24, 201, 97,
+                       141, 23, 209, 228, 209, 201, 26, 129, 39, 57, 140, 227, 132, 209, 206, 137, 8, 230, 97, 78, 5, 39, 90, 174, 2,
+                       52, 39, 245, 88, 186, 240, 97, 151, 74, 220, 89, 165, 41, 170, 48, 99, 155, 150, 177, 132, 99, 34, 185, 96,
+                       139, 57, 70, 61, 190, 250, 86, 29, 245, 234, 63, 36, 84, 130, 52, 201, 63];
+        let text = (0..60).map(|i| format!("{},", i * i % 97)).collect::<String>();
+        assert_eq!(crate::inflate::inflate(&dynamic, 1000).unwrap(), text.as_bytes());
+        assert!(crate::inflate::inflate(&dynamic, 100).is_err());
+        assert!(crate::inflate::inflate(&dynamic[..40], 1000).is_err());
+        let mut corrupt = buf.clone();
+        corrupt[100] ^= 0xff;
+        assert!(SparseMatCRS::<f32, u64>::read_npz(&mut corrupt.as_slice()).is_err());
+        assert!(SparseMatCRS::<f32, u64>::read_npz(&mut &buf[..buf.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn check_harwell_boeing() {
+        // Symmetric matrix [4 1 0; 1 4 2; 0 2 5] with the lower triangle stored by columns
+        let rsa = format!("{:<72}{:<8}\n{:>14}{:>14}{:>14}{:>14}{:>14}\n{:<3}{:11}{:>14}{:>14}{:>14}{:>14}\n{:<16}{:<16}{:<20}{:<20}\n{}",
+                          "Test matrix", "TEST", 6, 2, 1, 3, 0, "RSA", "", 3, 3, 5, 0, "(3I5)", "(5I5)", "(2D16.8)", "",
+                          "    1    3    5\n    6\n    1    2    2    3    3\n  0.40000000D+01  1.00000000D+00\n  4.00000000D+00     2.000000+00\n       5.0000000\n");
+        let mat = SparseMatCRS::<f64, u32>::read_harwell_boeing(&mut rsa.as_bytes()).unwrap();
+        assert_eq!(mat.n_rows(), 3);
+        assert_eq!(mat.n_non_zero_entries(), 7);
+        assert!(mat.is_symmetric());
+        assert!(mat.rows_sorted());
+        assert_eq!(mat.get(0, 0), 4.0);
+        assert_eq!(mat.get(1, 0), 1.0);
+        assert_eq!(mat.get(1, 2), 2.0);
+        assert_eq!(mat.get(2, 2), 5.0);
+        assert_eq!(mat.get(0, 2), 0.0);
+        // Rutherford-Boeing pattern matrix with 2 rows and 3 columns
+        let rb = format!("{:<72}{:<8}\n{:>14}{:>14}{:>14}{:>14}\n{:<3}{:11}{:>14}{:>14}{:>14}{:>14}\n{:<16}{:<16}\n{}",
+                         "Pattern", "PAT", 2, 1, 1, 0, "pra", "", 2, 3, 3, 0, "(4I3)", "(3I3)",
+                         "  1  2  3  4\n  2  1  2\n");
+        let mat_rb = SparseMatCRS::<f32, u32>::read_harwell_boeing(&mut rb.as_bytes()).unwrap();
+        assert_eq!(mat_rb.n_rows(), 2);
+        assert_eq!(mat_rb.n_cols(), 3);
+        assert_eq!(mat_rb.get(1, 0), 1.0);
+        assert_eq!(mat_rb.get(0, 1), 1.0);
+        assert_eq!(mat_rb.get(1, 2), 1.0);
+        assert_eq!(mat_rb.n_non_zero_entries(), 3);
+        // Malformed input
+        let err = SparseMatCRS::<f64, u32>::read_harwell_boeing(&mut rsa.replace("RSA", "CSA").as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Complex Harwell-Boeing matrices are not supported");
+        let err = SparseMatCRS::<f64, u32>::read_harwell_boeing(&mut rsa.replace("(3I5)", "(3X5)").as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid Harwell-Boeing header - unsupported Fortran format (3X5)");
+        assert!(SparseMatCRS::<f64, u32>::read_harwell_boeing(&mut rsa.replace("    3    3\n", "    3    4\n").as_bytes()).is_err());
+        assert!(SparseMatCRS::<f64, u32>::read_harwell_boeing(&mut &rsa.as_bytes()[..200]).is_err());
+        // Header fields are fixed-width, so a count filling all 14 columns is not merged with the next one
+        let wide = rsa.replacen("             6", "99999999999999", 1);
+        assert_eq!(SparseMatCRS::<f64, u32>::read_harwell_boeing(&mut wide.as_bytes()).unwrap().n_non_zero_entries(), 7);
+        // A huge number of entries is rejected by the section lengths instead of being allocated
+        let huge = rsa.replace("             5             0", "99999999999999             0");
+        let err = SparseMatCRS::<f64, u32>::read_harwell_boeing(&mut huge.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid Harwell-Boeing file - expected 99999999999999 row indices, found 5");
+    }
+
+    #[test]
+    fn check_spy_plot() {
+        let mut mat = poisson_2d(10);
+        mat.set(0, 99, 1000.0);
+        let spy = mat.spy_plot(16).unwrap();
+        assert_eq!(spy.bin_size(), 7);
+        assert_eq!((spy.width(), spy.height()), (15, 15));
+        assert_eq!(spy.count(0, 0), 7 + 2 * 6);
+        assert_eq!(spy.count(14, 0), 1);
+        assert_eq!(spy.count(14, 1), 0);
+        let mut pgm = Vec::<u8>::new();
+        spy.write_pgm(&mut pgm).unwrap();
+        assert!(pgm.starts_with(b"P5\n15 15\n255\n"));
+        assert_eq!(pgm.len(), 13 + 15 * 15);
+        assert_eq!(pgm[13], 0);
+        assert_eq!(pgm[13 + 15 + 14], 255);
+        let mut ppm = Vec::<u8>::new();
+        spy.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm.len(), 13 + 3 * 15 * 15);
+        // The largest magnitude is mapped to red
+        assert_eq!(&ppm[13 + 3 * 14..13 + 3 * 15], &[179, 0, 0]);
+        let mut svg = Vec::<u8>::new();
+        spy.write_svg(&mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("100 x 100, nnz = 461"));
+        assert!(mat.spy_plot(0).is_err());
+        assert!(SparseMatCRS::<f64, u32>::new().spy_plot(16).is_err());
+    }
+
+    #[test]
+    fn check_graph() {
+        // Symmetric matrices are written as undirected graph
+        let mut dot = Vec::<u8>::new();
+        poisson_2d(2).write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("graph {\n  0;\n"));
+        assert!(dot.contains("  0 -- 1 [label=\"-1\"];\n"));
+        assert!(!dot.contains("  1 -- 0"));
+        assert_eq!(dot.matches("--").count(), 4 + 4);
+        // Edge list with comments, weights and repeated edges
+        let edges = "# dependencies\n1 2\n2 3 0.5\n\n% comment\n3 1 2\n2 3 0.25\n";
+        let mat = SparseMatIndexList::<f64, u32>::read_edge_list(&mut edges.as_bytes(), true).unwrap();
+        assert_eq!(mat.n_rows(), 3);
+        assert_eq!(mat.n_non_zero_entries(), 3);
+        assert_eq!(mat.get(0, 1), 1.0);
+        assert_eq!(mat.get(1, 2), 0.75);
+        assert_eq!(mat.get(2, 0), 2.0);
+        let mut dot = Vec::<u8>::new();
+        mat.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("  1 -> 2 [label=\"0.75\"];\n"));
+        let mut buf = Vec::<u8>::new();
+        mat.write_edge_list(&mut buf, false).unwrap();
+        assert_eq!(String::from_utf8(buf.clone()).unwrap(), "0 1 1\n1 2 0.75\n2 0 2\n");
+        let mat_read = SparseMatIndexList::<f64, u32>::read_edge_list(&mut buf.as_slice(), false).unwrap();
+        assert!(mat_read.iter().eq(mat.iter()));
+        let err = SparseMatIndexList::<f64, u32>::read_edge_list(&mut "0 1\n".as_bytes(), true).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid edge list in line 1 - invalid node 0");
+        assert!(SparseMatIndexList::<f64, u32>::read_edge_list(&mut "1 2 x\n".as_bytes(), true).is_err());
+        assert!(SparseMatIndexList::<f64, u32>::read_edge_list(&mut "1\n".as_bytes(), false).is_err());
+    }
+
+    #[test]
+    fn check_densemat() {
+        // Conversions
+        let mat = poisson_2d(3);
+        let dense = mat.to_dense();
+        assert_eq!((dense.n_rows(), dense.n_cols()), (9, 9));
+        assert_eq!(dense.get(4, 1), -1.0);
+        assert_eq!(dense.get(4, 0), 0.0);
+        let mat_sparse = SparseMatCRS::<f64, u32>::from_dense(&dense, 0.0);
+        assert_eq!(mat_sparse.n_non_zero_entries(), mat.n_non_zero_entries());
+        assert_eq!(mat_sparse.to_dense(), dense);
+        assert_eq!(SparseMatIndexList::<f64, u32>::from_dense(&dense, 1.5).n_non_zero_entries(), 9);
+        // Trailing zero rows and columns are kept
+        let padded = DenseMat::<f64>::from_vec(3, 4, vec![1.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]).unwrap();
+        let mat_padded = SparseMatCRS::<f64, u32>::from_dense(&padded, 0.0);
+        assert_eq!((mat_padded.n_rows(), mat_padded.n_cols()), (3, 4));
+        assert_eq!(mat_padded.n_non_zero_entries(), 2);
+        assert_eq!(mat_padded.to_dense(), padded);
+        fn round_trip<M>(dense: &DenseMat<f64>) -> bool
+        where M: for<'a> SparseMatrix<'a, Value = f64> {
+            M::from_dense(dense, 0.0).to_dense() == *dense
+        }
+        assert!(round_trip::<SparseMatIndexList<f64, u32>>(&padded));
+        assert!(round_trip::<SparseMatRowVec<f64, u32>>(&padded));
+        assert!(round_trip::<SparseMatDOK<f64, u32>>(&padded));
+        assert!(round_trip::<SparseMatELL<f64, u32>>(&padded));
+        assert!(round_trip::<SparseMatSELL<f64, u32>>(&padded));
+        assert!(round_trip::<SparseMatDIA<f64, u32>>(&padded));
+        assert!(round_trip::<SparseMatBSR<f64, u32>>(&padded));
+        let padded_sym = DenseMat::<f64>::from_vec(3, 3, vec![1.0, 2.0, 0.0, 2.0, 3.0, 0.0, 0.0, 0.0, 0.0]).unwrap();
+        assert!(round_trip::<SparseMatSym<f64, u32>>(&padded_sym));
+        assert!(round_trip::<SparseMatSkyline<f64, u32>>(&padded_sym));
+        // NaN is reported by the LU decomposition instead of panicking
+        let nan = DenseMat::<f64>::from_vec(2, 2, vec![1.0, 2.0, f64::NAN, 1.0]).unwrap();
+        assert_eq!(nan.lu().unwrap_err().to_string(), "Matrix contains NaN in column 0");
+        // Matrix products against the sparse mvp
+        let block = DenseMat::<f64>::from_vec(9, 2, (0..18).map(|k| (k % 5) as f64 - 1.5).collect()).unwrap();
+        let spmm = mat.prod_dense(&block).unwrap();
+        let dense_prod = dense.prod(&block).unwrap();
+        assert_eq!(spmm.max_abs_diff(&dense_prod), 0.0);
+        for j in 0..2 {
+            let col = mat.mvp(&block.col(j));
+            assert!((0..9).all(|i| spmm.get(i, j) == col.get(i)));
+        }
+        assert!(mat.prod_dense(&spmm.transpose()).is_err());
+        assert!(DenseMat::<f64>::from_vec(2, 2, vec![1.0; 3]).is_err());
+        // LU with pivoting
+        let a = DenseMat::<f64>::from_vec(3, 3, vec![0.0, 2.0, 1.0, 1.0, 1.0, 0.0, 3.0, 0.0, 1.0]).unwrap();
+        let lu = a.lu().unwrap();
+        assert!((lu.determinant() - -5.0).abs() < 1e-12);
+        let mut pa = DenseMat::<f64>::zeros(3, 3);
+        for (i, &p) in lu.permutation().iter().enumerate() {
+            pa.row_mut(i).copy_from_slice(a.row(p));
+        }
+        assert!(lu.l().prod(&lu.u()).unwrap().max_abs_diff(&pa) < 1e-12);
+        let x = lu.solve(&DenseVec::from_vec(vec![3.0, 2.0, 4.0])).unwrap();
+        assert!(x.iter().all(|val| (val - 1.0).abs() < 1e-12));
+        let singular = DenseMat::<f64>::from_vec(2, 2, vec![1.0, 2.0, 2.0, 4.0]).unwrap();
+        assert!(singular.lu().is_err());
+        // QR and least squares fit of a line through (0, 1), (1, 3), (2, 5), (3, 7.5)
+        let a = DenseMat::<f64>::from_vec(4, 2, vec![1.0, 0.0, 1.0, 1.0, 1.0, 2.0, 1.0, 3.0]).unwrap();
+        let qr = a.qr().unwrap();
+        assert!(qr.q().prod(qr.r()).unwrap().max_abs_diff(&a) < 1e-12);
+        assert!(qr.q().transpose().prod(qr.q()).unwrap().max_abs_diff(&DenseMat::eye(2)) < 1e-12);
+        assert_eq!(qr.r().get(1, 0), 0.0);
+        let x = qr.solve(&DenseVec::from_vec(vec![1.0, 3.0, 5.0, 7.5])).unwrap();
+        assert!((x.get(0) - 0.9).abs() < 1e-12);
+        assert!((x.get(1) - 2.15).abs() < 1e-12);
+        assert!(a.transpose().qr().is_err());
+    }
+
+    #[test]
+    fn check_mvp_multi() {
+        let mut mat = poisson_2d(4);
+        mat.add_to(3, 12, 0.5);
+        let mat_il = SparseMatIndexList::<f64, u32>::from_dense(&mat.to_dense(), 0.0);
+        for k in 0..10 {
+            let block = DenseMat::<f64>::from_vec(16, k, (0..16 * k).map(|n| ((7 * n) % 11) as f64 - 5.0).collect()).unwrap();
+            // Specialized CRS kernel against the default implementation and single products
+            let ret = mat.mvp_multi(&block).unwrap();
+            assert_eq!(ret, mat_il.mvp_multi(&block).unwrap());
+            assert_eq!(ret, mat.prod_dense(&block).unwrap());
+            for j in 0..k {
+                assert_eq!(ret.col(j).iter_ref().as_slice(), mat.mvp(&block.col(j)).iter_ref().as_slice());
+            }
+        }
+        // Slack mode
+        let block = DenseMat::<f64>::from_vec(16, 3, (0..48).map(|n| n as f64).collect()).unwrap();
+        let ret = mat.mvp_multi(&block).unwrap();
+        mat.reserve_slack(2);
+        assert_eq!(mat.mvp_multi(&block).unwrap(), ret);
+        assert!(mat.mvp_multi(&DenseMat::<f64>::zeros(15, 3)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_serde() {
+        let mut mat_il = SparseMatIndexList::<f64, u32>::new();
+        for k in 0..20 {
+            mat_il.add_to((3 * k) % 7, (5 * k) % 6, k as f64);
+        }
+        let json = serde_json::to_string(&mat_il).unwrap();
+        let mat_il_read: SparseMatIndexList<f64, u32> = serde_json::from_str(&json).unwrap();
+        let mut mat_crs = mat_il.to_crs();
+        mat_crs.reserve_slack(2);
+        let json_crs = serde_json::to_string(&mat_crs).unwrap();
+        assert_eq!(json, json_crs);
+        let mat_crs_read: SparseMatCRS<f64, u32> = serde_json::from_str(&json).unwrap();
+        let mut mat_rv = SparseMatRowVec::<f64, u32>::new();
+        for (i, j, &val) in mat_il.iter() {
+            mat_rv.set(i, j, val);
+        }
+        let mat_rv_read: SparseMatRowVec<f64, u32> = serde_json::from_str(&serde_json::to_string(&mat_rv).unwrap()).unwrap();
+        for i in 0..7 {
+            assert!(mat_il_read.iter_row(i).eq(mat_il.iter_row(i)));
+            assert!(mat_crs_read.iter_row(i).eq(mat_il.iter_row(i)));
+            assert!(mat_rv_read.iter_row(i).eq(mat_il.iter_row(i)));
+        }
+        let mut mat_par = SparseMatPar::<SparseMatCRS<f64, u32>>::with_sub_matrices(2, 8);
+        mat_par.add_to(5, 1, 2.0);
+        let mat_par_read: SparseMatPar<SparseMatCRS<f64, u32>> = serde_json::from_str(&serde_json::to_string(&mat_par).unwrap()).unwrap();
+        assert_eq!(mat_par_read.get(5, 1), 2.0);
+        let dense = DenseVec::from_vec(vec![1.0, 2.0]);
+        let dense_read: DenseVec<f64> = serde_json::from_str(&serde_json::to_string(&dense).unwrap()).unwrap();
+        assert_eq!(dense_read.get(1), 2.0);
+        let sparse = SparseVec::<f64, u32>::from_parts(vec![3], vec![1.5], 5);
+        let sparse_read: SparseVec<f64, u32> = serde_json::from_str(&serde_json::to_string(&sparse).unwrap()).unwrap();
+        assert_eq!(sparse_read.get(3), 1.5);
+
+        // Corrupt input is rejected
+        let corrupt = r#"{"n_rows":2,"n_cols":2,"offset_rows":[0,1,2],"columns":[0,5],"values":[1.0,2.0]}"#;
+        assert!(serde_json::from_str::<SparseMatCRS<f64, u32>>(corrupt).is_err());
+        assert!(serde_json::from_str::<SparseMatIndexList<f64, u32>>(corrupt).is_err());
+        let corrupt = r#"{"n_rows":2,"n_cols":2,"offset_rows":[0,2,1],"columns":[0,1],"values":[1.0,2.0]}"#;
+        assert!(serde_json::from_str::<SparseMatCRS<f64, u32>>(corrupt).is_err());
+        let corrupt = r#"{"n_cols":2,"columns":[[0,1]],"values":[[1.0]]}"#;
+        assert!(serde_json::from_str::<SparseMatRowVec<f64, u32>>(corrupt).is_err());
+        let corrupt = r#"{"n_rows_sub_matrix":0,"n_blocks":0,"sub_matrices":[]}"#;
+        assert!(serde_json::from_str::<SparseMatPar<SparseMatCRS<f64, u32>>>(corrupt).is_err());
+        let mut mat_par = SparseMatPar::<SparseMatCRS<f64, u32>>::with_sub_matrices(2, 8);
+        mat_par.add_to(7, 1, 2.0);
+        let corrupt = serde_json::to_string(&mat_par).unwrap().replace("\"n_rows_sub_matrix\":4", "\"n_rows_sub_matrix\":2");
+        let err = serde_json::from_str::<SparseMatPar<SparseMatCRS<f64, u32>>>(&corrupt).unwrap_err();
+        assert!(err.to_string().starts_with("Sub matrix 1 has more rows than a block"));
+        let corrupt = r#"{"values":[1.0],"indices":[7],"dim":5}"#;
+        assert!(serde_json::from_str::<SparseVec<f64, u32>>(corrupt).is_err());
+    }
+
     #[test]
     fn check_indexlist() {
         let mut list = IndexList::<u16>::new();
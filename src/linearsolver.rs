@@ -7,6 +7,21 @@ pub trait LinearSolver {
     fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
     where M: SparseMatrix<'a>,
           M::Value: FloatType;
+
+    // Solves the system and reports failures like a singular matrix
+    // Solvers which cannot detect a failure always succeed
+    fn try_solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>) -> Result<(), SparseMatError>
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        self.solve(mat, b, x);
+        Ok(())
+    }
+}
+
+// Interface for preconditioners approximating the inverse of a matrix
+pub trait Preconditioner<T> {
+    // Returns z = P^-1 * r
+    fn apply(&self, r: &DenseVec<T>) -> DenseVec<T>;
 }
 
 pub struct ConjugateGradient {
@@ -23,6 +38,44 @@ impl Default for ConjugateGradient {
     }
 }
 
+impl ConjugateGradient {
+    // Solves the system with the preconditioned conjugate gradient method
+    // The preconditioner has to be symmetric and positive definite
+    pub fn solve_preconditioned<'a, M, P>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>, precond: &P)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType,
+          P: Preconditioner<M::Value> {
+        check_dimensions(mat, b, x);
+        // r = b - M * x
+        let mut r = b.clone() - mat.mvp(x);
+        // z = P^-1 * r
+        let mut z = precond.apply(&r);
+        let mut p = z.clone();
+        let mut r_z = r.inner_prod(&z);
+        for _k in 0..self.iter_max {
+            // M * p
+            let mat_p = mat.mvp(&p);
+            // alpha = r * z / (p * M * p)
+            let alpha = r_z / p.inner_prod(&mat_p);
+            // x = x + alpha * p
+            *x += p.clone() * alpha;
+            // r = r - alpha * (M * p)
+            r -= mat_p * alpha;
+            if r.norm() < self.tol {
+                break;
+            }
+            z = precond.apply(&r);
+            let r_z_prev = r_z;
+            r_z = r.inner_prod(&z);
+            // beta = r * z / (r_prev * z_prev)
+            let beta = r_z / r_z_prev;
+            // p = z + beta * p
+            p.scale(beta);
+            p.add(&z);
+        }
+    }
+}
+
 impl LinearSolver for ConjugateGradient {
     fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
     where M: SparseMatrix<'a>,
@@ -60,3 +113,306 @@ impl LinearSolver for ConjugateGradient {
         }
     }
 }
+
+// Direct solver converting the matrix to a dense matrix and solving with its LU decomposition
+// Valid for indefinite and nonsymmetric systems, but only intended for small matrices
+// A singular matrix leaves x unchanged with solve and is reported by try_solve
+#[derive(Default)]
+pub struct DenseDirect;
+
+impl LinearSolver for DenseDirect {
+    fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        check_dimensions(mat, b, x);
+        let _ = self.try_solve(mat, b, x);
+    }
+
+    fn try_solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>) -> Result<(), SparseMatError>
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        if mat.n_rows() != b.dim() || mat.n_rows() != x.dim() {
+            return Err(SparseMatError::new("Matrix and vector size mismatch"));
+        }
+        *x = mat.to_dense().lu()?.solve(b)?;
+        Ok(())
+    }
+}
+
+// Interface for stationary methods applying a fixed number of sweeps to x
+// Used for smoothing in multigrid methods
+pub trait Smoother {
+    // Sweeps with the inverse diagonal of the matrix computed by the caller,
+    // so repeated smoothing like in multigrid cycles extracts it only once
+    fn smooth_with_diagonal<'a, M>(&self, mat: &'a M, inv_diag: &[M::Value], b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>, n_sweeps: usize)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType;
+
+    fn smooth<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>, n_sweeps: usize)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        self.smooth_with_diagonal(mat, &inverse_diagonal(mat), b, x, n_sweeps);
+    }
+}
+
+// Order of the rows visited in a Gauss-Seidel or SOR sweep
+// A symmetric sweep is a forward sweep followed by a backward sweep
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SweepDirection {
+    Forward,
+    Backward,
+    Symmetric,
+}
+
+// Returns the diagonal of the matrix and panics if an entry is zero
+fn diagonal<'a, M>(mat: &'a M) -> Vec<M::Value>
+where M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    let mut diag = vec![M::Value::zero(); mat.n_rows()];
+    for (i, d) in diag.iter_mut().enumerate() {
+        for (&col, &val) in mat.iter_row(i) {
+            if col.as_usize() == i {
+                *d = val;
+            }
+        }
+        if *d == M::Value::zero() {
+            panic!("Zero diagonal entry in row {}", i);
+        }
+    }
+    diag
+}
+
+// Returns the inverse of the diagonal for the smoothers and panics if an entry is zero
+pub fn inverse_diagonal<'a, M>(mat: &'a M) -> Vec<M::Value>
+where M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    diagonal(mat).into_iter().map(|d| M::Value::one() / d).collect()
+}
+
+// Returns b_i - sum_{j != i} a_ij * x_j
+fn off_diagonal_residual<'a, M>(mat: &'a M, b: &DenseVec<M::Value>, x: &DenseVec<M::Value>, i: usize) -> M::Value
+where M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    let mut sum = b.get(i);
+    for (&col, &val) in mat.iter_row(i) {
+        let j = col.as_usize();
+        if j != i {
+            sum -= val * x.get(j);
+        }
+    }
+    sum
+}
+
+// Relaxes the rows in the given order updating x in place
+fn sor_sweep<'a, M>(mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>, inv_diag: &[M::Value], omega: M::Value, direction: SweepDirection)
+where M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    let mut relax = |i: usize| {
+        let sum = off_diagonal_residual(mat, b, x, i);
+        let x_i = x.get(i);
+        x.set(i, x_i + omega * (sum * inv_diag[i] - x_i));
+    };
+    match direction {
+        SweepDirection::Forward => (0..mat.n_rows()).for_each(&mut relax),
+        SweepDirection::Backward => (0..mat.n_rows()).rev().for_each(&mut relax),
+        SweepDirection::Symmetric => {
+            (0..mat.n_rows()).for_each(&mut relax);
+            (0..mat.n_rows()).rev().for_each(&mut relax);
+        },
+    }
+}
+
+pub(crate) fn check_dimensions<'a, M>(mat: &'a M, b: &DenseVec<M::Value>, x: &DenseVec<M::Value>)
+where M: SparseMatrix<'a> {
+    if mat.n_rows() != mat.n_cols() {
+        panic!("Matrix is not square");
+    }
+    if mat.n_rows() != b.dim()
+        || mat.n_rows() != x.dim() {
+        panic!("Matrix and vector size mismatch");
+    }
+}
+
+// Applies single sweeps of the smoother until the residual norm drops below tol
+// The diagonal is extracted once for all sweeps
+fn solve_stationary<'a, S, M>(smoother: &S, tol: f64, iter_max: usize, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+where S: Smoother,
+      M: SparseMatrix<'a>,
+      M::Value: FloatType {
+    check_dimensions(mat, b, x);
+    let inv_diag = inverse_diagonal(mat);
+    for _k in 0..iter_max {
+        smoother.smooth_with_diagonal(mat, &inv_diag, b, x, 1);
+        // r = b - M * x
+        let r = b.clone() - mat.mvp(x);
+        if r.norm() < tol {
+            break;
+        }
+    }
+}
+
+// Damped Jacobi method: x = x + omega * D^-1 * (b - M * x)
+pub struct Jacobi {
+    omega: f64,
+    tol: f64,
+    iter_max: usize,
+}
+
+impl Jacobi {
+    pub fn new(omega: f64) -> Self {
+        Self {
+            omega,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+        self.tol = tol;
+        self.iter_max = iter_max;
+        self
+    }
+}
+
+impl Default for Jacobi {
+    fn default() -> Self {
+        Self {
+            omega: 1.0,
+            tol: 1e-12f64,
+            iter_max: 10_000,
+        }
+    }
+}
+
+impl Smoother for Jacobi {
+    fn smooth_with_diagonal<'a, M>(&self, mat: &'a M, inv_diag: &[M::Value], b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>, n_sweeps: usize)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        let omega = M::Value::from_f64(self.omega);
+        for _s in 0..n_sweeps {
+            let x_prev = x.clone();
+            for (i, &inv_d) in inv_diag.iter().enumerate() {
+                let sum = off_diagonal_residual(mat, b, &x_prev, i);
+                let x_i = x_prev.get(i);
+                x.set(i, x_i + omega * (sum * inv_d - x_i));
+            }
+        }
+    }
+}
+
+impl LinearSolver for Jacobi {
+    fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        solve_stationary(self, self.tol, self.iter_max, mat, b, x);
+    }
+}
+
+// Gauss-Seidel method updating the entries of x in place
+pub struct GaussSeidel {
+    direction: SweepDirection,
+    tol: f64,
+    iter_max: usize,
+}
+
+impl GaussSeidel {
+    pub fn new(direction: SweepDirection) -> Self {
+        Self {
+            direction,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+        self.tol = tol;
+        self.iter_max = iter_max;
+        self
+    }
+}
+
+impl Default for GaussSeidel {
+    fn default() -> Self {
+        Self {
+            direction: SweepDirection::Forward,
+            tol: 1e-12f64,
+            iter_max: 10_000,
+        }
+    }
+}
+
+impl Smoother for GaussSeidel {
+    fn smooth_with_diagonal<'a, M>(&self, mat: &'a M, inv_diag: &[M::Value], b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>, n_sweeps: usize)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        for _s in 0..n_sweeps {
+            sor_sweep(mat, b, x, inv_diag, M::Value::one(), self.direction);
+        }
+    }
+}
+
+impl LinearSolver for GaussSeidel {
+    fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        solve_stationary(self, self.tol, self.iter_max, mat, b, x);
+    }
+}
+
+// Successive over-relaxation with relaxation factor omega in (0, 2)
+// Using symmetric sweeps results in the SSOR method
+pub struct SuccessiveOverRelaxation {
+    omega: f64,
+    direction: SweepDirection,
+    tol: f64,
+    iter_max: usize,
+}
+
+impl SuccessiveOverRelaxation {
+    pub fn new(omega: f64, direction: SweepDirection) -> Self {
+        Self {
+            omega,
+            direction,
+            ..Self::default()
+        }
+    }
+
+    // Returns the symmetric variant SSOR
+    pub fn symmetric(omega: f64) -> Self {
+        Self::new(omega, SweepDirection::Symmetric)
+    }
+
+    pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+        self.tol = tol;
+        self.iter_max = iter_max;
+        self
+    }
+}
+
+impl Default for SuccessiveOverRelaxation {
+    fn default() -> Self {
+        Self {
+            omega: 1.5,
+            direction: SweepDirection::Forward,
+            tol: 1e-12f64,
+            iter_max: 10_000,
+        }
+    }
+}
+
+impl Smoother for SuccessiveOverRelaxation {
+    fn smooth_with_diagonal<'a, M>(&self, mat: &'a M, inv_diag: &[M::Value], b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>, n_sweeps: usize)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        let omega = M::Value::from_f64(self.omega);
+        for _s in 0..n_sweeps {
+            sor_sweep(mat, b, x, inv_diag, omega, self.direction);
+        }
+    }
+}
+
+impl LinearSolver for SuccessiveOverRelaxation {
+    fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        solve_stationary(self, self.tol, self.iter_max, mat, b, x);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::types::{IndexType, FloatType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::*;
+use crate::vector::*;
+use crate::densevec::*;
+use crate::linearsolver::*;
+
+// Type of the multigrid cycle
+// The V-cycle visits each coarse level once, the W-cycle twice
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cycle {
+    V,
+    W,
+}
+
+// Parameters of the algebraic multigrid method based on smoothed aggregation
+// Used for building the hierarchy and as a linear solver
+pub struct AlgebraicMultigrid {
+    // Threshold for the strength of connection
+    theta: f64,
+    // Damping factor for smoothing the prolongation
+    omega: f64,
+    max_levels: usize,
+    coarse_size: usize,
+    cycle: Cycle,
+    // Number of pre- and post-smoothing sweeps
+    n_sweeps: usize,
+    tol: f64,
+    iter_max: usize,
+}
+
+impl AlgebraicMultigrid {
+    pub fn new(cycle: Cycle) -> Self {
+        Self {
+            cycle,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_strength(mut self, theta: f64) -> Self {
+        self.theta = theta;
+        self
+    }
+
+    pub fn with_levels(mut self, max_levels: usize, coarse_size: usize) -> Self {
+        self.max_levels = max_levels;
+        self.coarse_size = coarse_size;
+        self
+    }
+
+    pub fn with_sweeps(mut self, n_sweeps: usize) -> Self {
+        self.n_sweeps = n_sweeps;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tol: f64, iter_max: usize) -> Self {
+        self.tol = tol;
+        self.iter_max = iter_max;
+        self
+    }
+}
+
+impl Default for AlgebraicMultigrid {
+    fn default() -> Self {
+        Self {
+            theta: 0.08,
+            omega: 4.0 / 3.0,
+            max_levels: 10,
+            coarse_size: 50,
+            cycle: Cycle::V,
+            n_sweeps: 1,
+            tol: 1e-12f64,
+            iter_max: 100,
+        }
+    }
+}
+
+// The multigrid hierarchy from the finest to the coarsest level
+pub struct AmgHierarchy<T, I> {
+    matrices: Vec<SparseMatCRS<T, I>>,
+    prolongations: Vec<SparseMatCRS<T, I>>,
+    restrictions: Vec<SparseMatCRS<T, I>>,
+    // LU factorization of the coarsest matrix stored densely
+    coarse_lu: Vec<T>,
+    coarse_perm: Vec<usize>,
+    // Inverse diagonal of each level except the coarsest one for the smoothers
+    inv_diagonals: Vec<Vec<T>>,
+    cycle: Cycle,
+    n_sweeps: usize,
+}
+
+// Marks nodes which do not belong to an aggregate yet
+const UNAGGREGATED: usize = usize::MAX;
+
+impl<T, I> SparseMatCRS<T, I>
+where T: FloatType,
+      I: IndexType {
+    // Returns the strongly connected neighbours of each row
+    // Entry j is strong for row i if |a_ij| >= theta * sqrt(|a_ii * a_jj|)
+    fn strong_connections(&self, theta: f64) -> Vec<Vec<usize>> {
+        let diag = (0..self.n_rows()).map(|i| self.get(i, i).abs()).collect::<Vec<T>>();
+        let theta = T::from_f64(theta);
+        (0..self.n_rows()).map(|i| {
+            self.iter_row(i)
+                .map(|(&col, &val)| (col.as_usize(), val))
+                .filter(|&(j, val)| j != i && val.abs() >= theta * (diag[i] * diag[j]).sqrt())
+                .map(|(j, _val)| j)
+                .collect()
+        }).collect()
+    }
+
+    // Builds the multigrid hierarchy with smoothed aggregation
+    // Coarse operators are computed with the Galerkin product R * A * P
+    pub fn amg_hierarchy(&self, amg: &AlgebraicMultigrid) -> AmgHierarchy<T, I> {
+        let mut matrices = vec![self.clone()];
+        let mut prolongations = Vec::<SparseMatCRS<T, I>>::new();
+        let mut restrictions = Vec::<SparseMatCRS<T, I>>::new();
+        while matrices.len() < amg.max_levels {
+            let mat = &matrices[matrices.len() - 1];
+            if mat.n_rows() <= amg.coarse_size {
+                break;
+            }
+            let strong = mat.strong_connections(amg.theta);
+            let (aggregates, n_aggregates) = aggregate(&strong);
+            if n_aggregates == mat.n_rows() || n_aggregates == 0 {
+                break;
+            }
+            let tentative = tentative_prolongation::<T, I>(&aggregates, n_aggregates);
+            let prolongation = mat.smooth_prolongation(&tentative, amg.omega);
+            let restriction = prolongation.transpose();
+            let mat_coarse = restriction.spgemm(&mat.spgemm(&prolongation).unwrap()).unwrap();
+            prolongations.push(prolongation);
+            restrictions.push(restriction);
+            matrices.push(mat_coarse);
+        }
+        let (coarse_lu, coarse_perm) = dense_lu(&matrices[matrices.len() - 1]);
+        let inv_diagonals = matrices[..matrices.len() - 1].iter().map(inverse_diagonal).collect();
+        AmgHierarchy::<T, I> {
+            matrices,
+            prolongations,
+            restrictions,
+            coarse_lu,
+            coarse_perm,
+            inv_diagonals,
+            cycle: amg.cycle,
+            n_sweeps: amg.n_sweeps,
+        }
+    }
+
+    // Smooths the tentative prolongation with a damped Jacobi step
+    // P = (I - omega / rho * D^-1 * A) * P_0
+    // The spectral radius rho of D^-1 * A is estimated with Gershgorin's theorem
+    fn smooth_prolongation(&self, tentative: &Self, omega: f64) -> Self {
+        let n = self.n_rows();
+        let diag = (0..n).map(|i| self.get(i, i)).collect::<Vec<T>>();
+        let mut rho = T::zero();
+        for (i, &d) in diag.iter().enumerate() {
+            let sum = self.iter_row(i).map(|(_col, &val)| val.abs()).sum::<T>() / d.abs();
+            if sum > rho {
+                rho = sum;
+            }
+        }
+        let omega = T::from_f64(omega) / rho;
+        let mat_tentative = self.spgemm(tentative).unwrap();
+        let mut values = Vec::<T>::with_capacity(mat_tentative.n_non_zero_entries());
+        let mut columns = Vec::<I>::with_capacity(mat_tentative.n_non_zero_entries());
+        let mut offset_rows = Vec::<I>::with_capacity(n + 1);
+        for (i, &d) in diag.iter().enumerate() {
+            offset_rows.push(I::as_indextype(columns.len()));
+            let start = columns.len();
+            for (&col, &val) in mat_tentative.iter_row(i) {
+                columns.push(col);
+                values.push(-(omega / d) * val);
+            }
+            // Both rows are sorted, but the tentative row only has a single entry
+            for (&col, &val) in tentative.iter_row(i) {
+                match columns[start..].iter().position(|&c| c == col) {
+                    Some(pos) => values[start + pos] += val,
+                    None => {
+                        columns.push(col);
+                        values.push(val);
+                    },
+                }
+            }
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        let mut ret = Self::from_raw(n, tentative.n_cols(), offset_rows, columns, values);
+        ret.sort();
+        ret
+    }
+}
+
+// Groups the nodes into aggregates of strongly connected neighbourhoods
+// Returns the aggregate of each node and the number of aggregates
+fn aggregate(strong: &[Vec<usize>]) -> (Vec<usize>, usize) {
+    let n = strong.len();
+    let mut aggregates = vec![UNAGGREGATED; n];
+    let mut n_aggregates = 0;
+    // Phase 1: Nodes with a completely unaggregated neighbourhood form new aggregates
+    for i in 0..n {
+        if aggregates[i] == UNAGGREGATED && strong[i].iter().all(|&j| aggregates[j] == UNAGGREGATED) {
+            aggregates[i] = n_aggregates;
+            for &j in strong[i].iter() {
+                aggregates[j] = n_aggregates;
+            }
+            n_aggregates += 1;
+        }
+    }
+    // Phase 2: Remaining nodes join a neighbouring aggregate from phase 1
+    let aggregates_phase1 = aggregates.clone();
+    for i in 0..n {
+        if aggregates[i] == UNAGGREGATED {
+            if let Some(&j) = strong[i].iter().find(|&&j| aggregates_phase1[j] != UNAGGREGATED) {
+                aggregates[i] = aggregates_phase1[j];
+            }
+        }
+    }
+    // Phase 3: Leftover nodes form aggregates with their unaggregated neighbours
+    for i in 0..n {
+        if aggregates[i] == UNAGGREGATED {
+            aggregates[i] = n_aggregates;
+            for &j in strong[i].iter() {
+                if aggregates[j] == UNAGGREGATED {
+                    aggregates[j] = n_aggregates;
+                }
+            }
+            n_aggregates += 1;
+        }
+    }
+    (aggregates, n_aggregates)
+}
+
+// Tentative prolongation interpolating the constant vector on each aggregate
+// The columns are normalized
+fn tentative_prolongation<T, I>(aggregates: &[usize], n_aggregates: usize) -> SparseMatCRS<T, I>
+where T: FloatType,
+      I: IndexType {
+    let mut sizes = vec![0usize; n_aggregates];
+    for &agg in aggregates.iter() {
+        sizes[agg] += 1;
+    }
+    let offset_rows = (0..=aggregates.len()).map(I::as_indextype).collect::<Vec<I>>();
+    let columns = aggregates.iter().map(|&agg| I::as_indextype(agg)).collect::<Vec<I>>();
+    let values = aggregates.iter().map(|&agg| T::one() / T::from_f64(sizes[agg] as f64).sqrt()).collect::<Vec<T>>();
+    SparseMatCRS::from_raw(aggregates.len(), n_aggregates, offset_rows, columns, values)
+}
+
+// Computes the LU factorization with partial pivoting of a small matrix
+// Returns the combined factors stored row-major and the row permutation
+fn dense_lu<T, I>(mat: &SparseMatCRS<T, I>) -> (Vec<T>, Vec<usize>)
+where T: FloatType,
+      I: IndexType {
+    let n = mat.n_rows();
+    let mut lu = vec![T::zero(); n * n];
+    for i in 0..n {
+        for (&col, &val) in mat.iter_row(i) {
+            lu[i * n + col.as_usize()] = val;
+        }
+    }
+    let mut perm = (0..n).collect::<Vec<usize>>();
+    for k in 0..n {
+        let pivot = (k..n).fold(k, |p, i| if lu[i * n + k].abs() > lu[p * n + k].abs() { i } else { p });
+        if pivot != k {
+            for j in 0..n {
+                lu.swap(k * n + j, pivot * n + j);
+            }
+            perm.swap(k, pivot);
+        }
+        let pivot_val = lu[k * n + k];
+        // Singular coarse matrices are regularized by skipping the elimination
+        if pivot_val == T::zero() {
+            continue;
+        }
+        for i in (k + 1)..n {
+            let factor = lu[i * n + k] / pivot_val;
+            lu[i * n + k] = factor;
+            for j in (k + 1)..n {
+                let val = lu[k * n + j];
+                lu[i * n + j] -= factor * val;
+            }
+        }
+    }
+    (lu, perm)
+}
+
+impl<T, I> AmgHierarchy<T, I>
+where T: FloatType,
+      I: IndexType {
+    // Returns the number of levels including the finest one
+    pub fn n_levels(&self) -> usize {
+        self.matrices.len()
+    }
+
+    // Returns the sum of non-zero entries on all levels over those of the finest level
+    pub fn operator_complexity(&self) -> f64 {
+        let nnz = self.matrices.iter().map(|mat| mat.n_non_zero_entries()).sum::<usize>();
+        nnz as f64 / self.matrices[0].n_non_zero_entries() as f64
+    }
+
+    // Solves the coarsest system with the dense LU factorization
+    fn solve_coarse(&self, b: &DenseVec<T>) -> DenseVec<T> {
+        let n = self.coarse_perm.len();
+        let lu = &self.coarse_lu;
+        let mut x = self.coarse_perm.iter().map(|&p| b.get(p)).collect::<Vec<T>>();
+        for i in 0..n {
+            for j in 0..i {
+                let val = x[j];
+                x[i] -= lu[i * n + j] * val;
+            }
+        }
+        for i in (0..n).rev() {
+            for j in (i + 1)..n {
+                let val = x[j];
+                x[i] -= lu[i * n + j] * val;
+            }
+            if lu[i * n + i] != T::zero() {
+                x[i] = x[i] / lu[i * n + i];
+            }
+        }
+        DenseVec::from_vec(x)
+    }
+
+    fn cycle_level(&self, level: usize, b: &DenseVec<T>, x: &mut DenseVec<T>) {
+        if level + 1 == self.matrices.len() {
+            *x = self.solve_coarse(b);
+            return;
+        }
+        let mat = &self.matrices[level];
+        let inv_diag = &self.inv_diagonals[level];
+        // Forward and backward sweeps keep the cycle symmetric
+        GaussSeidel::new(SweepDirection::Forward).smooth_with_diagonal(mat, inv_diag, b, x, self.n_sweeps);
+        let r = b.clone() - mat.mvp(x);
+        let r_coarse = self.restrictions[level].mvp(&r);
+        let mut e_coarse = DenseVec::from_vec(vec![T::zero(); r_coarse.dim()]);
+        let n_visits = match self.cycle {
+            Cycle::V => 1,
+            Cycle::W => 2,
+        };
+        for _v in 0..n_visits {
+            self.cycle_level(level + 1, &r_coarse, &mut e_coarse);
+        }
+        *x += self.prolongations[level].mvp(&e_coarse);
+        GaussSeidel::new(SweepDirection::Backward).smooth_with_diagonal(mat, inv_diag, b, x, self.n_sweeps);
+    }
+
+    // Applies a single cycle on the finest level improving x
+    pub fn cycle(&self, b: &DenseVec<T>, x: &mut DenseVec<T>) {
+        self.cycle_level(0, b, x);
+    }
+
+    // Applies cycles until the residual norm drops below tol
+    pub fn solve(&self, b: &DenseVec<T>, x: &mut DenseVec<T>, tol: f64, iter_max: usize) {
+        check_dimensions(&self.matrices[0], b, x);
+        for _k in 0..iter_max {
+            self.cycle(b, x);
+            let r = b.clone() - self.matrices[0].mvp(x);
+            if r.norm() < tol {
+                break;
+            }
+        }
+    }
+}
+
+// A single cycle with a zero initial guess is used as preconditioner
+impl<T, I> Preconditioner<T> for AmgHierarchy<T, I>
+where T: FloatType,
+      I: IndexType {
+    fn apply(&self, r: &DenseVec<T>) -> DenseVec<T> {
+        let mut z = DenseVec::from_vec(vec![T::zero(); r.dim()]);
+        self.cycle(r, &mut z);
+        z
+    }
+}
+
+impl LinearSolver for AlgebraicMultigrid {
+    fn solve<'a, M>(&self, mat: &'a M, b: &DenseVec<M::Value>, x: &mut DenseVec<M::Value>)
+    where M: SparseMatrix<'a>,
+          M::Value: FloatType {
+        let mat_crs = SparseMatCRS::<M::Value, M::Index>::from_sparsemat(mat);
+        let hierarchy = mat_crs.amg_hierarchy(self);
+        hierarchy.solve(b, x, self.tol, self.iter_max);
+    }
+}
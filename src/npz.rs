@@ -0,0 +1,522 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{Read, Write, BufReader, BufWriter};
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::{SparseMatCRS, check_raw};
+use crate::binary::{BinaryType, KIND_UNSIGNED, KIND_SIGNED, KIND_FLOAT, type_name};
+use crate::inflate::inflate;
+
+// Exchange of matrices with the layout of scipy.sparse.save_npz
+// An npz file is a zip archive with one .npy file per array:
+//   format   byte string "csr", "csc" or "coo"
+//   shape    (n_rows, n_cols)
+//   data     values
+//   indptr   offsets of the rows (csr) or columns (csc)
+//   indices  columns (csr) or rows (csc)
+//   row, col coordinates of the entries (coo)
+// Archives are written without compression, while stored and deflated entries can be read,
+// so files saved by Python with save_npz(filename, matrix) and compressed=True or False are supported
+
+// Layout of the arrays in the npz file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NpzFormat {
+    CSR,
+    CSC,
+    COO,
+}
+
+impl NpzFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            NpzFormat::CSR => "csr",
+            NpzFormat::CSC => "csc",
+            NpzFormat::COO => "coo",
+        }
+    }
+}
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+const ZIP_LOCAL_HEADER: u32 = 0x04034b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014b50;
+const ZIP_END_RECORD: u32 = 0x06054b50;
+// 1980-01-01 00:00 in MS-DOS format, the earliest date a zip archive can store
+const ZIP_DATE: u16 = 0x21;
+const ZIP_TIME: u16 = 0;
+
+// CRC-32 as used by zip archives
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _k in 0..8 {
+            c = if c & 1 == 1 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    let mut crc = 0xffffffffu32;
+    for &byte in data.iter() {
+        crc = table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xffffffff
+}
+
+// Minimal writer for zip archives storing the files without compression
+struct ZipWriter<'w, W> {
+    writer: &'w mut W,
+    offset: usize,
+    // Name, CRC, size and offset of the local header of each file
+    entries: Vec<(String, u32, u32, u32)>,
+}
+
+impl<'w, W> ZipWriter<'w, W>
+where W: Write {
+    fn new(writer: &'w mut W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    // Converts a size or offset to the 32 bit fields of the archive
+    fn field(val: usize) -> Result<u32, SparseMatError> {
+        if val >= u32::MAX as usize {
+            return Err(SparseMatError::new("Matrix too large for an npz file without ZIP64"));
+        }
+        Ok(val as u32)
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<(), SparseMatError> {
+        let crc = crc32(data);
+        let size = Self::field(data.len())?;
+        let offset = Self::field(self.offset)?;
+        let mut buf = Vec::<u8>::with_capacity(30 + name.len());
+        buf.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+        for field in [20u16, 0, 0, ZIP_TIME, ZIP_DATE].iter() {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [crc, size, size].iter() {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(name.as_bytes());
+        self.writer.write_all(&buf)?;
+        self.writer.write_all(data)?;
+        self.offset += buf.len() + data.len();
+        self.entries.push((name.to_string(), crc, size, offset));
+        Ok(())
+    }
+
+    // Writes the central directory and the end record
+    fn finish(self) -> Result<(), SparseMatError> {
+        let mut buf = Vec::<u8>::new();
+        for (name, crc, size, offset) in self.entries.iter() {
+            buf.extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+            for field in [20u16, 20, 0, 0, ZIP_TIME, ZIP_DATE].iter() {
+                buf.extend_from_slice(&field.to_le_bytes());
+            }
+            for field in [*crc, *size, *size].iter() {
+                buf.extend_from_slice(&field.to_le_bytes());
+            }
+            // Name length, extra field, comment, disk number and internal attributes
+            for field in [name.len() as u16, 0, 0, 0, 0].iter() {
+                buf.extend_from_slice(&field.to_le_bytes());
+            }
+            // External attributes
+            buf.extend_from_slice(&0u32.to_le_bytes());
+            buf.extend_from_slice(&offset.to_le_bytes());
+            buf.extend_from_slice(name.as_bytes());
+        }
+        let n_entries = self.entries.len() as u16;
+        let size = Self::field(buf.len())?;
+        let offset = Self::field(self.offset)?;
+        buf.extend_from_slice(&ZIP_END_RECORD.to_le_bytes());
+        for field in [0u16, 0, n_entries, n_entries].iter() {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&offset.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        self.writer.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+// Reads a little endian u16 or u32 at pos, failing for truncated archives
+fn zip_u16(bytes: &[u8], pos: usize) -> Result<u16, SparseMatError> {
+    match bytes.get(pos..pos + 2) {
+        Some(field) => Ok(u16::from_le_bytes(field.try_into().unwrap())),
+        None => Err(SparseMatError::new("Invalid npz file - truncated zip archive")),
+    }
+}
+
+fn zip_u32(bytes: &[u8], pos: usize) -> Result<u32, SparseMatError> {
+    match bytes.get(pos..pos + 4) {
+        Some(field) => Ok(u32::from_le_bytes(field.try_into().unwrap())),
+        None => Err(SparseMatError::new("Invalid npz file - truncated zip archive")),
+    }
+}
+
+// Name and content of a file in a zip archive
+type ZipFile<'b> = (String, Cow<'b, [u8]>);
+
+// Returns the name and the content of all files in a zip archive
+// using the central directory, since numpy does not store the sizes in the local headers
+// Deflated files are decompressed, stored ones are borrowed from the archive
+fn read_zip(bytes: &[u8]) -> Result<Vec<ZipFile<'_>>, SparseMatError> {
+    // The end record is followed by a comment of at most 64 KiB
+    let min_pos = bytes.len().saturating_sub(22 + u16::MAX as usize);
+    let end = (min_pos..bytes.len().saturating_sub(21)).rev()
+        .find(|&pos| matches!(zip_u32(bytes, pos), Ok(ZIP_END_RECORD)))
+        .ok_or_else(|| SparseMatError::new("Invalid npz file - not a zip archive"))?;
+    let n_entries = zip_u16(bytes, end + 10)? as usize;
+    let mut pos = zip_u32(bytes, end + 16)? as usize;
+    if n_entries == u16::MAX as usize || pos == u32::MAX as usize {
+        return Err(SparseMatError::new("ZIP64 npz files are not supported"));
+    }
+    let mut files = Vec::with_capacity(n_entries);
+    for _k in 0..n_entries {
+        if zip_u32(bytes, pos)? != ZIP_CENTRAL_HEADER {
+            return Err(SparseMatError::new("Invalid npz file - corrupt central directory"));
+        }
+        let method = zip_u16(bytes, pos + 10)?;
+        let crc = zip_u32(bytes, pos + 16)?;
+        let size = zip_u32(bytes, pos + 20)? as usize;
+        let size_uncompressed = zip_u32(bytes, pos + 24)? as usize;
+        let name_len = zip_u16(bytes, pos + 28)? as usize;
+        let extra_len = zip_u16(bytes, pos + 30)? as usize;
+        let comment_len = zip_u16(bytes, pos + 32)? as usize;
+        let offset = zip_u32(bytes, pos + 42)? as usize;
+        let name = bytes.get(pos + 46..pos + 46 + name_len)
+            .ok_or_else(|| SparseMatError::new("Invalid npz file - truncated zip archive"))?;
+        let name = String::from_utf8_lossy(name).to_string();
+        if method != 0 && method != 8 {
+            return Err(SparseMatError::new(&format!("Unsupported compression method {} for {}", method, name)));
+        }
+        if zip_u32(bytes, offset)? != ZIP_LOCAL_HEADER {
+            return Err(SparseMatError::new("Invalid npz file - corrupt local header"));
+        }
+        let start = offset + 30 + zip_u16(bytes, offset + 26)? as usize + zip_u16(bytes, offset + 28)? as usize;
+        let data = bytes.get(start..start + size)
+            .ok_or_else(|| SparseMatError::new("Invalid npz file - truncated zip archive"))?;
+        let data = if method == 8 {
+            Cow::Owned(inflate(data, size_uncompressed)?)
+        } else {
+            Cow::Borrowed(data)
+        };
+        if data.len() != size_uncompressed {
+            return Err(SparseMatError::new(&format!("Invalid npz file - size mismatch for {}", name)));
+        }
+        if crc32(&data) != crc {
+            return Err(SparseMatError::new(&format!("Invalid npz file - CRC mismatch for {}", name)));
+        }
+        files.push((name, data));
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(files)
+}
+
+// Returns the numpy type descriptor for a binary type like '<f8'
+fn descr(type_code: u8) -> String {
+    let kind = match type_code & 0xf0 {
+        KIND_UNSIGNED => 'u',
+        KIND_SIGNED => 'i',
+        _ => 'f',
+    };
+    let size = (type_code & 0x0f) as usize;
+    let endian = if size == 1 {
+        '|'
+    } else if cfg!(target_endian = "big") {
+        '>'
+    } else {
+        '<'
+    };
+    format!("{}{}{}", endian, kind, size)
+}
+
+// Creates the content of an .npy file with version 1.0
+fn npy_bytes(descr: &str, shape: &[usize], data: &[u8]) -> Vec<u8> {
+    let shape = match shape.len() {
+        1 => format!("({},)", shape[0]),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<String>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    // The data is aligned to 64 bytes and the header terminated by a newline
+    while (NPY_MAGIC.len() + 4 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+    let mut buf = Vec::<u8>::with_capacity(NPY_MAGIC.len() + 4 + header.len() + data.len());
+    buf.extend_from_slice(NPY_MAGIC);
+    buf.extend_from_slice(&[1, 0]);
+    buf.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    buf.extend_from_slice(header.as_bytes());
+    buf.extend_from_slice(data);
+    buf
+}
+
+// Creates an .npy file with the elements of a binary type
+fn npy_array<B>(vals: &[B]) -> Vec<u8>
+where B: BinaryType {
+    let mut data = Vec::<u8>::with_capacity(vals.len() * B::SIZE);
+    for val in vals.iter() {
+        val.write_bytes(&mut data);
+    }
+    npy_bytes(&descr(B::TYPE_CODE), &[vals.len()], &data)
+}
+
+// Creates an .npy file with indices using 32 bit integers if possible like scipy
+fn npy_indices<It>(indices: It, wide: bool) -> Vec<u8>
+where It: Iterator<Item = usize> {
+    if wide {
+        npy_array(&indices.map(|index| index as i64).collect::<Vec<i64>>())
+    } else {
+        npy_array(&indices.map(|index| index as i32).collect::<Vec<i32>>())
+    }
+}
+
+// An array read from an .npy file
+struct NpyArray<'b> {
+    kind: char,
+    size: usize,
+    big_endian: bool,
+    data: &'b [u8],
+}
+
+// Returns the text following the key in the header of an .npy file
+fn header_value<'h>(header: &'h str, key: &str) -> Result<&'h str, SparseMatError> {
+    let pattern = format!("'{}':", key);
+    match header.find(&pattern) {
+        Some(pos) => Ok(header[pos + pattern.len()..].trim_start()),
+        None => Err(SparseMatError::new(&format!("Invalid npy header - missing {}", key))),
+    }
+}
+
+impl<'b> NpyArray<'b> {
+    fn parse(name: &str, bytes: &'b [u8]) -> Result<Self, SparseMatError> {
+        let invalid = |what: &str| SparseMatError::new(&format!("Invalid npy file {} - {}", name, what));
+        if bytes.len() < 10 || &bytes[0..6] != NPY_MAGIC {
+            return Err(invalid("magic number does not match"));
+        }
+        let (header_start, header_len) = match bytes[6] {
+            1 => (10, zip_u16(bytes, 8)? as usize),
+            2 | 3 => (12, zip_u32(bytes, 8)? as usize),
+            version => return Err(invalid(&format!("unsupported version {}", version))),
+        };
+        let header = bytes.get(header_start..header_start + header_len).ok_or_else(|| invalid("truncated header"))?;
+        let header = std::str::from_utf8(header).map_err(|_| invalid("header is not text"))?;
+        // Type descriptor like '<f8' or '|S3'
+        let descr = header_value(header, "descr")?;
+        let descr = descr.get(1..).and_then(|d| d.split('\'').next()).ok_or_else(|| invalid("invalid descr"))?;
+        let mut chars = descr.chars();
+        let big_endian = match chars.next() {
+            Some('<') | Some('|') => false,
+            Some('>') => true,
+            Some('=') => cfg!(target_endian = "big"),
+            _ => return Err(invalid(&format!("invalid type {}", descr))),
+        };
+        let kind = chars.next().ok_or_else(|| invalid("invalid descr"))?;
+        let count = chars.as_str().parse::<usize>().map_err(|_| invalid(&format!("invalid type {}", descr)))?;
+        let size = if kind == 'U' { 4 * count } else { count };
+        // Shape like (), (3,) or (2, 3)
+        let shape = header_value(header, "shape")?;
+        let shape = shape.strip_prefix('(').and_then(|s| s.split(')').next()).ok_or_else(|| invalid("invalid shape"))?;
+        let mut len = 1usize;
+        let mut n_dims = 0;
+        for dim in shape.split(',').map(|dim| dim.trim()).filter(|dim| !dim.is_empty()) {
+            let dim = dim.parse::<usize>().map_err(|_| invalid("invalid shape"))?;
+            len = len.checked_mul(dim).ok_or_else(|| invalid("invalid shape"))?;
+            n_dims += 1;
+        }
+        if n_dims > 1 && header_value(header, "fortran_order")?.starts_with("True") {
+            return Err(invalid("fortran order is not supported"));
+        }
+        let n_bytes = len.checked_mul(size).ok_or_else(|| invalid("invalid shape"))?;
+        let data = bytes.get(header_start + header_len..)
+            .and_then(|data| data.get(..n_bytes))
+            .ok_or_else(|| invalid("truncated data"))?;
+        Ok(Self {
+            kind,
+            size,
+            big_endian,
+            data,
+        })
+    }
+
+    // Returns the type code of a numeric array
+    fn type_code(&self) -> Option<u8> {
+        let kind = match self.kind {
+            'u' => KIND_UNSIGNED,
+            'i' => KIND_SIGNED,
+            'f' => KIND_FLOAT,
+            _ => return None,
+        };
+        match self.size {
+            1 | 2 | 4 | 8 => Some(kind | self.size as u8),
+            _ => None,
+        }
+    }
+
+    // Returns the elements, which must have exactly the binary type B
+    fn values<B>(&self, what: &str) -> Result<Vec<B>, SparseMatError>
+    where B: BinaryType {
+        if self.type_code() != Some(B::TYPE_CODE) {
+            let found = match self.type_code() {
+                Some(code) => type_name(code),
+                None => format!("{}{}", self.kind, self.size),
+            };
+            return Err(SparseMatError::new(&format!("Type mismatch of {} - file has {}, expected {}",
+                                                    what, found, type_name(B::TYPE_CODE))));
+        }
+        Ok(self.data.chunks_exact(B::SIZE).map(|chunk| B::read_bytes(chunk, self.big_endian)).collect())
+    }
+
+    // Returns the elements of an integer array of any width as indices
+    fn indices(&self, what: &str) -> Result<Vec<usize>, SparseMatError> {
+        let to_index = |val: i128| -> Result<usize, SparseMatError> {
+            if val < 0 || val > usize::MAX as i128 {
+                return Err(SparseMatError::new(&format!("Invalid {} {}", what, val)));
+            }
+            Ok(val as usize)
+        };
+        let chunks = self.data.chunks_exact(self.size);
+        let be = self.big_endian;
+        match self.type_code() {
+            Some(0x11) => chunks.map(|c| to_index(u8::read_bytes(c, be) as i128)).collect(),
+            Some(0x12) => chunks.map(|c| to_index(u16::read_bytes(c, be) as i128)).collect(),
+            Some(0x14) => chunks.map(|c| to_index(u32::read_bytes(c, be) as i128)).collect(),
+            Some(0x18) => chunks.map(|c| to_index(u64::read_bytes(c, be) as i128)).collect(),
+            Some(0x21) => chunks.map(|c| to_index(i8::read_bytes(c, be) as i128)).collect(),
+            Some(0x22) => chunks.map(|c| to_index(i16::read_bytes(c, be) as i128)).collect(),
+            Some(0x24) => chunks.map(|c| to_index(i32::read_bytes(c, be) as i128)).collect(),
+            Some(0x28) => chunks.map(|c| to_index(i64::read_bytes(c, be) as i128)).collect(),
+            _ => Err(SparseMatError::new(&format!("Type mismatch of {} - expected an integer array", what))),
+        }
+    }
+
+    // Returns the content of a byte or unicode string array
+    fn text(&self) -> Result<String, SparseMatError> {
+        let text = match self.kind {
+            'S' => self.data.iter().map(|&c| c as char).collect::<String>(),
+            'U' => self.data.chunks_exact(4)
+                .map(|c| std::char::from_u32(u32::read_bytes(c, self.big_endian)).unwrap_or('?'))
+                .collect::<String>(),
+            _ => return Err(SparseMatError::new("Invalid npz file - format is not a string")),
+        };
+        Ok(text.trim_end_matches('\0').to_string())
+    }
+}
+
+// Converts indices to the index type of the matrix
+fn to_index_type<I>(indices: Vec<usize>, what: &str) -> Result<Vec<I>, SparseMatError>
+where I: IndexType {
+    if indices.iter().any(|&index| index >= I::MAX.as_usize()) {
+        return Err(SparseMatError::new(&format!("Invalid {} - does not fit into the index type", what)));
+    }
+    Ok(indices.into_iter().map(I::as_indextype).collect())
+}
+
+impl<T, I> SparseMatCRS<T, I>
+where T: ValueType + BinaryType,
+      I: IndexType + BinaryType {
+    // Writes the matrix in the npz layout of scipy.sparse with the given format
+    // Indices are stored as 32 bit integers if possible and as 64 bit integers otherwise
+    pub fn write_npz<W>(&self, writer: &mut W, format: NpzFormat) -> Result<(), SparseMatError>
+    where W: Write {
+        let nnz = self.n_non_zero_entries();
+        let wide = std::cmp::max(nnz, std::cmp::max(self.n_rows(), self.n_cols())) > i32::MAX as usize;
+        // The CSC arrays are the CRS arrays of the transpose
+        let transposed = match format {
+            NpzFormat::CSC => Some(self.transpose()),
+            _ => None,
+        };
+        let mat = transposed.as_ref().unwrap_or(self);
+        let mut zip = ZipWriter::new(writer);
+        match format {
+            NpzFormat::CSR | NpzFormat::CSC => {
+                let indices = (0..mat.n_rows()).flat_map(|i| mat.iter_row(i).map(|(col, _val)| col.as_usize()));
+                zip.add("indices.npy", &npy_indices(indices, wide))?;
+                let indptr = std::iter::once(0).chain((0..mat.n_rows()).scan(0, |offset, i| {
+                    *offset += mat.iter_row(i).count();
+                    Some(*offset)
+                }));
+                zip.add("indptr.npy", &npy_indices(indptr, wide))?;
+            },
+            NpzFormat::COO => {
+                let rows = (0..self.n_rows()).flat_map(|i| std::iter::repeat(i).take(self.iter_row(i).count()));
+                zip.add("row.npy", &npy_indices(rows, wide))?;
+                let cols = (0..self.n_rows()).flat_map(|i| self.iter_row(i).map(|(col, _val)| col.as_usize()));
+                zip.add("col.npy", &npy_indices(cols, wide))?;
+            },
+        }
+        let name = format.name();
+        zip.add("format.npy", &npy_bytes(&format!("|S{}", name.len()), &[], name.as_bytes()))?;
+        zip.add("shape.npy", &npy_array(&[self.n_rows() as i64, self.n_cols() as i64]))?;
+        let data = (0..mat.n_rows()).flat_map(|i| mat.iter_row(i).map(|(_col, &val)| val)).collect::<Vec<T>>();
+        zip.add("data.npy", &npy_array(&data))?;
+        zip.finish()
+    }
+
+    // Reads a matrix in the npz layout of scipy.sparse from a zip archive with stored or deflated entries
+    // The values must have the value type of the matrix, while indices of any integer type are accepted
+    pub fn read_npz<R>(reader: &mut R) -> Result<Self, SparseMatError>
+    where R: Read {
+        let mut bytes = Vec::<u8>::new();
+        reader.read_to_end(&mut bytes)?;
+        let files = read_zip(&bytes)?;
+        let array = |name: &str| -> Result<NpyArray, SparseMatError> {
+            let filename = format!("{}.npy", name);
+            match files.iter().find(|(file, _data)| *file == filename) {
+                Some((_file, data)) => NpyArray::parse(&filename, data.as_ref()),
+                None => Err(SparseMatError::new(&format!("Invalid npz file - missing array {}", name))),
+            }
+        };
+        let format = array("format")?.text()?;
+        let shape = array("shape")?.indices("shape")?;
+        if shape.len() != 2 {
+            return Err(SparseMatError::new("Invalid npz file - shape must have two dimensions"));
+        }
+        let (n_rows, n_cols) = (shape[0], shape[1]);
+        if n_rows >= I::MAX.as_usize() || n_cols >= I::MAX.as_usize() {
+            return Err(SparseMatError::new("Matrix does not fit into the index type"));
+        }
+        let values = array("data")?.values::<T>("data")?;
+        match format.as_str() {
+            "csr" => {
+                let offset_rows = to_index_type(array("indptr")?.indices("indptr")?, "indptr")?;
+                let columns = to_index_type(array("indices")?.indices("indices")?, "indices")?;
+                Self::try_from_raw(n_rows, n_cols, offset_rows, columns, values)
+            },
+            "csc" => {
+                // The arrays describe the transpose in CRS format
+                let offset_cols = to_index_type(array("indptr")?.indices("indptr")?, "indptr")?;
+                let rows = to_index_type(array("indices")?.indices("indices")?, "indices")?;
+                check_raw(n_cols, n_rows, &offset_cols, &rows, values.len())?;
+                Ok(Self::from_raw(n_cols, n_rows, offset_cols, rows, values).transpose())
+            },
+            "coo" => {
+                let rows = array("row")?.indices("row")?;
+                let cols = to_index_type(array("col")?.indices("col")?, "col")?;
+                Self::try_from_triplets(n_rows, n_cols, &rows, &cols, &values)
+            },
+            _ => Err(SparseMatError::new(&format!("Unsupported sparse format {}", format))),
+        }
+    }
+
+    // Saves the matrix to an npz file readable by scipy.sparse.load_npz
+    pub fn save_npz(&self, filename: &str, format: NpzFormat) -> Result<(), SparseMatError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_npz(&mut writer, format)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Loads a matrix from a compressed or uncompressed npz file written by scipy.sparse.save_npz
+    pub fn load_npz(filename: &str) -> Result<Self, SparseMatError> {
+        let mut reader = BufReader::new(File::open(filename)?);
+        Self::read_npz(&mut reader)
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+use crate::vector::Vector;
+use crate::densevec::DenseVec;
+
+// Marks nodes which have not been visited yet
+const UNVISITED: usize = usize::MAX;
+
+impl<T, I> SparseMatCRS<T, I>
+where T: ValueType,
+      I: IndexType {
+    // Returns the symmetrized adjacency of the matrix graph without the diagonal
+    // The neighbours of each node are sorted by their degree
+    fn adjacency(&self) -> Vec<Vec<usize>> {
+        let n = std::cmp::max(self.n_rows(), self.n_cols());
+        let mut adj = vec![Vec::<usize>::new(); n];
+        for i in 0..self.n_rows() {
+            for (&col, _val) in self.iter_row(i) {
+                let j = col.as_usize();
+                if i != j {
+                    adj[i].push(j);
+                    adj[j].push(i);
+                }
+            }
+        }
+        let degrees = adj.iter().map(|neighbours| {
+            let mut neighbours = neighbours.clone();
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            neighbours.len()
+        }).collect::<Vec<usize>>();
+        for neighbours in adj.iter_mut() {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            neighbours.sort_by_key(|&j| degrees[j]);
+        }
+        adj
+    }
+
+    // Returns the reverse Cuthill-McKee ordering, which reduces bandwidth and profile
+    // The entry k of the permutation is the old index of the new node k
+    // Each connected component starts at a pseudo-peripheral node
+    pub fn rcm_ordering(&self) -> Vec<usize> {
+        let adj = self.adjacency();
+        let n = adj.len();
+        let mut perm = Vec::<usize>::with_capacity(n);
+        let mut level = vec![UNVISITED; n];
+        let mut visited = vec![false; n];
+        for root in 0..n {
+            if visited[root] {
+                continue;
+            }
+            let start = pseudo_peripheral_node(&adj, root, &mut level);
+            // Breadth first search visiting the neighbours by increasing degree
+            let mut queue = VecDeque::from(vec![start]);
+            visited[start] = true;
+            while let Some(node) = queue.pop_front() {
+                perm.push(node);
+                for &j in adj[node].iter() {
+                    if !visited[j] {
+                        visited[j] = true;
+                        queue.push_back(j);
+                    }
+                }
+            }
+        }
+        perm.reverse();
+        perm
+    }
+
+    // Returns the symmetrically permuted matrix P * A * P^T,
+    // where entry k of the permutation is the old index of the new row and column k
+    pub fn permute(&self, perm: &[usize]) -> Self {
+        let perm_inv = inverse_permutation(perm);
+        let mut values = Vec::<T>::with_capacity(self.n_non_zero_entries());
+        let mut columns = Vec::<I>::with_capacity(self.n_non_zero_entries());
+        let mut offset_rows = Vec::<I>::with_capacity(perm.len() + 1);
+        let mut cols_vals = Vec::<(usize, T)>::new();
+        for &i in perm.iter() {
+            offset_rows.push(I::as_indextype(columns.len()));
+            cols_vals.extend(self.iter_row(i).map(|(&col, &val)| (perm_inv[col.as_usize()], val)));
+            cols_vals.sort_unstable_by_key(|&(col, _val)| col);
+            for &(col, val) in cols_vals.iter() {
+                columns.push(I::as_indextype(col));
+                values.push(val);
+            }
+            cols_vals.clear();
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        Self::from_raw(perm.len(), perm.len(), offset_rows, columns, values)
+    }
+
+    // Returns the bandwidth max |i - j| of the matrix
+    pub fn bandwidth(&self) -> usize {
+        (0..self.n_rows()).flat_map(|i| self.iter_row(i).map(move |(&col, _val)| i.abs_diff(col.as_usize()))).max().unwrap_or(0)
+    }
+
+    // Returns the profile of the lower triangular part,
+    // which is the sum of the distances from the first entry of each row to the diagonal
+    pub fn profile(&self) -> usize {
+        (0..self.n_rows()).map(|i| {
+            self.iter_row(i).map(|(&col, _val)| col.as_usize()).filter(|&j| j < i).min().map_or(0, |j| i - j)
+        }).sum()
+    }
+}
+
+// Finds a node with a large eccentricity in the component of root (George-Liu)
+// The levels are used as workspace and are reset afterwards
+fn pseudo_peripheral_node(adj: &[Vec<usize>], root: usize, level: &mut [usize]) -> usize {
+    let mut node = root;
+    let mut eccentricity = 0;
+    loop {
+        let (visited, depth) = level_structure(adj, node, level);
+        // Pick the node with the smallest degree in the last level
+        let last = visited.iter()
+            .filter(|&&i| level[i] == depth)
+            .min_by_key(|&&i| adj[i].len())
+            .copied()
+            .unwrap_or(node);
+        for &i in visited.iter() {
+            level[i] = UNVISITED;
+        }
+        if depth <= eccentricity {
+            return node;
+        }
+        eccentricity = depth;
+        node = last;
+    }
+}
+
+// Computes the levels of a breadth first search from root
+// Returns the visited nodes and the depth of the level structure
+fn level_structure(adj: &[Vec<usize>], root: usize, level: &mut [usize]) -> (Vec<usize>, usize) {
+    let mut visited = vec![root];
+    level[root] = 0;
+    let mut pos = 0;
+    let mut depth = 0;
+    while pos < visited.len() {
+        let node = visited[pos];
+        pos += 1;
+        for &j in adj[node].iter() {
+            if level[j] == UNVISITED {
+                level[j] = level[node] + 1;
+                depth = level[j];
+                visited.push(j);
+            }
+        }
+    }
+    (visited, depth)
+}
+
+// Returns the inverse of a permutation
+pub fn inverse_permutation(perm: &[usize]) -> Vec<usize> {
+    let mut perm_inv = vec![0; perm.len()];
+    for (k, &i) in perm.iter().enumerate() {
+        perm_inv[i] = k;
+    }
+    perm_inv
+}
+
+impl<T> DenseVec<T>
+where T: ValueType {
+    // Returns the permuted vector with entry k set to entry perm[k] of this vector
+    pub fn permute(&self, perm: &[usize]) -> Self {
+        Self::from_vec(perm.iter().map(|&i| self.get(i)).collect())
+    }
+
+    // Reverts the permutation - entry perm[k] is set to entry k of this vector
+    pub fn permute_inverse(&self, perm: &[usize]) -> Self {
+        let mut ret = vec![T::zero(); perm.len()];
+        for (k, &i) in perm.iter().enumerate() {
+            ret[i] = self.get(k);
+        }
+        Self::from_vec(ret)
+    }
+}
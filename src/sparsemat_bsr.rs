@@ -0,0 +1,453 @@
+use crate::types::{IndexType, ValueType, FloatType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+use crate::linearsolver::Preconditioner;
+use crate::vector::Vector;
+use crate::densevec::DenseVec;
+
+// Implementation of a sparse matrix with block sparse row format
+// The matrix consists of dense blocks of size block_size x block_size,
+// which are stored like the entries of a CRS matrix
+// The entries of each block are stored row-major, so block k starts at k * block_size^2
+// The vec columns holds the actual column of each stored entry in the same layout as the values,
+// which is required for the row iterator
+// Example: block_size = 2, blocks (0, 0) and (0, 2) in the first block row
+//          offset_block_rows = [0, 2]
+//          block_columns     = [0, 2]
+//          columns           = [0, 1, 0, 1, 4, 5, 4, 5]
+// All entries of a stored block count as non-zero entries
+// The dimensions need not be multiples of the block size - the blocks at the last block row
+// and column are then padded with zeros, which are neither iterated nor counted as entries
+#[derive(Clone, Debug)]
+pub struct SparseMatBSR<T, I> {
+    block_size: usize,
+    n_rows: usize,
+    n_cols: usize,
+    offset_block_rows: Vec<I>,
+    block_columns: Vec<I>,
+    columns: Vec<I>,
+    values: Vec<T>,
+}
+
+impl<T, I> SparseMatBSR<T, I>
+where T: ValueType,
+      I: IndexType {
+
+    // Creates an empty matrix with the given block size
+    pub fn with_block_size(block_size: usize) -> Self {
+        assert!(block_size > 0, "Block size has to be positive");
+        Self {
+            block_size,
+            n_rows: 0,
+            n_cols: 0,
+            offset_block_rows: Vec::<I>::new(),
+            block_columns: Vec::<I>::new(),
+            columns: Vec::<I>::new(),
+            values: Vec::<T>::new(),
+        }
+    }
+
+    // Returns the block size
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    // Returns the number of block rows
+    pub fn n_block_rows(&self) -> usize {
+        if self.offset_block_rows.is_empty() {
+            0
+        } else {
+            self.offset_block_rows.len() - 1
+        }
+    }
+
+    // Returns the number of block columns
+    pub fn n_block_cols(&self) -> usize {
+        self.n_cols.div_ceil(self.block_size)
+    }
+
+    // Returns the number of stored blocks
+    pub fn n_blocks(&self) -> usize {
+        self.block_columns.len()
+    }
+
+    // Returns the index of block (bi, bj) or UNSET if it does not exist
+    fn find_block(&self, bi: usize, bj: usize) -> usize {
+        let mut ret = Self::UNSET.as_usize();
+        if bi < self.n_block_rows() {
+            let start = self.offset_block_rows[bi].as_usize();
+            let end = self.offset_block_rows[bi + 1].as_usize();
+            for k in start..end {
+                if self.block_columns[k].as_usize() == bj {
+                    ret = k;
+                    break;
+                }
+            }
+        }
+        ret
+    }
+
+    // Adds a zero block at (bi, bj) without check
+    // This is very inefficient since we use insert of Vec here
+    fn push_block(&mut self, bi: usize, bj: usize) -> usize {
+        let bs = self.block_size;
+        if self.offset_block_rows.is_empty() {
+            self.offset_block_rows.push(I::ZERO);
+        }
+        if bi >= self.n_block_rows() {
+            let offset_last = self.offset_block_rows[self.offset_block_rows.len() - 1];
+            self.offset_block_rows.resize(bi + 2, offset_last);
+        }
+        // Keep the blocks of the block row sorted
+        let start = self.offset_block_rows[bi].as_usize();
+        let end = self.offset_block_rows[bi + 1].as_usize();
+        let k = start + self.block_columns[start..end].partition_point(|col| col.as_usize() < bj);
+        self.block_columns.insert(k, I::as_indextype(bj));
+        let cols_block = (0..bs * bs).map(|index| I::as_indextype(bj * bs + index % bs));
+        self.columns.splice(k * bs * bs..k * bs * bs, cols_block);
+        self.values.splice(k * bs * bs..k * bs * bs, vec![T::zero(); bs * bs]);
+        for offset in self.offset_block_rows[(bi + 1)..].iter_mut() {
+            *offset += I::ONE;
+        }
+        k
+    }
+
+    // Returns the row-major entries of block (bi, bj) if it exists
+    pub fn get_block(&self, bi: usize, bj: usize) -> Option<&[T]> {
+        let bs2 = self.block_size * self.block_size;
+        match self.find_block(bi, bj) {
+            k if k == Self::UNSET.as_usize() => None,
+            k => Some(&self.values[k * bs2..(k + 1) * bs2]),
+        }
+    }
+
+    // Returns the row-major entries of block (bi, bj) mutably
+    // Inserts a zero block if it does not exist yet and extends the dimensions to the whole block
+    pub fn get_block_mut(&mut self, bi: usize, bj: usize) -> &mut [T] {
+        let bs = self.block_size;
+        self.n_rows = std::cmp::max(self.n_rows, (bi + 1) * bs);
+        self.n_cols = std::cmp::max(self.n_cols, (bj + 1) * bs);
+        self.block_mut(bi, bj)
+    }
+
+    // Returns the entries of block (bi, bj) mutably without changing the dimensions
+    fn block_mut(&mut self, bi: usize, bj: usize) -> &mut [T] {
+        let bs2 = self.block_size * self.block_size;
+        let mut k = self.find_block(bi, bj);
+        if k == Self::UNSET.as_usize() {
+            k = self.push_block(bi, bj);
+        }
+        &mut self.values[k * bs2..(k + 1) * bs2]
+    }
+
+    // Creates a matrix in BSR format from a matrix in CRS format
+    // Every block containing at least one entry is stored
+    // The dimensions are kept, partial blocks at the edges are padded with zeros
+    pub fn from_crs(rhs: &SparseMatCRS<T, I>, block_size: usize) -> Self {
+        let mut ret = Self::with_block_size(block_size);
+        let bs = block_size;
+        ret.n_rows = rhs.n_rows();
+        ret.n_cols = rhs.n_cols();
+        let n_block_rows = rhs.n_rows().div_ceil(bs);
+        // Position of each block column within the current block row
+        let mut pos_block = vec![Self::UNSET.as_usize(); ret.n_block_cols()];
+        let mut cols_block_row = Vec::<usize>::new();
+        ret.offset_block_rows.push(I::ZERO);
+        for bi in 0..n_block_rows {
+            let rows = (bi * bs)..std::cmp::min((bi + 1) * bs, rhs.n_rows());
+            for i in rows.clone() {
+                for (&col, _val) in rhs.iter_row(i) {
+                    let bj = col.as_usize() / bs;
+                    if pos_block[bj] == Self::UNSET.as_usize() {
+                        pos_block[bj] = 0;
+                        cols_block_row.push(bj);
+                    }
+                }
+            }
+            cols_block_row.sort_unstable();
+            let first = ret.block_columns.len();
+            for (count, &bj) in cols_block_row.iter().enumerate() {
+                pos_block[bj] = first + count;
+                ret.block_columns.push(I::as_indextype(bj));
+                ret.columns.extend((0..bs * bs).map(|index| I::as_indextype(bj * bs + index % bs)));
+            }
+            ret.values.resize(ret.columns.len(), T::zero());
+            for i in rows {
+                for (&col, &val) in rhs.iter_row(i) {
+                    let j = col.as_usize();
+                    let k = pos_block[j / bs];
+                    ret.values[k * bs * bs + (i % bs) * bs + j % bs] = val;
+                }
+            }
+            for &bj in cols_block_row.iter() {
+                pos_block[bj] = Self::UNSET.as_usize();
+            }
+            cols_block_row.clear();
+            ret.offset_block_rows.push(I::as_indextype(ret.block_columns.len()));
+        }
+        ret
+    }
+
+    // Creates a new sparse matrix with CRS format
+    // Zero entries within the blocks are dropped
+    pub fn to_crs(&self) -> SparseMatCRS<T, I> {
+        let mut values = Vec::<T>::with_capacity(self.values.len());
+        let mut columns = Vec::<I>::with_capacity(self.values.len());
+        let mut offset_rows = Vec::<I>::with_capacity(self.n_rows() + 1);
+        for i in 0..self.n_rows() {
+            offset_rows.push(I::as_indextype(columns.len()));
+            for (&col, &val) in self.iter_row(i) {
+                if val != T::zero() {
+                    columns.push(col);
+                    values.push(val);
+                }
+            }
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        SparseMatCRS::from_raw(self.n_rows(), self.n_cols(), offset_rows, columns, values)
+    }
+}
+
+impl<'a, T, I> SparseMatrix<'a> for SparseMatBSR<T, I>
+where T: 'a + ValueType,
+      I: 'a + IndexType {
+    type Value = T;
+    type Index = I;
+    type IterRow = IterRow<'a, T, I>;
+
+    fn iter_row(&'a self, row: usize) -> Self::IterRow {
+        let bs = self.block_size;
+        let (block, block_end) = if row < self.n_rows() {
+            (self.offset_block_rows[row / bs].as_usize(), self.offset_block_rows[row / bs + 1].as_usize())
+        } else {
+            (0, 0)
+        };
+        IterRow::<T, I> {
+            mat: self,
+            row_block: row % bs,
+            block,
+            block_end,
+            col_block: 0,
+        }
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        let mut ret = Self::with_block_size(1);
+        ret.block_columns.reserve(cap);
+        ret.columns.reserve(cap);
+        ret.values.reserve(cap);
+        ret
+    }
+
+    fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    fn n_non_zero_entries(&self) -> usize {
+        let bs = self.block_size;
+        if self.n_block_rows() * bs == self.n_rows && self.n_block_cols() * bs == self.n_cols {
+            return self.values.len();
+        }
+        // Leave out the padding of the partial blocks at the edges
+        let height = |bi: usize| std::cmp::min(bs, self.n_rows - bi * bs);
+        let width = |bj: usize| std::cmp::min(bs, self.n_cols - bj * bs);
+        (0..self.n_block_rows()).map(|bi| {
+            let start = self.offset_block_rows[bi].as_usize();
+            let end = self.offset_block_rows[bi + 1].as_usize();
+            height(bi) * self.block_columns[start..end].iter().map(|bj| width(bj.as_usize())).sum::<usize>()
+        }).sum()
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        let bs = self.block_size;
+        match self.get_block(i / bs, j / bs) {
+            Some(block) => block[(i % bs) * bs + j % bs],
+            None => T::zero(),
+        }
+    }
+
+    // Inserts the whole block containing (i, j) if it does not exist yet
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        let bs = self.block_size;
+        self.n_rows = std::cmp::max(self.n_rows, i + 1);
+        self.n_cols = std::cmp::max(self.n_cols, j + 1);
+        &mut self.block_mut(i / bs, j / bs)[(i % bs) * bs + j % bs]
+    }
+
+    // Adds empty block rows as needed
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        self.n_rows = std::cmp::max(self.n_rows, n_rows);
+        self.n_cols = std::cmp::max(self.n_cols, n_cols);
+        let n_block_rows = self.n_rows.div_ceil(self.block_size);
+        if n_block_rows > self.n_block_rows() {
+            let offset_last = self.offset_block_rows.last().copied().unwrap_or(I::ZERO);
+            self.offset_block_rows.resize(n_block_rows + 1, offset_last);
+        }
+    }
+
+    fn scale(&mut self, rhs: Self::Value) {
+        for iter in self.values.iter_mut() {
+            *iter *= rhs;
+        }
+    }
+
+    // Multiplies each dense block with the corresponding part of the vector
+    // The vectors are padded with zeros to whole blocks
+    fn mvp<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = T> {
+        let bs = self.block_size;
+        let x = (0..self.n_block_cols() * bs).map(|j| if j < self.n_cols { rhs.get(j) } else { T::zero() }).collect::<Vec<T>>();
+        let mut ret = vec![T::zero(); self.n_block_rows() * bs];
+        for (bi, y) in ret.chunks_exact_mut(bs).enumerate() {
+            let start = self.offset_block_rows[bi].as_usize();
+            let end = self.offset_block_rows[bi + 1].as_usize();
+            for k in start..end {
+                let bj = self.block_columns[k].as_usize();
+                let x_block = &x[bj * bs..(bj + 1) * bs];
+                let block = &self.values[k * bs * bs..(k + 1) * bs * bs];
+                for (y_r, block_row) in y.iter_mut().zip(block.chunks_exact(bs)) {
+                    for (&val, &x_c) in block_row.iter().zip(x_block.iter()) {
+                        *y_r += val * x_c;
+                    }
+                }
+            }
+        }
+        ret.truncate(self.n_rows);
+        V::from_vec(ret)
+    }
+}
+
+// Iterator over the entries of a row passing the blocks of its block row
+pub struct IterRow<'a, T, I> {
+    mat: &'a SparseMatBSR<T, I>,
+    row_block: usize,
+    block: usize,
+    block_end: usize,
+    col_block: usize,
+}
+
+impl<'a, T, I> Iterator for IterRow<'a, T, I>
+where I: IndexType {
+    type Item = (&'a I, &'a T);
+
+    // The padding beyond the last column is skipped
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.block < self.block_end {
+            let bs = self.mat.block_size;
+            let index = self.block * bs * bs + self.row_block * bs + self.col_block;
+            self.col_block += 1;
+            if self.col_block == bs {
+                self.col_block = 0;
+                self.block += 1;
+            }
+            if self.mat.columns[index].as_usize() < self.mat.n_cols {
+                return Some((&self.mat.columns[index], &self.mat.values[index]));
+            }
+        }
+        None
+    }
+}
+
+// Block Jacobi preconditioner using the inverses of the diagonal blocks
+pub struct BlockJacobi<T> {
+    block_size: usize,
+    dim: usize,
+    inv_blocks: Vec<T>,
+}
+
+impl<T> BlockJacobi<T>
+where T: FloatType {
+    // Inverts the diagonal blocks of the matrix
+    // The padding of a partial last block is completed by the identity
+    // Returns an error if the matrix is not square or a diagonal block is singular
+    pub fn new<I>(mat: &SparseMatBSR<T, I>) -> Result<Self, SparseMatError>
+    where I: IndexType {
+        if mat.n_rows() != mat.n_cols() {
+            return Err(SparseMatError::new("Matrix is not square"));
+        }
+        let bs = mat.block_size();
+        let mut inv_blocks = Vec::<T>::with_capacity(mat.n_block_rows() * bs * bs);
+        for bi in 0..mat.n_block_rows() {
+            let mut block = match mat.get_block(bi, bi) {
+                Some(block) => block.to_vec(),
+                None => return Err(SparseMatError::new(&format!("Missing diagonal block {}", bi))),
+            };
+            for k in (mat.n_rows() - bi * bs)..bs {
+                block[k * bs + k] = T::one();
+            }
+            match invert_block(block, bs) {
+                Some(inv) => inv_blocks.extend(inv),
+                None => return Err(SparseMatError::new(&format!("Singular diagonal block {}", bi))),
+            }
+        }
+        Ok(Self {
+            block_size: bs,
+            dim: mat.n_rows(),
+            inv_blocks,
+        })
+    }
+}
+
+impl<T> Preconditioner<T> for BlockJacobi<T>
+where T: FloatType {
+    fn apply(&self, r: &DenseVec<T>) -> DenseVec<T> {
+        let bs = self.block_size;
+        if r.dim() != self.dim {
+            panic!("Dimension mismatch");
+        }
+        let mut z = vec![T::zero(); self.dim];
+        for (bi, inv) in self.inv_blocks.chunks_exact(bs * bs).enumerate() {
+            // Rows and columns of the block within the matrix
+            let n = std::cmp::min(bs, self.dim - bi * bs);
+            for (row, inv_row) in inv.chunks_exact(bs).take(n).enumerate() {
+                let mut sum = T::zero();
+                for (col, &val) in inv_row.iter().take(n).enumerate() {
+                    sum += val * r.get(bi * bs + col);
+                }
+                z[bi * bs + row] = sum;
+            }
+        }
+        DenseVec::from_vec(z)
+    }
+}
+
+// Inverts a dense row-major block with Gauss-Jordan elimination and partial pivoting
+fn invert_block<T>(mut block: Vec<T>, n: usize) -> Option<Vec<T>>
+where T: FloatType {
+    let mut inv = vec![T::zero(); n * n];
+    for k in 0..n {
+        inv[k * n + k] = T::one();
+    }
+    for k in 0..n {
+        let pivot_row = (k..n).max_by(|&r1, &r2| block[r1 * n + k].abs().partial_cmp(&block[r2 * n + k].abs()).unwrap())?;
+        if block[pivot_row * n + k] == T::zero() {
+            return None;
+        }
+        for col in 0..n {
+            block.swap(k * n + col, pivot_row * n + col);
+            inv.swap(k * n + col, pivot_row * n + col);
+        }
+        let pivot = block[k * n + k];
+        for col in 0..n {
+            block[k * n + col] = block[k * n + col] / pivot;
+            inv[k * n + col] = inv[k * n + col] / pivot;
+        }
+        for row in (0..n).filter(|&row| row != k) {
+            let factor = block[row * n + k];
+            if factor != T::zero() {
+                for col in 0..n {
+                    let (val_block, val_inv) = (block[k * n + col], inv[k * n + col]);
+                    block[row * n + col] -= factor * val_block;
+                    inv[row * n + col] -= factor * val_inv;
+                }
+            }
+        }
+    }
+    Some(inv)
+}
+
+sparsemat_ops!(SparseMatBSR);
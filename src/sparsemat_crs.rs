@@ -1,19 +1,35 @@
+use std::convert::TryInto;
 use crate::types::{IndexType, ValueType};
 use crate::indexlist::IndexList;
 use crate::sparsematrix::*;
 use crate::sparsemat_indexlist::*;
+use crate::sparsevec::SparseVec;
+use crate::vector::Vector;
 use crate::densevec::DenseVec;
+use crate::densemat::DenseMat;
 
 // Implementation of a sparse matrix with compressed row storage format
+// If rows_sorted is set all rows are sorted by columns, which allows a binary search
+// for the entries and keeps the rows sorted on insertion
+// In slack mode each row may hold free positions at its end for cheap insertions:
+// Row i then occupies offset_rows[i]..offset_rows[i + 1] of the storage,
+// but only the positions offset_rows[i]..row_ends[i] are actual entries
+// Example: offset_rows = [0, 3, 5], row_ends = [2, 4]
+//          columns     = [0, 2, *, 1, *]
+// The vec row_ends is empty and slack is zero if the storage is packed
 #[derive(Clone, Debug)]
 pub struct SparseMatCRS<T, I> {
     n_rows: usize,
     n_cols: usize,
+    nnz: usize,
     values: Vec<T>,
     columns: Vec<I>,
     offset_rows: Vec<I>,
+    row_ends: Vec<I>,
+    slack: usize,
     rows: Vec<I>,
     indexlist_col: IndexList<I>,
+    rows_sorted: bool,
 }
 
 impl<T, I> SparseMatCRS<T, I>
@@ -35,45 +51,439 @@ where T: ValueType,
             }
             offset_rows.push(I::as_indextype(columns.len()));
             let (rows, indexlist_column) = rhs.column_info();
+            let rows_sorted = Self::check_rows_sorted(&offset_rows, &columns);
             SparseMatCRS::<T, I> {
                 n_rows: rhs.n_rows(),
                 n_cols: rhs.n_cols(),
-                values: values,
-                columns: columns,
-                offset_rows: offset_rows,
-                rows: rows,
+                nnz: columns.len(),
+                values,
+                columns,
+                offset_rows,
+                row_ends: Vec::<I>::new(),
+                slack: 0,
+                rows,
                 indexlist_col: indexlist_column,
+                rows_sorted,
             }
         } else {
             SparseMatCRS::<T, I>::new()
         }
     }
 
+    // Creates a matrix directly from the CRS arrays without any checks
+    // Only the sorting of the rows is checked
+    pub(crate) fn from_raw(n_rows: usize, n_cols: usize, offset_rows: Vec<I>, columns: Vec<I>, values: Vec<T>) -> Self {
+        let rows_sorted = Self::check_rows_sorted(&offset_rows, &columns);
+        SparseMatCRS::<T, I> {
+            n_rows,
+            n_cols,
+            nnz: columns.len(),
+            values,
+            columns,
+            offset_rows,
+            row_ends: Vec::<I>::new(),
+            slack: 0,
+            rows: Vec::<I>::new(),
+            indexlist_col: IndexList::<I>::new(),
+            rows_sorted,
+        }
+    }
+
+    // Creates a matrix from the CRS arrays after validating their structure
+    pub(crate) fn try_from_raw(n_rows: usize, n_cols: usize, offset_rows: Vec<I>, columns: Vec<I>, values: Vec<T>) -> Result<Self, SparseMatError> {
+        check_raw(n_rows, n_cols, &offset_rows, &columns, values.len())?;
+        Ok(Self::from_raw(n_rows, n_cols, offset_rows, columns, values))
+    }
+
+    // Creates a matrix from coordinates by a counting sort of the entries by rows
+    // The order of the entries within each row is kept and duplicates are not merged
+    pub(crate) fn try_from_triplets(n_rows: usize, n_cols: usize, rows: &[usize], cols: &[I], values: &[T]) -> Result<Self, SparseMatError> {
+        if rows.len() != values.len() || cols.len() != values.len() {
+            return Err(SparseMatError::new("Size mismatch of the coordinate arrays"));
+        }
+        if rows.iter().any(|&row| row >= n_rows) {
+            return Err(SparseMatError::new("Row index out of bounds"));
+        }
+        let mut offsets = vec![0usize; n_rows + 1];
+        for &row in rows.iter() {
+            offsets[row + 1] += 1;
+        }
+        for i in 0..n_rows {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut next = offsets.clone();
+        let mut columns = vec![I::ZERO; values.len()];
+        let mut vals = vec![T::zero(); values.len()];
+        for (k, &row) in rows.iter().enumerate() {
+            columns[next[row]] = cols[k];
+            vals[next[row]] = values[k];
+            next[row] += 1;
+        }
+        let offset_rows = offsets.into_iter().map(I::as_indextype).collect();
+        Self::try_from_raw(n_rows, n_cols, offset_rows, columns, vals)
+    }
+
+    // Checks if the columns of each row are in ascending order
+    fn check_rows_sorted(offset_rows: &[I], columns: &[I]) -> bool {
+        offset_rows.windows(2).all(|offsets| {
+            let row = &columns[offsets[0].as_usize()..offsets[1].as_usize()];
+            row.windows(2).all(|cols| cols[0] < cols[1])
+        })
+    }
+
+    // Returns true if all rows are known to be sorted by columns
+    // The flag is set by sort() and by operations creating sorted rows like transpose()
+    pub fn rows_sorted(&self) -> bool {
+        self.rows_sorted
+    }
+
+    // Product with a row-major block of K vectors - rhs and ret hold K values per row
+    fn mvp_multi_fixed<const K: usize>(&self, rhs: &[T], ret: &mut [T]) {
+        for (i, ret_i) in ret.chunks_exact_mut(K).enumerate() {
+            let mut acc = [T::zero(); K];
+            let (start, end) = self.row_range(i);
+            for (col, &val) in self.columns[start..end].iter().zip(self.values[start..end].iter()) {
+                let offset = col.as_usize() * K;
+                let x: &[T; K] = rhs[offset..offset + K].try_into().unwrap();
+                for (a, &x_c) in acc.iter_mut().zip(x.iter()) {
+                    *a += val * x_c;
+                }
+            }
+            ret_i.copy_from_slice(&acc);
+        }
+    }
+
+    // Product with a row-major block of k vectors accumulating directly into the rows of ret
+    fn mvp_multi_general(&self, k: usize, rhs: &[T], ret: &mut [T]) {
+        if k == 0 {
+            return;
+        }
+        for (i, ret_i) in ret.chunks_exact_mut(k).enumerate() {
+            let (start, end) = self.row_range(i);
+            for (col, &val) in self.columns[start..end].iter().zip(self.values[start..end].iter()) {
+                let offset = col.as_usize() * k;
+                for (r, &x_c) in ret_i.iter_mut().zip(rhs[offset..offset + k].iter()) {
+                    *r += val * x_c;
+                }
+            }
+        }
+    }
+
+    // Returns the range of the entries of row i in the columns and values vec
+    #[inline]
+    fn row_range(&self, i: usize) -> (usize, usize) {
+        let start = self.offset_rows[i].as_usize();
+        if self.slack == 0 {
+            (start, self.offset_rows[i + 1].as_usize())
+        } else {
+            (start, self.row_ends[i].as_usize())
+        }
+    }
+
+    // Switches to slack mode, where each row reserves at least slack free positions
+    // for new entries - inserting into a row with free positions takes O(row length)
+    // If a row runs full, its reservation is doubled by moving the following rows into
+    // their free positions, so the cost of insertions is amortised
+    // Use finalize() to pack the storage again after the insertions
+    pub fn reserve_slack(&mut self, slack: usize) {
+        if !self.has_slack() {
+            self.row_ends = self.offset_rows.iter().skip(1).copied().collect();
+        }
+        self.slack = std::cmp::max(slack, 1);
+        self.relayout();
+    }
+
+    // Returns true if the matrix is in slack mode
+    pub fn has_slack(&self) -> bool {
+        self.slack > 0
+    }
+
+    // Packs the storage removing all free positions and leaves the slack mode
+    pub fn finalize(&mut self) {
+        if !self.has_slack() {
+            return;
+        }
+        let mut pos = 0;
+        for i in 0..self.n_rows() {
+            let (start, end) = self.row_range(i);
+            self.columns.copy_within(start..end, pos);
+            self.values.copy_within(start..end, pos);
+            self.offset_rows[i] = I::as_indextype(pos);
+            pos += end - start;
+        }
+        let n_rows = self.n_rows();
+        self.offset_rows[n_rows] = I::as_indextype(pos);
+        self.columns.truncate(pos);
+        self.values.truncate(pos);
+        self.row_ends.clear();
+        self.slack = 0;
+    }
+
+    // Rebuilds the storage in slack mode
+    // Each row gets max(slack, row length / 2) free positions
+    fn relayout(&mut self) {
+        let mut capacity = 0;
+        for i in 0..self.n_rows() {
+            let (start, end) = self.row_range(i);
+            capacity += (end - start) + std::cmp::max(self.slack, (end - start) / 2);
+        }
+        let mut values = Vec::<T>::with_capacity(capacity);
+        let mut columns = Vec::<I>::with_capacity(capacity);
+        let mut offset_rows = Vec::<I>::with_capacity(self.n_rows() + 1);
+        let mut row_ends = Vec::<I>::with_capacity(self.n_rows());
+        for i in 0..self.n_rows() {
+            let (start, end) = self.row_range(i);
+            offset_rows.push(I::as_indextype(columns.len()));
+            columns.extend_from_slice(&self.columns[start..end]);
+            values.extend_from_slice(&self.values[start..end]);
+            row_ends.push(I::as_indextype(columns.len()));
+            let free = std::cmp::max(self.slack, (end - start) / 2);
+            columns.resize(columns.len() + free, I::ZERO);
+            values.resize(values.len() + free, T::zero());
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        self.values = values;
+        self.columns = columns;
+        self.offset_rows = offset_rows;
+        self.row_ends = row_ends;
+    }
+
+    // Grows the reservation of the full row i by max(slack, row length) positions in slack mode
+    // Only the following rows up to the first ones providing enough free positions are moved,
+    // they are packed and the last moved row keeps the remaining free positions
+    // If the free positions of all following rows do not suffice, the storage is extended at the end
+    fn grow_row(&mut self, i: usize) {
+        let (start, end) = self.row_range(i);
+        let extra = std::cmp::max(self.slack, end - start);
+        let mut free = 0;
+        let mut k = i + 1;
+        while k < self.n_rows() && free < extra {
+            free += self.offset_rows[k + 1].as_usize() - self.row_ends[k].as_usize();
+            k += 1;
+        }
+        if free < extra {
+            let len = self.columns.len() + extra - free;
+            self.columns.resize(len, I::ZERO);
+            self.values.resize(len, T::zero());
+            let n_rows = self.n_rows();
+            self.offset_rows[n_rows] = I::as_indextype(len);
+        }
+        // New offsets of the moved rows i + 1..k, which only move towards the end
+        let mut offsets = Vec::<usize>::with_capacity(k - i - 1);
+        let mut pos = self.offset_rows[i + 1].as_usize() + extra;
+        for r in (i + 1)..k {
+            offsets.push(pos);
+            let (start_r, end_r) = self.row_range(r);
+            pos += end_r - start_r;
+        }
+        for r in ((i + 1)..k).rev() {
+            let (start_r, end_r) = self.row_range(r);
+            let offset = offsets[r - i - 1];
+            self.columns.copy_within(start_r..end_r, offset);
+            self.values.copy_within(start_r..end_r, offset);
+            self.offset_rows[r] = I::as_indextype(offset);
+            self.row_ends[r] = I::as_indextype(offset + end_r - start_r);
+        }
+    }
+
+    // Appends empty rows with free positions at the end of the storage in slack mode
+    fn push_rows_slack(&mut self, n_rows: usize) {
+        if self.offset_rows.is_empty() {
+            self.offset_rows.push(I::ZERO);
+        }
+        for _ in self.n_rows..n_rows {
+            self.row_ends.push(I::as_indextype(self.columns.len()));
+            self.columns.resize(self.columns.len() + self.slack, I::ZERO);
+            self.values.resize(self.values.len() + self.slack, T::zero());
+            self.offset_rows.push(I::as_indextype(self.columns.len()));
+        }
+        self.n_rows = n_rows;
+    }
+
+    // Adds a value at entry (i, j) in slack mode
+    // New entries are appended to the row unless the rows are sorted
+    fn push_slack(&mut self, i: usize, j: usize, val: T) -> usize {
+        if i >= self.n_rows() {
+            self.push_rows_slack(i + 1);
+        }
+        if self.row_ends[i] == self.offset_rows[i + 1] {
+            self.grow_row(i);
+        }
+        let (start, end) = self.row_range(i);
+        if end >= Self::UNSET.as_usize() {
+            panic!("Maximum number of {} entries reached", Self::UNSET);
+        }
+        let mut index = end;
+        if self.rows_sorted {
+            index = start + self.sorted_position(start, end, j);
+            self.columns.copy_within(index..end, index + 1);
+            self.values.copy_within(index..end, index + 1);
+        }
+        self.columns[index] = I::as_indextype(j);
+        self.values[index] = val;
+        self.row_ends[i] += I::ONE;
+        index
+    }
+
+    // Creates a matrix in CRS format from any other sparse matrix
+    pub fn from_sparsemat<'b, M>(rhs: &'b M) -> Self
+    where M: SparseMatrix<'b, Value = T, Index = I>,
+          T: 'b,
+          I: 'b {
+        let mut values = Vec::<T>::with_capacity(rhs.n_non_zero_entries());
+        let mut columns = Vec::<I>::with_capacity(rhs.n_non_zero_entries());
+        let mut offset_rows = Vec::<I>::with_capacity(rhs.n_rows() + 1);
+        for i in 0..rhs.n_rows() {
+            offset_rows.push(I::as_indextype(columns.len()));
+            for (&j, &val) in rhs.iter_row(i) {
+                columns.push(j);
+                values.push(val);
+            }
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        Self::from_raw(rhs.n_rows(), rhs.n_cols(), offset_rows, columns, values)
+    }
+
+    // Sparse matrix-matrix product using Gustavson's row-wise algorithm
+    // The entries of each row of the result are sorted by columns
+    pub fn spgemm(&self, rhs: &Self) -> Result<Self, SparseMatError> {
+        if self.n_cols() > rhs.n_rows() {
+            return Err(SparseMatError::new("Dimension mismatch"));
+        }
+        let n_cols = rhs.n_cols();
+        let mut values = Vec::<T>::with_capacity(self.n_non_zero_entries());
+        let mut columns = Vec::<I>::with_capacity(self.n_non_zero_entries());
+        let mut offset_rows = Vec::<I>::with_capacity(self.n_rows() + 1);
+        // Dense accumulator and the positions of the non-zero entries of the current row
+        let mut acc = vec![T::zero(); n_cols];
+        let mut occupied = vec![false; n_cols];
+        let mut cols_row = Vec::<usize>::new();
+        for i in 0..self.n_rows() {
+            offset_rows.push(I::as_indextype(columns.len()));
+            for (&k, &val_lhs) in self.iter_row(i) {
+                for (&col, &val_rhs) in rhs.iter_row(k.as_usize()) {
+                    let j = col.as_usize();
+                    if !occupied[j] {
+                        occupied[j] = true;
+                        cols_row.push(j);
+                    }
+                    acc[j] += val_lhs * val_rhs;
+                }
+            }
+            cols_row.sort_unstable();
+            for &j in cols_row.iter() {
+                columns.push(I::as_indextype(j));
+                values.push(acc[j]);
+                acc[j] = T::zero();
+                occupied[j] = false;
+            }
+            cols_row.clear();
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        Ok(Self::from_raw(self.n_rows(), n_cols, offset_rows, columns, values))
+    }
+
+    // Returns the row offsets of the transpose and the target position of each entry
+    // computed with a counting sort over the columns
+    // Free positions in slack mode are not part of the permutation
+    fn transpose_positions(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut offset_cols = vec![0usize; self.n_cols() + 1];
+        for (&col, _val) in self.iter_all() {
+            offset_cols[col.as_usize() + 1] += 1;
+        }
+        for j in 0..self.n_cols() {
+            offset_cols[j + 1] += offset_cols[j];
+        }
+        let mut next = offset_cols.clone();
+        let mut perm = vec![0usize; self.columns.len()];
+        for i in 0..self.n_rows() {
+            let (start, end) = self.row_range(i);
+            for (pos, col) in perm[start..end].iter_mut().zip(self.columns[start..end].iter()) {
+                let j = col.as_usize();
+                *pos = next[j];
+                next[j] += 1;
+            }
+        }
+        (offset_cols, perm)
+    }
+
+    // Iterates over the entries of all rows skipping the free positions in slack mode
+    fn iter_all(&self) -> impl Iterator<Item = (&I, &T)> {
+        (0..self.n_rows()).flat_map(move |i| {
+            let (start, end) = self.row_range(i);
+            self.columns[start..end].iter().zip(self.values[start..end].iter())
+        })
+    }
+
+    // Transposes the matrix reusing the storage of the columns and values
+    // Only a permutation vec is allocated, the entries are moved by following its cycles
+    // The rows of the result are sorted
+    // The storage is packed first in slack mode
+    pub fn transpose_in_place(&mut self) {
+        self.finalize();
+        let (offset_cols, mut perm) = self.transpose_positions();
+        // Replace the column of each entry by its row
+        for i in 0..self.n_rows() {
+            let start = self.offset_rows[i].as_usize();
+            let end = self.offset_rows[i + 1].as_usize();
+            for col in self.columns[start..end].iter_mut() {
+                *col = I::as_indextype(i);
+            }
+        }
+        permute_in_place(&mut perm, &mut self.columns, &mut self.values);
+        self.offset_rows.clear();
+        self.offset_rows.extend(offset_cols.iter().map(|&offset| I::as_indextype(offset)));
+        std::mem::swap(&mut self.n_rows, &mut self.n_cols);
+        self.rows_sorted = true;
+        // The column info is not valid anymore
+        self.rows.clear();
+        self.indexlist_col = IndexList::<I>::new();
+    }
+
     // Returns the offset for the columns and values vec
     // or UNSET if entry (i, j) does not exist
     fn find_index(&self, i: usize, j: usize) -> usize {
         let mut ret = Self::UNSET.as_usize();
         if i < self.n_rows() {
-            let start = self.offset_rows[i].as_usize();
-            let end = self.offset_rows[i + 1].as_usize();
-            for index in start..end {
-                if self.columns[index].as_usize() == j {
+            let (start, end) = self.row_range(i);
+            if self.rows_sorted {
+                let index = start + self.sorted_position(start, end, j);
+                if index < end && self.columns[index].as_usize() == j {
                     ret = index;
-                    break;
+                }
+            } else {
+                for index in start..end {
+                    if self.columns[index].as_usize() == j {
+                        ret = index;
+                        break;
+                    }
                 }
             }
         }
         ret
     }
-    
+
+    // Binary search for the first position in the sorted range start..end with a column >= j
+    fn sorted_position(&self, start: usize, end: usize, j: usize) -> usize {
+        self.columns[start..end].partition_point(|col| col.as_usize() < j)
+    }
+
     // Adds a value at entry (i, j) without check
     // This is very inefficient since we use insert of Vec here
+    // New entries are placed at the start of the row unless the rows are sorted
     fn push(&mut self, i: usize, j: usize, val: T) -> usize {
         if j >= self.n_cols {
             self.n_cols = j + 1;
         }
-        if self.offset_rows.len() == 0 {
+        self.nnz += 1;
+        // The column info is not valid anymore
+        self.rows.clear();
+        if self.has_slack() {
+            return self.push_slack(i, j, val);
+        }
+        if self.offset_rows.is_empty() {
             self.offset_rows.resize(i + 2, I::ZERO);
+            self.n_rows = i + 1;
         } else if i >= self.n_rows() {
             let offset_last = self.offset_rows[self.offset_rows.len() - 1];
             self.offset_rows.resize(i + 2, offset_last);
@@ -82,7 +492,10 @@ where T: ValueType,
         if self.offset_rows[i] == Self::UNSET {
             panic!("Maximum number of {} entries reached", Self::UNSET);
         }
-        let index = self.offset_rows[i].as_usize();
+        let mut index = self.offset_rows[i].as_usize();
+        if self.rows_sorted {
+            index += self.sorted_position(index, self.offset_rows[i + 1].as_usize(), j);
+        }
         self.columns.insert(index, I::as_indextype(j));
         self.values.insert(index, val);
         for k in (i + 1)..self.offset_rows.len() {
@@ -90,6 +503,210 @@ where T: ValueType,
         }
         index
     }
+
+    // Checks if the matrix fits into a triangular system of dimension dim
+    fn check_triangular_dim(&self, dim: usize) -> Result<usize, SparseMatError> {
+        if self.n_rows() > dim || self.n_cols() > dim {
+            return Err(SparseMatError::new("Matrix and vector size mismatch"));
+        }
+        Ok(dim)
+    }
+
+    // Divides by the pivot of row i or skips the division for a unit diagonal
+    fn divide_pivot(sum: T, pivot: T, i: usize, unit: bool) -> Result<T, SparseMatError> {
+        if unit {
+            Ok(sum)
+        } else if pivot == T::zero() {
+            Err(SparseMatError::new(&format!("Zero pivot in row {}", i)))
+        } else {
+            Ok(sum / pivot)
+        }
+    }
+
+    // Forward substitution using the entries with j <= i
+    fn substitute_lower(&self, b: &DenseVec<T>, unit: bool) -> Result<DenseVec<T>, SparseMatError> {
+        let n = self.check_triangular_dim(b.dim())?;
+        let mut x = b.clone();
+        for i in 0..n {
+            let mut sum = b.get(i);
+            let mut pivot = T::zero();
+            for (&col, &val) in self.iter_row(i) {
+                let j = col.as_usize();
+                if j < i {
+                    sum -= val * x.get(j);
+                } else if j == i {
+                    pivot = val;
+                }
+            }
+            x.set(i, Self::divide_pivot(sum, pivot, i, unit)?);
+        }
+        Ok(x)
+    }
+
+    // Backward substitution using the entries with j >= i
+    fn substitute_upper(&self, b: &DenseVec<T>, unit: bool) -> Result<DenseVec<T>, SparseMatError> {
+        let n = self.check_triangular_dim(b.dim())?;
+        let mut x = b.clone();
+        for i in (0..n).rev() {
+            let mut sum = b.get(i);
+            let mut pivot = T::zero();
+            for (&col, &val) in self.iter_row(i) {
+                let j = col.as_usize();
+                if j > i {
+                    sum -= val * x.get(j);
+                } else if j == i {
+                    pivot = val;
+                }
+            }
+            x.set(i, Self::divide_pivot(sum, pivot, i, unit)?);
+        }
+        Ok(x)
+    }
+
+    // Backward substitution with the transpose of the lower part
+    // The rows of L are the columns of the transpose, so the solution is scattered row by row
+    fn substitute_lower_transpose(&self, b: &DenseVec<T>, unit: bool) -> Result<DenseVec<T>, SparseMatError> {
+        let n = self.check_triangular_dim(b.dim())?;
+        let mut x = b.clone();
+        for i in (0..n).rev() {
+            let x_i = Self::divide_pivot(x.get(i), self.get(i, i), i, unit)?;
+            x.set(i, x_i);
+            for (&col, &val) in self.iter_row(i) {
+                let j = col.as_usize();
+                if j < i {
+                    *x.get_mut(j) -= val * x_i;
+                }
+            }
+        }
+        Ok(x)
+    }
+
+    // Solves L * x = b where L is the lower triangular part of this matrix
+    // Entries above the diagonal are ignored
+    pub fn solve_lower(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        self.substitute_lower(b, false)
+    }
+
+    // Solves L * x = b assuming a unit diagonal - stored diagonal entries are ignored
+    pub fn solve_lower_unit(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        self.substitute_lower(b, true)
+    }
+
+    // Solves U * x = b where U is the upper triangular part of this matrix
+    // Entries below the diagonal are ignored
+    pub fn solve_upper(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        self.substitute_upper(b, false)
+    }
+
+    // Solves U * x = b assuming a unit diagonal - stored diagonal entries are ignored
+    pub fn solve_upper_unit(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        self.substitute_upper(b, true)
+    }
+
+    // Solves L^T * x = b without forming the transpose of the lower triangular part
+    pub fn solve_lower_transpose(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        self.substitute_lower_transpose(b, false)
+    }
+
+    // Solves L^T * x = b assuming a unit diagonal
+    pub fn solve_lower_transpose_unit(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        self.substitute_lower_transpose(b, true)
+    }
+
+    // Returns the entries of x reachable from the non-zero entries of b
+    // in the graph of L in topological order (Gilbert-Peierls)
+    // The edges j -> i are given by the entries below the diagonal in column j
+    fn reach_lower(&self, b: &SparseVec<T, I>, n: usize) -> Result<Vec<usize>, SparseMatError> {
+        let mut visited = vec![false; n];
+        let mut post_order = Vec::<usize>::new();
+        let mut stack = Vec::<(usize, IterCol<T, I>)>::new();
+        for (&ind, _val) in b.iter_sparse() {
+            let start = ind.as_usize();
+            if start >= n {
+                return Err(SparseMatError::new("Matrix and vector size mismatch"));
+            }
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            stack.push((start, self.iter_col(start)?));
+            // Depth-first search storing the nodes in post order
+            while let Some((node, iter)) = stack.last_mut() {
+                let node = *node;
+                match iter.find(|(&row, _val)| row.as_usize() > node && !visited[row.as_usize()]) {
+                    Some((&row, _val)) => {
+                        let i = row.as_usize();
+                        visited[i] = true;
+                        let iter_i = self.iter_col(i)?;
+                        stack.push((i, iter_i));
+                    },
+                    None => {
+                        post_order.push(node);
+                        stack.pop();
+                    },
+                }
+            }
+        }
+        post_order.reverse();
+        Ok(post_order)
+    }
+
+    fn substitute_lower_sparse(&self, b: &SparseVec<T, I>, unit: bool) -> Result<SparseVec<T, I>, SparseMatError> {
+        let n = std::cmp::max(self.n_rows(), self.n_cols());
+        let mut reach = self.reach_lower(b, n)?;
+        let mut x = vec![T::zero(); n];
+        for (&ind, &val) in b.iter_sparse() {
+            x[ind.as_usize()] += val;
+        }
+        for &j in reach.iter() {
+            x[j] = Self::divide_pivot(x[j], self.get(j, j), j, unit)?;
+            let x_j = x[j];
+            for (&row, &val) in self.iter_col(j)? {
+                let i = row.as_usize();
+                if i > j {
+                    x[i] -= val * x_j;
+                }
+            }
+        }
+        reach.sort_unstable();
+        let indices = reach.iter().map(|&j| I::as_indextype(j)).collect::<Vec<I>>();
+        let values = reach.iter().map(|&j| x[j]).collect::<Vec<T>>();
+        Ok(SparseVec::from_parts(indices, values, n))
+    }
+
+    // Solves L * x = b for a sparse right hand side
+    // Only the entries of x reachable from the non-zero entries of b are computed,
+    // which requires the column info - use assemble_column_info() first
+    pub fn solve_lower_sparse(&self, b: &SparseVec<T, I>) -> Result<SparseVec<T, I>, SparseMatError> {
+        self.substitute_lower_sparse(b, false)
+    }
+
+    // Solves L * x = b for a sparse right hand side assuming a unit diagonal
+    pub fn solve_lower_unit_sparse(&self, b: &SparseVec<T, I>) -> Result<SparseVec<T, I>, SparseMatError> {
+        self.substitute_lower_sparse(b, true)
+    }
+}
+
+// Validates the structure of the CRS arrays, so no index can be out of bounds
+pub(crate) fn check_raw<I>(n_rows: usize, n_cols: usize, offset_rows: &[I], columns: &[I], n_values: usize) -> Result<(), SparseMatError>
+where I: IndexType {
+    // An empty matrix may not hold any offsets
+    if offset_rows.is_empty() && n_rows == 0 && columns.is_empty() && n_values == 0 {
+        return Ok(());
+    }
+    if offset_rows.len().checked_sub(1) != Some(n_rows) || columns.len() != n_values {
+        return Err(SparseMatError::new("Size mismatch of the CRS arrays"));
+    }
+    if offset_rows[0].as_usize() != 0 || offset_rows[n_rows].as_usize() != columns.len() {
+        return Err(SparseMatError::new("Invalid row offsets"));
+    }
+    if offset_rows.windows(2).any(|offsets| offsets[0] > offsets[1]) {
+        return Err(SparseMatError::new("Row offsets are not ascending"));
+    }
+    if let Some(col) = columns.iter().find(|col| col.as_usize() >= n_cols) {
+        return Err(SparseMatError::new(&format!("Column {} out of bounds", col)));
+    }
+    Ok(())
 }
 
 impl<'a, T, I> SparseMatrix<'a> for SparseMatCRS<T, I>
@@ -101,8 +718,7 @@ where T: 'a + ValueType,
 
     fn iter_row(&'a self, row: usize) -> Self::IterRow {
         if row < self.n_rows() {
-            let start = self.offset_rows[row].as_usize();
-            let end = self.offset_rows[row + 1].as_usize();
+            let (start, end) = self.row_range(row);
             self.columns[start..end].iter().zip(self.values[start..end].iter())
         } else {
             self.columns[0..0].iter().zip(self.values[0..0].iter())
@@ -113,11 +729,15 @@ where T: 'a + ValueType,
         Self {
             n_rows: 0,
             n_cols: 0,
+            nnz: 0,
             values: Vec::<T>::with_capacity(cap),
             columns: Vec::<I>::with_capacity(cap),
             offset_rows: Vec::<I>::with_capacity(cap + 1),
+            row_ends: Vec::<I>::new(),
+            slack: 0,
             rows: Vec::<I>::new(),
             indexlist_col: IndexList::<I>::new(),
+            rows_sorted: false,
         }
     }
 
@@ -130,7 +750,7 @@ where T: 'a + ValueType,
     }
     
     fn n_non_zero_entries(&self) -> usize {
-        self.columns.len()
+        self.nnz
     }
 
     fn get(&self, i: usize, j: usize) -> T {
@@ -150,11 +770,110 @@ where T: 'a + ValueType,
         &mut self.values[index]
     }
 
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        if n_rows > self.n_rows {
+            if self.has_slack() {
+                self.push_rows_slack(n_rows);
+            } else {
+                let offset_last = self.offset_rows.last().copied().unwrap_or(I::ZERO);
+                self.offset_rows.resize(n_rows + 1, offset_last);
+                self.n_rows = n_rows;
+            }
+        }
+        self.n_cols = std::cmp::max(self.n_cols, n_cols);
+        // The column info is not valid anymore
+        self.rows.clear();
+    }
+
     fn scale(&mut self, rhs: Self::Value) {
         for iter in self.values.iter_mut() {
             *iter *= rhs;
         }
     }
+
+    // Builds the arrays row by row keeping the dimensions of the dense matrix
+    fn from_dense(dense: &DenseMat<T>, tol: f64) -> Self {
+        let mut offset_rows = Vec::<I>::with_capacity(dense.n_rows() + 1);
+        let mut columns = Vec::<I>::new();
+        let mut values = Vec::<T>::new();
+        offset_rows.push(I::ZERO);
+        for i in 0..dense.n_rows() {
+            for (j, &val) in dense.row(i).iter().enumerate() {
+                if val.into().abs() > tol {
+                    columns.push(I::as_indextype(j));
+                    values.push(val);
+                }
+            }
+            offset_rows.push(I::as_indextype(columns.len()));
+        }
+        Self::from_raw(dense.n_rows(), dense.n_cols(), offset_rows, columns, values)
+    }
+
+    // Counting sort over the columns builds the transpose in O(nnz)
+    // The rows of the result are sorted
+    fn transpose(&'a self) -> Self {
+        let (offset_cols, perm) = self.transpose_positions();
+        let mut columns = vec![I::ZERO; self.nnz];
+        let mut values = vec![T::zero(); self.nnz];
+        for i in 0..self.n_rows() {
+            let (start, end) = self.row_range(i);
+            for index in start..end {
+                columns[perm[index]] = I::as_indextype(i);
+                values[perm[index]] = self.values[index];
+            }
+        }
+        let offset_rows = offset_cols.iter().map(|&offset| I::as_indextype(offset)).collect();
+        Self::from_raw(self.n_cols(), self.n_rows(), offset_rows, columns, values)
+    }
+
+    // Scatters the rows directly into a dense accumulator
+    fn mvp_transpose<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = T> {
+        let mut ret = vec![T::zero(); self.n_cols()];
+        for i in 0..self.n_rows() {
+            let x_i = rhs.get(i);
+            let (start, end) = self.row_range(i);
+            for (col, &val) in self.columns[start..end].iter().zip(self.values[start..end].iter()) {
+                ret[col.as_usize()] += val * x_i;
+            }
+        }
+        V::from_vec(ret)
+    }
+
+    // Small blocks use accumulators of fixed size for each row, which the compiler unrolls
+    fn mvp_multi(&'a self, rhs: &DenseMat<T>) -> Result<DenseMat<T>, SparseMatError> {
+        if self.n_cols() != rhs.n_rows() {
+            return Err(SparseMatError::new("Dimension mismatch"));
+        }
+        let mut ret = DenseMat::<T>::zeros(self.n_rows(), rhs.n_cols());
+        match rhs.n_cols() {
+            1 => self.mvp_multi_fixed::<1>(rhs.as_slice(), ret.as_mut_slice()),
+            2 => self.mvp_multi_fixed::<2>(rhs.as_slice(), ret.as_mut_slice()),
+            3 => self.mvp_multi_fixed::<3>(rhs.as_slice(), ret.as_mut_slice()),
+            4 => self.mvp_multi_fixed::<4>(rhs.as_slice(), ret.as_mut_slice()),
+            8 => self.mvp_multi_fixed::<8>(rhs.as_slice(), ret.as_mut_slice()),
+            k => self.mvp_multi_general(k, rhs.as_slice(), ret.as_mut_slice()),
+        }
+        Ok(ret)
+    }
+
+    fn mvp_add<V>(&'a self, alpha: T, rhs: &V, beta: T, ret: &mut V)
+    where V: Vector<'a, Value = T> {
+        for i in 0..self.n_rows() {
+            let (start, end) = self.row_range(i);
+            let mut sum = T::zero();
+            for (col, &val) in self.columns[start..end].iter().zip(self.values[start..end].iter()) {
+                sum += rhs.get(col.as_usize()) * val;
+            }
+            let y = ret.get_mut(i);
+            if beta == T::zero() {
+                *y = alpha * sum;
+            } else {
+                *y *= beta;
+                *y += alpha * sum;
+            }
+        }
+    }
 }
 
 impl<'a, T, I> Sortable<'a> for SparseMatCRS<T, I>
@@ -170,6 +889,13 @@ where T: 'a + ValueType,
             self.values[index] = *val;
         }
     }
+
+    fn sort(&mut self) {
+        for i in 0..self.n_rows() {
+            self.sort_row(i);
+        }
+        self.rows_sorted = true;
+    }
 }
 
 impl<'a, T, I> ColumnIter<'a> for SparseMatCRS<T, I>
@@ -177,7 +903,11 @@ where T: 'a + ValueType,
       I: 'a + IndexType {
     type IterCol = IterCol<'a, T, I>;
 
+    // The column info refers to positions in the storage, so it has to be packed
     fn assemble_column_info(&mut self) {
+        self.finalize();
+        self.rows.clear();
+        self.indexlist_col = IndexList::<I>::new();
         self.rows.reserve(self.columns.len());
         for i in 0..self.n_rows() {
             let start = self.offset_rows[i].as_usize();
@@ -192,7 +922,7 @@ where T: 'a + ValueType,
 
     fn iter_col(&'a self, col: usize) -> Result<Self::IterCol, SparseMatError> {
         // Check if the column info for the iterator is available and consistent
-        if self.rows.len() != self.columns.len() {
+        if self.has_slack() || self.rows.len() != self.columns.len() {
             return Err(SparseMatError::new("Column iterator not available - use assemble_column_info()"));
         }
         let ret = IterCol::<T, I> {
@@ -221,3 +951,59 @@ where I: IndexType {
 }
 
 sparsemat_ops!(SparseMatCRS);
+
+#[cfg(feature = "serde")]
+mod serialization {
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+    use super::*;
+
+    #[derive(Serialize)]
+    struct RawRef<'a, T, I> {
+        n_rows: usize,
+        n_cols: usize,
+        offset_rows: &'a [I],
+        columns: &'a [I],
+        values: &'a [T],
+    }
+
+    #[derive(Deserialize)]
+    struct Raw<T, I> {
+        n_rows: usize,
+        n_cols: usize,
+        offset_rows: Vec<I>,
+        columns: Vec<I>,
+        values: Vec<T>,
+    }
+
+    // Only the packed CRS arrays are serialized
+    impl<T, I> Serialize for SparseMatCRS<T, I>
+    where T: ValueType + Serialize,
+          I: IndexType + Serialize {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+            if self.has_slack() {
+                let mut packed = self.clone();
+                packed.finalize();
+                return packed.serialize(serializer);
+            }
+            RawRef {
+                n_rows: self.n_rows,
+                n_cols: self.n_cols,
+                offset_rows: &self.offset_rows,
+                columns: &self.columns,
+                values: &self.values,
+            }.serialize(serializer)
+        }
+    }
+
+    impl<'de, T, I> Deserialize<'de> for SparseMatCRS<T, I>
+    where T: ValueType + Deserialize<'de>,
+          I: IndexType + Deserialize<'de> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            let raw = Raw::<T, I>::deserialize(deserializer)?;
+            Self::try_from_raw(raw.n_rows, raw.n_cols, raw.offset_rows, raw.columns, raw.values)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::mem::size_of;
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+use crate::vector::Vector;
+use crate::densevec::DenseVec;
+
+// Implementation of a sparse matrix with diagonal storage format
+// Each stored diagonal is given by its offset j - i and holds one value for each row,
+// so entry (i, i + offsets[d]) is at position d * n_rows + i in the data vec
+// Positions outside of the matrix are unused and remain zero
+// Example: offsets = [-1, 0, 1] for a tridiagonal matrix with 3 rows
+//          data    = [*, a10, a21, a00, a11, a22, a01, a12, *]
+// The row iterator needs references to the column indices,
+// which is why the vec columns simply holds all the indices from 0 to n_cols
+// All entries of a stored diagonal inside the matrix count as non-zero entries
+#[derive(Clone, Debug)]
+pub struct SparseMatDIA<T, I> {
+    n_rows: usize,
+    offsets: Vec<isize>,
+    data: Vec<T>,
+    columns: Vec<I>,
+}
+
+impl<T, I> SparseMatDIA<T, I>
+where T: ValueType,
+      I: IndexType {
+
+    // Returns the range of rows in which diagonal d lies inside the matrix
+    fn rows_diagonal(&self, d: usize) -> std::ops::Range<usize> {
+        let offset = self.offsets[d];
+        let start = if offset < 0 { offset.unsigned_abs() } else { 0 };
+        let end = (self.n_cols() as isize - offset).clamp(0, self.n_rows as isize) as usize;
+        start..std::cmp::max(start, end)
+    }
+
+    // Returns the index of the diagonal with the given offset or UNSET if it does not exist
+    fn find_diagonal(&self, offset: isize) -> usize {
+        match self.offsets.binary_search(&offset) {
+            Ok(d) => d,
+            Err(_) => Self::UNSET.as_usize(),
+        }
+    }
+
+    // Rebuilds the data for n_rows rows and the offsets of the new diagonals
+    fn resize(&mut self, n_rows: usize, offsets: Vec<isize>) {
+        let mut data = vec![T::zero(); n_rows * offsets.len()];
+        for (d_old, offset) in self.offsets.iter().enumerate() {
+            let d = offsets.binary_search(offset).unwrap();
+            let len = std::cmp::min(n_rows, self.n_rows);
+            data[d * n_rows..d * n_rows + len].copy_from_slice(&self.data[d_old * self.n_rows..d_old * self.n_rows + len]);
+        }
+        self.n_rows = n_rows;
+        self.offsets = offsets;
+        self.data = data;
+    }
+
+    // Returns the sorted offsets of all diagonals containing entries of the matrix
+    pub fn diagonal_offsets(mat: &SparseMatCRS<T, I>) -> Vec<isize> {
+        let mut offsets = (0..mat.n_rows())
+            .flat_map(|i| mat.iter_row(i).map(move |(&col, _val)| col.as_usize() as isize - i as isize))
+            .collect::<Vec<isize>>();
+        offsets.sort_unstable();
+        offsets.dedup();
+        offsets
+    }
+
+    // Checks if storing the matrix in DIA format needs less memory than in CRS format
+    // This is the case for matrices with a few densely populated diagonals
+    pub fn is_beneficial(mat: &SparseMatCRS<T, I>) -> bool {
+        let n_diagonals = Self::diagonal_offsets(mat).len();
+        let size_dia = n_diagonals * (mat.n_rows() * size_of::<T>() + size_of::<isize>());
+        let size_crs = mat.n_non_zero_entries() * (size_of::<T>() + size_of::<I>()) + (mat.n_rows() + 1) * size_of::<I>();
+        size_dia <= size_crs
+    }
+
+    // Creates a matrix in DIA format from a matrix in CRS format
+    pub fn from_crs(rhs: &SparseMatCRS<T, I>) -> Self {
+        let offsets = Self::diagonal_offsets(rhs);
+        let n_rows = rhs.n_rows();
+        let mut data = vec![T::zero(); n_rows * offsets.len()];
+        for i in 0..n_rows {
+            for (&col, &val) in rhs.iter_row(i) {
+                let d = offsets.binary_search(&(col.as_usize() as isize - i as isize)).unwrap();
+                data[d * n_rows + i] = val;
+            }
+        }
+        Self {
+            n_rows,
+            offsets,
+            data,
+            columns: (0..rhs.n_cols()).map(I::as_indextype).collect(),
+        }
+    }
+
+    // Creates a new sparse matrix with CRS format
+    // Zero entries of the diagonals are dropped
+    pub fn to_crs(&self) -> SparseMatCRS<T, I> {
+        let mut values = Vec::<T>::with_capacity(self.data.len());
+        let mut columns = Vec::<I>::with_capacity(self.data.len());
+        let mut offset_rows = Vec::<I>::with_capacity(self.n_rows + 1);
+        for i in 0..self.n_rows {
+            offset_rows.push(I::as_indextype(columns.len()));
+            for (&col, &val) in self.iter_row(i) {
+                if val != T::zero() {
+                    columns.push(col);
+                    values.push(val);
+                }
+            }
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        SparseMatCRS::from_raw(self.n_rows, self.n_cols(), offset_rows, columns, values)
+    }
+
+    // Returns the offsets of the stored diagonals
+    pub fn offsets(&self) -> &[isize] {
+        &self.offsets
+    }
+}
+
+impl<'a, T, I> SparseMatrix<'a> for SparseMatDIA<T, I>
+where T: 'a + ValueType,
+      I: 'a + IndexType {
+    type Value = T;
+    type Index = I;
+    type IterRow = IterRow<'a, T, I>;
+
+    fn iter_row(&'a self, row: usize) -> Self::IterRow {
+        IterRow::<T, I> {
+            mat: self,
+            row,
+            diagonal: if row < self.n_rows { 0 } else { self.offsets.len() },
+        }
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            n_rows: 0,
+            offsets: Vec::<isize>::new(),
+            data: Vec::<T>::with_capacity(cap),
+            columns: Vec::<I>::new(),
+        }
+    }
+
+    fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    fn n_cols(&self) -> usize {
+        self.columns.len()
+    }
+
+    fn n_non_zero_entries(&self) -> usize {
+        (0..self.offsets.len()).map(|d| self.rows_diagonal(d).len()).sum()
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        let d = self.find_diagonal(j as isize - i as isize);
+        if i < self.n_rows && j < self.n_cols() && d != Self::UNSET.as_usize() {
+            self.data[d * self.n_rows + i]
+        } else {
+            T::zero()
+        }
+    }
+
+    // Inserting a new diagonal or row rebuilds the whole storage
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        let offset = j as isize - i as isize;
+        if j >= self.n_cols() {
+            self.columns.extend((self.n_cols()..(j + 1)).map(I::as_indextype));
+        }
+        if i >= self.n_rows || self.find_diagonal(offset) == Self::UNSET.as_usize() {
+            let mut offsets = self.offsets.clone();
+            if let Err(d) = offsets.binary_search(&offset) {
+                offsets.insert(d, offset);
+            }
+            self.resize(std::cmp::max(i + 1, self.n_rows), offsets);
+        }
+        let d = self.find_diagonal(offset);
+        &mut self.data[d * self.n_rows + i]
+    }
+
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        if n_cols > self.n_cols() {
+            self.columns.extend((self.n_cols()..n_cols).map(I::as_indextype));
+        }
+        if n_rows > self.n_rows {
+            self.resize(n_rows, self.offsets.clone());
+        }
+    }
+
+    fn scale(&mut self, rhs: Self::Value) {
+        for iter in self.data.iter_mut() {
+            *iter *= rhs;
+        }
+    }
+
+    // Streams over each diagonal with contiguous accesses to the data and the vectors
+    fn mvp<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = T> {
+        let x = (0..self.n_cols()).map(|j| rhs.get(j)).collect::<Vec<T>>();
+        let mut ret = vec![T::zero(); self.n_rows];
+        for (d, &offset) in self.offsets.iter().enumerate() {
+            let rows = self.rows_diagonal(d);
+            let cols = ((rows.start as isize + offset) as usize)..((rows.end as isize + offset) as usize);
+            let diagonal = &self.data[d * self.n_rows + rows.start..d * self.n_rows + rows.end];
+            for ((y, &val), &x_j) in ret[rows].iter_mut().zip(diagonal.iter()).zip(x[cols].iter()) {
+                *y += val * x_j;
+            }
+        }
+        V::from_vec(ret)
+    }
+}
+
+// Iterator over the entries of a row passing all the diagonals
+pub struct IterRow<'a, T, I> {
+    mat: &'a SparseMatDIA<T, I>,
+    row: usize,
+    diagonal: usize,
+}
+
+impl<'a, T, I> Iterator for IterRow<'a, T, I>
+where I: IndexType {
+    type Item = (&'a I, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.diagonal < self.mat.offsets.len() {
+            let d = self.diagonal;
+            self.diagonal += 1;
+            let col = self.row as isize + self.mat.offsets[d];
+            if col >= 0 && (col as usize) < self.mat.columns.len() {
+                return Some((&self.mat.columns[col as usize], &self.mat.data[d * self.mat.n_rows + self.row]));
+            }
+        }
+        None
+    }
+}
+
+sparsemat_ops!(SparseMatDIA);
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+use crate::densevec::DenseVec;
+
+// A sparse matrix implementation with a dictionary of keys
+// Each row stores its entries in a hash map with the columns as keys,
+// so accessing an arbitrary entry (i, j) takes O(1) expected time
+// The order of the entries within a row is arbitrary
+// This struct is useful assembling a sparse matrix with random access patterns
+// and can be converted to CRS with sorted rows afterwards
+#[derive(Clone, Debug)]
+pub struct SparseMatDOK<T, I> {
+    n_cols: usize,
+    nnz: usize,
+    rows: Vec<HashMap<I, T>>,
+}
+
+impl<T, I> SparseMatDOK<T, I>
+where T: ValueType,
+      I: IndexType {
+
+    // Creates a new sparse matrix with CRS format and sorted rows
+    pub fn to_crs(&self) -> SparseMatCRS<T, I> {
+        let mut values = Vec::<T>::with_capacity(self.nnz);
+        let mut columns = Vec::<I>::with_capacity(self.nnz);
+        let mut offset_rows = Vec::<I>::with_capacity(self.n_rows() + 1);
+        let mut cols_vals = Vec::<(I, T)>::new();
+        for row in self.rows.iter() {
+            offset_rows.push(I::as_indextype(columns.len()));
+            cols_vals.extend(row.iter().map(|(&col, &val)| (col, val)));
+            cols_vals.sort_unstable_by_key(|&(col, _val)| col);
+            for &(col, val) in cols_vals.iter() {
+                columns.push(col);
+                values.push(val);
+            }
+            cols_vals.clear();
+        }
+        offset_rows.push(I::as_indextype(columns.len()));
+        SparseMatCRS::from_raw(self.n_rows(), self.n_cols(), offset_rows, columns, values)
+    }
+
+    // Removes entry (i, j) and returns its value if it exists
+    pub fn remove(&mut self, i: usize, j: usize) -> Option<T> {
+        let ret = self.rows.get_mut(i).and_then(|row| row.remove(&I::as_indextype(j)));
+        if ret.is_some() {
+            self.nnz -= 1;
+        }
+        ret
+    }
+}
+
+impl<'a, T, I> SparseMatrix<'a> for SparseMatDOK<T, I>
+where T: 'a + ValueType,
+      I: 'a + IndexType {
+
+    type Value = T;
+    type Index = I;
+    type IterRow = std::collections::hash_map::Iter<'a, I, T>;
+
+    fn iter_row(&'a self, row: usize) -> Self::IterRow {
+        if row < self.n_rows() {
+            self.rows[row].iter()
+        } else {
+            panic!("Invalid row {} - Max row is {}", row, self.n_rows());
+        }
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            n_cols: 0,
+            nnz: 0,
+            rows: Vec::<HashMap<I, T>>::with_capacity(cap),
+        }
+    }
+
+    fn n_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    fn n_non_zero_entries(&self) -> usize {
+        self.nnz
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        match self.rows.get(i).and_then(|row| row.get(&I::as_indextype(j))) {
+            Some(&val) => val,
+            None => T::zero(),
+        }
+    }
+
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        if i >= self.n_rows() {
+            self.rows.resize(i + 1, HashMap::<I, T>::new());
+        }
+        if j >= self.n_cols {
+            self.n_cols = j + 1;
+        }
+        let col = I::as_indextype(j);
+        if !self.rows[i].contains_key(&col) {
+            self.nnz += 1;
+        }
+        self.rows[i].entry(col).or_insert_with(T::zero)
+    }
+
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        if n_rows > self.n_rows() {
+            self.rows.resize(n_rows, HashMap::<I, T>::new());
+        }
+        self.n_cols = std::cmp::max(self.n_cols, n_cols);
+    }
+
+    fn scale(&mut self, rhs: Self::Value) {
+        for row in self.rows.iter_mut() {
+            for val in row.values_mut() {
+                *val *= rhs;
+            }
+        }
+    }
+}
+
+sparsemat_ops!(SparseMatDOK);
@@ -0,0 +1,214 @@
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+use crate::vector::Vector;
+use crate::densevec::DenseVec;
+
+// Implementation of a sparse matrix with ELLPACK format
+// Every row is padded to the same width and the entries are stored column-major,
+// so slot k of row i is at position k * n_rows + i
+// Example: rows [0: (0, a), (2, b)], [1: (1, c)] -> width = 2
+//          columns = [0, 1, 2, 1]
+//          values  = [a, c, b, 0]
+// Padding entries repeat the last column of the row with a zero value
+// The format is efficient for nearly constant row lengths, since the product
+// processes one slot of all rows at a time without any branches
+// Inserting entries is expensive and should be avoided
+#[derive(Clone, Debug)]
+pub struct SparseMatELL<T, I> {
+    n_rows: usize,
+    n_cols: usize,
+    width: usize,
+    row_lengths: Vec<usize>,
+    columns: Vec<I>,
+    values: Vec<T>,
+}
+
+impl<T, I> SparseMatELL<T, I>
+where T: ValueType,
+      I: IndexType {
+
+    // Creates the matrix from the entries of each row and the given width
+    fn from_rows(n_cols: usize, rows: &[Vec<(I, T)>], width: usize) -> Self {
+        let n_rows = rows.len();
+        let mut columns = vec![I::ZERO; width * n_rows];
+        let mut values = vec![T::zero(); width * n_rows];
+        for (i, row) in rows.iter().enumerate() {
+            let mut col_pad = I::ZERO;
+            for (k, &(col, val)) in row.iter().enumerate() {
+                columns[k * n_rows + i] = col;
+                values[k * n_rows + i] = val;
+                col_pad = col;
+            }
+            for k in row.len()..width {
+                columns[k * n_rows + i] = col_pad;
+            }
+        }
+        Self {
+            n_rows,
+            n_cols,
+            width,
+            row_lengths: rows.iter().map(|row| row.len()).collect(),
+            columns,
+            values,
+        }
+    }
+
+    // Collects the entries of each row
+    fn to_rows(&self) -> Vec<Vec<(I, T)>> {
+        (0..self.n_rows).map(|i| {
+            (0..self.row_lengths[i]).map(|k| {
+                let index = k * self.n_rows + i;
+                (self.columns[index], self.values[index])
+            }).collect()
+        }).collect()
+    }
+
+    // Creates a matrix in ELLPACK format from a matrix in CRS format
+    pub fn from_crs(rhs: &SparseMatCRS<T, I>) -> Self {
+        let rows = (0..rhs.n_rows())
+            .map(|i| rhs.iter_row(i).map(|(&col, &val)| (col, val)).collect::<Vec<(I, T)>>())
+            .collect::<Vec<Vec<(I, T)>>>();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        Self::from_rows(rhs.n_cols(), &rows, width)
+    }
+
+    // Creates a new sparse matrix with CRS format
+    pub fn to_crs(&self) -> SparseMatCRS<T, I> {
+        SparseMatCRS::from_sparsemat(self)
+    }
+
+    // Returns the number of slots stored for each row
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // Returns the ratio of padding entries to actual entries
+    pub fn padding_overhead(&self) -> f64 {
+        let nnz = self.n_non_zero_entries();
+        if nnz > 0 {
+            (self.columns.len() - nnz) as f64 / nnz as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<'a, T, I> SparseMatrix<'a> for SparseMatELL<T, I>
+where T: 'a + ValueType,
+      I: 'a + IndexType {
+    type Value = T;
+    type Index = I;
+    type IterRow = std::iter::Zip<std::iter::Take<std::iter::StepBy<std::slice::Iter<'a, I>>>,
+                                  std::iter::Take<std::iter::StepBy<std::slice::Iter<'a, T>>>>;
+
+    fn iter_row(&'a self, row: usize) -> Self::IterRow {
+        let (start, len) = if row < self.n_rows {
+            (row, self.row_lengths[row])
+        } else {
+            (0, 0)
+        };
+        let step = std::cmp::max(self.n_rows, 1);
+        self.columns[start..].iter().step_by(step).take(len)
+            .zip(self.values[start..].iter().step_by(step).take(len))
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            n_rows: 0,
+            n_cols: 0,
+            width: 0,
+            row_lengths: Vec::<usize>::with_capacity(cap),
+            columns: Vec::<I>::new(),
+            values: Vec::<T>::new(),
+        }
+    }
+
+    fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    fn n_non_zero_entries(&self) -> usize {
+        self.row_lengths.iter().sum()
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        let mut ret = T::zero();
+        if let Some((_col, &val)) = self.iter_row(i).find(|(col, _val)| col.as_usize() == j) {
+            ret = val;
+        }
+        ret
+    }
+
+    // New entries are appended to the padding of the row if possible,
+    // otherwise the whole storage is rebuilt
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        let col = I::as_indextype(j);
+        if i < self.n_rows {
+            for k in 0..self.row_lengths[i] {
+                let index = k * self.n_rows + i;
+                if self.columns[index] == col {
+                    return &mut self.values[index];
+                }
+            }
+        }
+        if i >= self.n_rows || self.row_lengths[i] == self.width {
+            let mut rows = self.to_rows();
+            if i >= self.n_rows {
+                rows.resize(i + 1, Vec::<(I, T)>::new());
+            }
+            let width = std::cmp::max(self.width, rows[i].len() + 1);
+            *self = Self::from_rows(self.n_cols, &rows, width);
+        }
+        if j >= self.n_cols {
+            self.n_cols = j + 1;
+        }
+        let index = self.row_lengths[i] * self.n_rows + i;
+        self.columns[index] = col;
+        self.values[index] = T::zero();
+        self.row_lengths[i] += 1;
+        // Keep the padding of the row on the last column
+        for k in self.row_lengths[i]..self.width {
+            self.columns[k * self.n_rows + i] = col;
+        }
+        &mut self.values[index]
+    }
+
+    // New rows rebuild the whole storage
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        self.n_cols = std::cmp::max(self.n_cols, n_cols);
+        if n_rows > self.n_rows {
+            let mut rows = self.to_rows();
+            rows.resize(n_rows, Vec::<(I, T)>::new());
+            *self = Self::from_rows(self.n_cols, &rows, self.width);
+        }
+    }
+
+    fn scale(&mut self, rhs: Self::Value) {
+        for iter in self.values.iter_mut() {
+            *iter *= rhs;
+        }
+    }
+
+    // Processes one slot of all rows at a time including the padding,
+    // which gives a branch-free inner loop suitable for auto-vectorisation
+    fn mvp<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = T> {
+        let x = (0..self.n_cols).map(|j| rhs.get(j)).collect::<Vec<T>>();
+        let mut ret = vec![T::zero(); self.n_rows];
+        if self.n_rows > 0 {
+            for (cols, vals) in self.columns.chunks_exact(self.n_rows).zip(self.values.chunks_exact(self.n_rows)) {
+                for ((y, col), &val) in ret.iter_mut().zip(cols.iter()).zip(vals.iter()) {
+                    *y += val * x[col.as_usize()];
+                }
+            }
+        }
+        V::from_vec(ret)
+    }
+}
+
+sparsemat_ops!(SparseMatELL);
@@ -52,6 +52,72 @@ where T: ValueType,
         index
     }
 
+    // Returns the row lengths of the transpose and the target position of each entry
+    // when the rows of the transpose are stored contiguously
+    fn transpose_positions(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut lengths = vec![0usize; self.n_cols()];
+        for col in self.columns.iter() {
+            lengths[col.as_usize()] += 1;
+        }
+        let mut next = Vec::<usize>::with_capacity(self.n_cols());
+        let mut offset = 0;
+        for &len in lengths.iter() {
+            next.push(offset);
+            offset += len;
+        }
+        // Visit the entries row by row to keep the rows of the transpose sorted
+        let mut perm = vec![0usize; self.columns.len()];
+        for i in 0..self.n_rows() {
+            for index in self.indexlist.iter_row(i) {
+                let j = self.columns[index].as_usize();
+                perm[index] = next[j];
+                next[j] += 1;
+            }
+        }
+        (lengths, perm)
+    }
+
+    // Transposes the matrix reusing the storage of the columns, values and the index list
+    // Afterwards the entries of each row are stored contiguously and sorted
+    pub fn transpose_in_place(&mut self) {
+        let (lengths, mut perm) = self.transpose_positions();
+        // Replace the column of each entry by its row
+        for i in 0..self.n_rows() {
+            for index in self.indexlist.iter_row(i) {
+                self.columns[index] = I::as_indextype(i);
+            }
+        }
+        let n_cols = self.n_rows();
+        permute_in_place(&mut perm, &mut self.columns, &mut self.values);
+        self.indexlist.set_row_lengths(&lengths);
+        self.n_cols = n_cols;
+        // The column info is not valid anymore
+        self.rows.clear();
+        self.indexlist_col = IndexList::<I>::new();
+    }
+
+    // Reorders the columns and values so the entries of each row are stored contiguously
+    // in the order of the rows, which improves the cache behaviour when iterating
+    // An assembled column info is rebuilt for the new positions
+    pub fn defragment(&mut self) {
+        let mut lengths = Vec::<usize>::with_capacity(self.n_rows());
+        let mut perm = vec![0usize; self.columns.len()];
+        let mut pos = 0;
+        for i in 0..self.n_rows() {
+            let start = pos;
+            for index in self.indexlist.iter_row(i) {
+                perm[index] = pos;
+                pos += 1;
+            }
+            lengths.push(pos - start);
+        }
+        permute_in_place(&mut perm, &mut self.columns, &mut self.values);
+        self.indexlist.set_row_lengths(&lengths);
+        if !self.rows.is_empty() {
+            self.assemble_column_info();
+        }
+    }
+
     // Used for copying the column info to CRS
     pub(crate) fn column_info(&self) -> (Vec<I>, IndexList<I>) {
         (self.rows.clone(), self.indexlist_col.clone())
@@ -59,7 +125,7 @@ where T: ValueType,
 
     // Creates a new sparse matrix with CRS format
     pub fn to_crs(&self) -> SparseMatCRS<T, I> {
-        SparseMatCRS::from_sparsemat_index(&self)
+        SparseMatCRS::from_sparsemat_index(self)
     }
 }
 
@@ -70,6 +136,7 @@ where T: 'a + ValueType,
 
     fn assemble_column_info(&mut self) {
         // Track the rows in a vec at the same positions as the columns
+        self.indexlist_col = IndexList::<I>::new();
         self.rows.resize(self.columns.len(), Self::UNSET);
         for i in 0..self.n_rows() {
             for index in self.indexlist.iter_row(i) {
@@ -116,7 +183,7 @@ where T: 'a + ValueType,
     type Index = I;
     type IterRow = IterRow<'a, T, I>;
 
-    fn iter_row(&self, row: usize) -> IterRow<T, I> {
+    fn iter_row(&self, row: usize) -> IterRow<'_, T, I> {
         IterRow::<T, I> {
             mat: self,
             index_iter: self.indexlist.iter_row(row),
@@ -163,11 +230,38 @@ where T: 'a + ValueType,
         &mut self.values[index]
     }
 
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        self.indexlist.extend_rows(n_rows);
+        self.n_cols = std::cmp::max(self.n_cols, n_cols);
+    }
+
     fn scale(&mut self, rhs: Self::Value) {
         for iter in self.values.iter_mut() {
             *iter *= rhs;
         }
     }
+
+    // Counting sort over the columns builds the transpose in O(nnz)
+    // with the entries of each row stored contiguously
+    fn transpose(&'a self) -> Self {
+        let (lengths, perm) = self.transpose_positions();
+        let mut columns = vec![I::ZERO; self.columns.len()];
+        let mut values = vec![T::zero(); self.values.len()];
+        for i in 0..self.n_rows() {
+            for index in self.indexlist.iter_row(i) {
+                columns[perm[index]] = I::as_indextype(i);
+                values[perm[index]] = self.values[index];
+            }
+        }
+        Self {
+            n_cols: self.n_rows(),
+            columns,
+            values,
+            indexlist: IndexList::<I>::from_row_lengths(&lengths),
+            rows: Vec::<I>::new(),
+            indexlist_col: IndexList::<I>::new(),
+        }
+    }
 }
 
 pub struct IterRow<'a, T, I> {
@@ -205,3 +299,69 @@ where I: IndexType {
 }
 
 sparsemat_ops!(SparseMatIndexList);
+
+#[cfg(feature = "serde")]
+mod serialization {
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+    use crate::sparsemat_crs::check_raw;
+    use super::*;
+
+    // The matrix is stored in CRS format, so the index list is not part of the data
+    #[derive(Serialize, Deserialize)]
+    struct Raw<T, I> {
+        n_rows: usize,
+        n_cols: usize,
+        offset_rows: Vec<I>,
+        columns: Vec<I>,
+        values: Vec<T>,
+    }
+
+    impl<T, I> Serialize for SparseMatIndexList<T, I>
+    where T: ValueType + Serialize,
+          I: IndexType + Serialize {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+            let mut offset_rows = Vec::<I>::with_capacity(self.n_rows() + 1);
+            let mut columns = Vec::<I>::with_capacity(self.columns.len());
+            let mut values = Vec::<T>::with_capacity(self.values.len());
+            for i in 0..self.n_rows() {
+                offset_rows.push(I::as_indextype(columns.len()));
+                for index in self.indexlist.iter_row(i) {
+                    columns.push(self.columns[index]);
+                    values.push(self.values[index]);
+                }
+            }
+            offset_rows.push(I::as_indextype(columns.len()));
+            Raw {
+                n_rows: self.n_rows(),
+                n_cols: self.n_cols,
+                offset_rows,
+                columns,
+                values,
+            }.serialize(serializer)
+        }
+    }
+
+    // The entries of each row are stored contiguously afterwards
+    impl<'de, T, I> Deserialize<'de> for SparseMatIndexList<T, I>
+    where T: ValueType + Deserialize<'de>,
+          I: IndexType + Deserialize<'de> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            let raw = Raw::<T, I>::deserialize(deserializer)?;
+            check_raw(raw.n_rows, raw.n_cols, &raw.offset_rows, &raw.columns, raw.values.len())
+                .map_err(serde::de::Error::custom)?;
+            let lengths = raw.offset_rows.windows(2)
+                .map(|offsets| offsets[1].as_usize() - offsets[0].as_usize())
+                .collect::<Vec<usize>>();
+            Ok(Self {
+                n_cols: raw.n_cols,
+                columns: raw.columns,
+                values: raw.values,
+                indexlist: IndexList::<I>::from_row_lengths(&lengths),
+                rows: Vec::<I>::new(),
+                indexlist_col: IndexList::<I>::new(),
+            })
+        }
+    }
+}
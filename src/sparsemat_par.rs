@@ -3,8 +3,8 @@ use std::cmp::max;
 //use std::thread;
 //use std::sync::Arc;
 //use std::sync::mpsc;
-//use crate::vector::Vector;
-//use crate::types::{IndexType, ValueType};
+use crate::vector::Vector;
+use crate::types::{IndexType, ValueType};
 use crate::sparsematrix::SparseMatrix;
 
 // A sparse matrix implementation used for parallel operations
@@ -21,9 +21,9 @@ where M: SparseMatrix<'a> {
         let n_rows_sub_matrix = max_n_rows / n_blocks;
         let sub_matrices = vec![M::with_capacity(n_rows_sub_matrix); n_blocks];
         Self {
-            n_rows_sub_matrix: n_rows_sub_matrix,
-            n_blocks: n_blocks,
-            sub_matrices: sub_matrices,
+            n_rows_sub_matrix,
+            n_blocks,
+            sub_matrices,
         }
     }
 
@@ -132,9 +132,112 @@ where M: 'a + SparseMatrix<'a> {
         self.sub_matrices[block_id].get_mut(row_id, j)
     }
 
+    // The rows are distributed to the sub matrices like the entries
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        for (b, mat) in self.sub_matrices.iter_mut().enumerate() {
+            let n_rows_block = n_rows.saturating_sub(b * self.n_rows_sub_matrix);
+            let n_rows_block = if b + 1 < self.n_blocks { min(n_rows_block, self.n_rows_sub_matrix) } else { n_rows_block };
+            mat.extend_to(n_rows_block, n_cols);
+        }
+    }
+
     fn scale(&mut self, rhs: Self::Value) {
         for mat in &mut self.sub_matrices {
             mat.scale(rhs);
         }
     }
+
+    // Each sub matrix scatters its rows into a common dense accumulator
+    fn mvp_transpose<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = Self::Value> {
+        let mut ret = vec![Self::Value::zero(); self.n_cols()];
+        for (b, mat) in self.sub_matrices.iter().enumerate() {
+            let offset = b * self.n_rows_sub_matrix;
+            for i in 0..mat.n_rows() {
+                let x_i = rhs.get(offset + i);
+                for (&col, &val) in mat.iter_row(i) {
+                    ret[col.as_usize()] += val * x_i;
+                }
+            }
+        }
+        V::from_vec(ret)
+    }
+
+    fn mvp_add<V>(&'a self, alpha: Self::Value, rhs: &V, beta: Self::Value, ret: &mut V)
+    where V: Vector<'a, Value = Self::Value> {
+        let n_rows = self.n_rows();
+        for (b, mat) in self.sub_matrices.iter().enumerate() {
+            let offset = b * self.n_rows_sub_matrix;
+            // Empty rows at the end of a sub matrix are only scaled by beta
+            let n_rows_block = min(self.n_rows_sub_matrix, n_rows.saturating_sub(offset));
+            for i in 0..n_rows_block {
+                let mut sum = Self::Value::zero();
+                if i < mat.n_rows() {
+                    for (&col, &val) in mat.iter_row(i) {
+                        sum += rhs.get(col.as_usize()) * val;
+                    }
+                }
+                let y = ret.get_mut(offset + i);
+                if beta == Self::Value::zero() {
+                    *y = alpha * sum;
+                } else {
+                    *y *= beta;
+                    *y += alpha * sum;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialization {
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+    use super::*;
+
+    #[derive(Serialize)]
+    struct RawRef<'a, M> {
+        n_rows_sub_matrix: usize,
+        n_blocks: usize,
+        sub_matrices: &'a [M],
+    }
+
+    #[derive(Deserialize)]
+    struct Raw<M> {
+        n_rows_sub_matrix: usize,
+        n_blocks: usize,
+        sub_matrices: Vec<M>,
+    }
+
+    impl<M> Serialize for SparseMatPar<M>
+    where M: Serialize {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+            RawRef {
+                n_rows_sub_matrix: self.n_rows_sub_matrix,
+                n_blocks: self.n_blocks,
+                sub_matrices: &self.sub_matrices,
+            }.serialize(serializer)
+        }
+    }
+
+    // The sub matrices validate their own structure,
+    // but they must not have more rows than a block, since they would overlap the next one
+    impl<'de, M> Deserialize<'de> for SparseMatPar<M>
+    where M: Deserialize<'de> + for<'a> SparseMatrix<'a> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            let raw = Raw::<M>::deserialize(deserializer)?;
+            if raw.n_blocks == 0 || raw.n_rows_sub_matrix == 0 || raw.sub_matrices.len() != raw.n_blocks {
+                return Err(serde::de::Error::custom("Invalid number of blocks or rows per block"));
+            }
+            if let Some(b) = raw.sub_matrices.iter().position(|mat| mat.n_rows() > raw.n_rows_sub_matrix) {
+                return Err(serde::de::Error::custom(format!("Sub matrix {} has more rows than a block", b)));
+            }
+            Ok(Self {
+                n_rows_sub_matrix: raw.n_rows_sub_matrix,
+                n_blocks: raw.n_blocks,
+                sub_matrices: raw.sub_matrices,
+            })
+        }
+    }
 }
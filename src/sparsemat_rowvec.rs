@@ -4,34 +4,55 @@ use crate::densevec::DenseVec;
 
 // A sparse matrix implementation where each row is stored in separate vec
 // This implementation makes at least two allocations each row
+// If rows_sorted is set all rows are sorted by columns, which allows a binary search
+// for the entries and keeps the rows sorted on insertion
 #[derive(Clone, Debug)]
 pub struct SparseMatRowVec<T, I> {
     n_cols: usize,
     nnz: usize,
     columns: Vec<Vec<I>>,
     values: Vec<Vec<T>>,
+    rows_sorted: bool,
 }
  
 impl<T, I> SparseMatRowVec<T, I>
 where T: ValueType,
       I: IndexType {
 
+    // Returns true if all rows are known to be sorted by columns
+    // The flag is set by sort()
+    pub fn rows_sorted(&self) -> bool {
+        self.rows_sorted
+    }
+
     // Returns the offset for the columns and values vec for row i
     // or UNSET if entry (i, j) does not exist
     fn find_index(&self, i: usize, j: usize) -> usize {
         let col = I::as_indextype(j);
         let mut ret = Self::UNSET.as_usize();
         if i < self.n_rows() {
-            for index in 0..self.columns[i].len() {
-                if self.columns[i][index] == col {
+            if self.rows_sorted {
+                let index = self.sorted_position(i, j);
+                if index < self.columns[i].len() && self.columns[i][index] == col {
                     ret = index;
-                    break;
+                }
+            } else {
+                for index in 0..self.columns[i].len() {
+                    if self.columns[i][index] == col {
+                        ret = index;
+                        break;
+                    }
                 }
             }
         }
         ret
     }
 
+    // Binary search for the first position in the sorted row i with a column >= j
+    fn sorted_position(&self, i: usize, j: usize) -> usize {
+        self.columns[i].partition_point(|col| col.as_usize() < j)
+    }
+
     fn push(&mut self, i: usize, j: usize, val: T) -> usize {
         if i >= self.n_rows() {
             self.columns.resize(i + 1, Vec::<I>::new());
@@ -40,9 +61,13 @@ where T: ValueType,
         if j >= self.n_cols {
             self.n_cols = j + 1;
         }
-        let ret = self.columns[i].len();
-        self.columns[i].push(I::as_indextype(j));
-        self.values[i].push(val);
+        let ret = if self.rows_sorted {
+            self.sorted_position(i, j)
+        } else {
+            self.columns[i].len()
+        };
+        self.columns[i].insert(ret, I::as_indextype(j));
+        self.values[i].insert(ret, val);
         self.nnz += 1;
         ret
     }
@@ -70,6 +95,7 @@ where T: 'a + ValueType,
             nnz: 0,
             values: Vec::<Vec::<T>>::with_capacity(cap),
             columns: Vec::<Vec::<I>>::with_capacity(cap),
+            rows_sorted: false,
         }
     }
 
@@ -102,6 +128,14 @@ where T: 'a + ValueType,
         &mut self.values[i][index]
     }
 
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        if n_rows > self.n_rows() {
+            self.columns.resize(n_rows, Vec::<I>::new());
+            self.values.resize(n_rows, Vec::<T>::new());
+        }
+        self.n_cols = std::cmp::max(self.n_cols, n_cols);
+    }
+
     fn scale(&mut self, rhs: Self::Value) {
         for i in 0..self.n_rows() {
             for iter in self.values[i].iter_mut() {
@@ -122,6 +156,70 @@ where T: 'a + ValueType,
             self.values[i][index] = *val;
         }
     }
+
+    fn sort(&mut self) {
+        for i in 0..self.n_rows() {
+            self.sort_row(i);
+        }
+        self.rows_sorted = true;
+    }
 }
 
 sparsemat_ops!(SparseMatRowVec);
+
+#[cfg(feature = "serde")]
+mod serialization {
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+    use super::*;
+
+    #[derive(Serialize)]
+    struct RawRef<'a, T, I> {
+        n_cols: usize,
+        columns: &'a [Vec<I>],
+        values: &'a [Vec<T>],
+    }
+
+    #[derive(Deserialize)]
+    struct Raw<T, I> {
+        n_cols: usize,
+        columns: Vec<Vec<I>>,
+        values: Vec<Vec<T>>,
+    }
+
+    impl<T, I> Serialize for SparseMatRowVec<T, I>
+    where T: ValueType + Serialize,
+          I: IndexType + Serialize {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+            RawRef {
+                n_cols: self.n_cols,
+                columns: &self.columns,
+                values: &self.values,
+            }.serialize(serializer)
+        }
+    }
+
+    impl<'de, T, I> Deserialize<'de> for SparseMatRowVec<T, I>
+    where T: ValueType + Deserialize<'de>,
+          I: IndexType + Deserialize<'de> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            let raw = Raw::<T, I>::deserialize(deserializer)?;
+            if raw.columns.len() != raw.values.len()
+                || raw.columns.iter().zip(raw.values.iter()).any(|(cols, vals)| cols.len() != vals.len()) {
+                return Err(serde::de::Error::custom("Size mismatch of columns and values"));
+            }
+            if let Some(col) = raw.columns.iter().flatten().find(|col| col.as_usize() >= raw.n_cols) {
+                return Err(serde::de::Error::custom(format!("Column {} out of bounds", col)));
+            }
+            let rows_sorted = raw.columns.iter().all(|cols| cols.windows(2).all(|c| c[0] < c[1]));
+            Ok(Self {
+                n_cols: raw.n_cols,
+                nnz: raw.columns.iter().map(|cols| cols.len()).sum(),
+                columns: raw.columns,
+                values: raw.values,
+                rows_sorted,
+            })
+        }
+    }
+}
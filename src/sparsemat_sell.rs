@@ -0,0 +1,266 @@
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+use crate::vector::Vector;
+use crate::densevec::DenseVec;
+
+// Implementation of a sparse matrix with sliced ELLPACK format (SELL-C-sigma)
+// The rows are sorted by their lengths within windows of sigma rows and then grouped
+// into slices of C rows - each slice is stored in ELLPACK format with its own width
+// Slot k of the row at lane r of slice s is at position offset_slices[s] + k * C + r
+// Example: C = 2, rows [0: (0, a), (2, b)], [1: (1, c)], [2: (3, d)]
+//          -> slice 0 holds rows 0 and 1 with width 2, slice 1 holds row 2 with width 1
+//          offset_slices = [0, 4, 6]
+//          columns       = [0, 1, 2, 1, 3, 0]
+//          values        = [a, c, b, 0, d, 0]
+// Padding entries repeat the last column of the row with a zero value
+// Compared to ELLPACK the padding is limited to the rows of a slice
+// Inserting entries is expensive and should be avoided
+#[derive(Clone, Debug)]
+pub struct SparseMatSELL<T, I> {
+    n_rows: usize,
+    n_cols: usize,
+    chunk_size: usize,
+    sigma: usize,
+    row_lengths: Vec<usize>,
+    // Original row of each position in the sorted order and its inverse
+    perm: Vec<usize>,
+    perm_inv: Vec<usize>,
+    offset_slices: Vec<usize>,
+    columns: Vec<I>,
+    values: Vec<T>,
+}
+
+impl<T, I> SparseMatSELL<T, I>
+where T: ValueType,
+      I: IndexType {
+
+    // Creates the matrix from the entries of each row
+    fn from_rows(n_cols: usize, rows: &[Vec<(I, T)>], chunk_size: usize, sigma: usize) -> Self {
+        let n_rows = rows.len();
+        let chunk_size = std::cmp::max(chunk_size, 1);
+        let sigma = std::cmp::max(sigma, 1);
+        // Sort the rows by decreasing length within each window
+        let mut perm = (0..n_rows).collect::<Vec<usize>>();
+        for window in perm.chunks_mut(sigma) {
+            window.sort_by_key(|&i| std::cmp::Reverse(rows[i].len()));
+        }
+        let mut perm_inv = vec![0; n_rows];
+        for (pos, &i) in perm.iter().enumerate() {
+            perm_inv[i] = pos;
+        }
+        let mut offset_slices = vec![0];
+        let mut columns = Vec::<I>::new();
+        let mut values = Vec::<T>::new();
+        for slice in perm.chunks(chunk_size) {
+            let start = columns.len();
+            let width = slice.iter().map(|&i| rows[i].len()).max().unwrap_or(0);
+            columns.resize(start + width * chunk_size, I::ZERO);
+            values.resize(start + width * chunk_size, T::zero());
+            for (lane, &i) in slice.iter().enumerate() {
+                let mut col_pad = I::ZERO;
+                for (k, &(col, val)) in rows[i].iter().enumerate() {
+                    columns[start + k * chunk_size + lane] = col;
+                    values[start + k * chunk_size + lane] = val;
+                    col_pad = col;
+                }
+                for k in rows[i].len()..width {
+                    columns[start + k * chunk_size + lane] = col_pad;
+                }
+            }
+            offset_slices.push(columns.len());
+        }
+        Self {
+            n_rows,
+            n_cols,
+            chunk_size,
+            sigma,
+            row_lengths: rows.iter().map(|row| row.len()).collect(),
+            perm,
+            perm_inv,
+            offset_slices,
+            columns,
+            values,
+        }
+    }
+
+    // Collects the entries of each row
+    fn to_rows(&self) -> Vec<Vec<(I, T)>> {
+        (0..self.n_rows)
+            .map(|i| self.iter_row(i).map(|(&col, &val)| (col, val)).collect())
+            .collect()
+    }
+
+    // Returns the position of the first slot of row i
+    fn row_start(&self, i: usize) -> usize {
+        let pos = self.perm_inv[i];
+        self.offset_slices[pos / self.chunk_size] + pos % self.chunk_size
+    }
+
+    // Returns the number of slots of the slice containing row i
+    fn row_width(&self, i: usize) -> usize {
+        let slice = self.perm_inv[i] / self.chunk_size;
+        (self.offset_slices[slice + 1] - self.offset_slices[slice]) / self.chunk_size
+    }
+
+    // Creates a matrix in SELL-C-sigma format from a matrix in CRS format
+    // with slices of chunk_size rows, which are sorted within windows of sigma rows
+    // The chunk size should be a multiple of the SIMD width
+    pub fn from_crs(rhs: &SparseMatCRS<T, I>, chunk_size: usize, sigma: usize) -> Self {
+        let rows = (0..rhs.n_rows())
+            .map(|i| rhs.iter_row(i).map(|(&col, &val)| (col, val)).collect::<Vec<(I, T)>>())
+            .collect::<Vec<Vec<(I, T)>>>();
+        Self::from_rows(rhs.n_cols(), &rows, chunk_size, sigma)
+    }
+
+    // Creates a new sparse matrix with CRS format
+    pub fn to_crs(&self) -> SparseMatCRS<T, I> {
+        SparseMatCRS::from_sparsemat(self)
+    }
+
+    // Returns the ratio of padding entries to actual entries
+    pub fn padding_overhead(&self) -> f64 {
+        let nnz = self.n_non_zero_entries();
+        if nnz > 0 {
+            (self.columns.len() - nnz) as f64 / nnz as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<'a, T, I> SparseMatrix<'a> for SparseMatSELL<T, I>
+where T: 'a + ValueType,
+      I: 'a + IndexType {
+    type Value = T;
+    type Index = I;
+    type IterRow = std::iter::Zip<std::iter::Take<std::iter::StepBy<std::slice::Iter<'a, I>>>,
+                                  std::iter::Take<std::iter::StepBy<std::slice::Iter<'a, T>>>>;
+
+    fn iter_row(&'a self, row: usize) -> Self::IterRow {
+        // The first slot of an empty row may lie past the storage if its slice has no slots
+        let (start, len) = if row < self.n_rows && self.row_lengths[row] > 0 {
+            (self.row_start(row), self.row_lengths[row])
+        } else {
+            (0, 0)
+        };
+        self.columns[start..].iter().step_by(self.chunk_size).take(len)
+            .zip(self.values[start..].iter().step_by(self.chunk_size).take(len))
+    }
+
+    // The default chunk size of 8 rows fits the SIMD width of f32 with AVX2
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            n_rows: 0,
+            n_cols: 0,
+            chunk_size: 8,
+            sigma: 64,
+            row_lengths: Vec::<usize>::with_capacity(cap),
+            perm: Vec::<usize>::with_capacity(cap),
+            perm_inv: Vec::<usize>::with_capacity(cap),
+            offset_slices: vec![0],
+            columns: Vec::<I>::new(),
+            values: Vec::<T>::new(),
+        }
+    }
+
+    fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    fn n_non_zero_entries(&self) -> usize {
+        self.row_lengths.iter().sum()
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        let mut ret = T::zero();
+        if let Some((_col, &val)) = self.iter_row(i).find(|(col, _val)| col.as_usize() == j) {
+            ret = val;
+        }
+        ret
+    }
+
+    // New entries are appended to the padding of the row if possible,
+    // otherwise the whole storage is rebuilt
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        let col = I::as_indextype(j);
+        if i < self.n_rows {
+            let start = self.row_start(i);
+            for k in 0..self.row_lengths[i] {
+                let index = start + k * self.chunk_size;
+                if self.columns[index] == col {
+                    return &mut self.values[index];
+                }
+            }
+        }
+        if j >= self.n_cols {
+            self.n_cols = j + 1;
+        }
+        if i >= self.n_rows || self.row_lengths[i] == self.row_width(i) {
+            let mut rows = self.to_rows();
+            if i >= self.n_rows {
+                rows.resize(i + 1, Vec::<(I, T)>::new());
+            }
+            rows[i].push((col, T::zero()));
+            *self = Self::from_rows(self.n_cols, &rows, self.chunk_size, self.sigma);
+            let start = self.row_start(i);
+            return &mut self.values[start + (self.row_lengths[i] - 1) * self.chunk_size];
+        }
+        let start = self.row_start(i);
+        let index = start + self.row_lengths[i] * self.chunk_size;
+        self.columns[index] = col;
+        self.values[index] = T::zero();
+        self.row_lengths[i] += 1;
+        // Keep the padding of the row on the last column
+        for k in self.row_lengths[i]..self.row_width(i) {
+            self.columns[start + k * self.chunk_size] = col;
+        }
+        &mut self.values[index]
+    }
+
+    // New rows rebuild the whole storage
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        self.n_cols = std::cmp::max(self.n_cols, n_cols);
+        if n_rows > self.n_rows {
+            let mut rows = self.to_rows();
+            rows.resize(n_rows, Vec::<(I, T)>::new());
+            *self = Self::from_rows(self.n_cols, &rows, self.chunk_size, self.sigma);
+        }
+    }
+
+    fn scale(&mut self, rhs: Self::Value) {
+        for iter in self.values.iter_mut() {
+            *iter *= rhs;
+        }
+    }
+
+    // Processes one slot of all rows of a slice at a time including the padding,
+    // which gives a branch-free inner loop of chunk size suitable for auto-vectorisation
+    fn mvp<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = T> {
+        let x = (0..self.n_cols).map(|j| rhs.get(j)).collect::<Vec<T>>();
+        let c = self.chunk_size;
+        let mut sums = vec![T::zero(); c];
+        let mut ret = vec![T::zero(); self.n_rows];
+        for (slice, rows) in self.perm.chunks(c).enumerate() {
+            let start = self.offset_slices[slice];
+            let end = self.offset_slices[slice + 1];
+            for (cols, vals) in self.columns[start..end].chunks_exact(c).zip(self.values[start..end].chunks_exact(c)) {
+                for ((sum, col), &val) in sums.iter_mut().zip(cols.iter()).zip(vals.iter()) {
+                    *sum += val * x[col.as_usize()];
+                }
+            }
+            for (sum, &i) in sums.iter_mut().zip(rows.iter()) {
+                ret[i] = *sum;
+            }
+            sums.fill(T::zero());
+        }
+        V::from_vec(ret)
+    }
+}
+
+sparsemat_ops!(SparseMatSELL);
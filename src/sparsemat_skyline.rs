@@ -0,0 +1,319 @@
+use crate::types::{IndexType, ValueType};
+use crate::indexlist::IndexList;
+use crate::sparsematrix::*;
+use crate::vector::Vector;
+use crate::densevec::DenseVec;
+
+// A symmetric sparse matrix with skyline (variable band) storage
+// Each row i stores all entries from its first column first[i] up to the diagonal contiguously,
+// so the entries (i, j) with first[i] <= j <= i are at offset_rows[i] + j - first[i]
+// Example: rows [0: a00], [1: a10, a11], [2: a22] with a20 = 0
+//          first       = [0, 0, 2]
+//          offset_rows = [0, 1, 3, 4]
+//          values      = [a00, a10, a11, a22]
+// The entries right of the diagonal are the mirrored entries of the columns,
+// which are tracked with an index list holding the rows j > i with first[j] <= i for each row i
+// All entries inside the profile count as non-zero entries
+// factorize() stores the LDL^T factorization separately with the same layout as the values:
+// The unit lower triangular factor L is stored below the diagonal and D on the diagonal
+// Changing the matrix drops the factorization, so get, iter_row and mvp always return the matrix
+#[derive(Clone, Debug)]
+pub struct SparseMatSkyline<T, I> {
+    first: Vec<usize>,
+    offset_rows: Vec<usize>,
+    columns: Vec<I>,
+    values: Vec<T>,
+    mirror: IndexList<I>,
+    mirror_rows: Vec<I>,
+    factor: Option<Vec<T>>,
+}
+
+impl<T, I> SparseMatSkyline<T, I>
+where T: ValueType,
+      I: IndexType {
+
+    // Returns the offset of entry (i, j) with j <= i or UNSET if it is outside of the profile
+    fn find_index(&self, i: usize, j: usize) -> usize {
+        if i < self.first.len() && j >= self.first[i] && j <= i {
+            self.offset_rows[i] + j - self.first[i]
+        } else {
+            Self::UNSET.as_usize()
+        }
+    }
+
+    // Extends the profile of row i to start at column j <= i
+    // This is very inefficient since the values of all following rows are moved
+    fn extend_profile(&mut self, i: usize, j: usize) {
+        if i >= self.first.len() {
+            for k in self.first.len()..=i {
+                self.first.push(k);
+                self.columns.push(I::as_indextype(k));
+                self.values.push(T::zero());
+                self.offset_rows.push(self.values.len());
+            }
+        }
+        let first = self.first[i];
+        if j < first {
+            let start = self.offset_rows[i];
+            self.columns.splice(start..start, (j..first).map(I::as_indextype));
+            self.values.splice(start..start, vec![T::zero(); first - j]);
+            for offset in self.offset_rows[(i + 1)..].iter_mut() {
+                *offset += first - j;
+            }
+            for k in j..first {
+                self.mirror.push(k);
+                self.mirror_rows.push(I::as_indextype(i));
+            }
+            self.first[i] = j;
+        }
+    }
+
+    // Creates a skyline matrix from the lower triangular part of any other sparse matrix,
+    // which is assumed to be symmetric - the entries above the diagonal are ignored
+    pub fn from_sparsemat<'b, M>(rhs: &'b M) -> Self
+    where M: SparseMatrix<'b, Value = T, Index = I>,
+          T: 'b,
+          I: 'b {
+        let n = std::cmp::max(rhs.n_rows(), rhs.n_cols());
+        let first = (0..n).map(|i| {
+            rhs.iter_row(i).map(|(&col, _val)| col.as_usize()).filter(|&j| j < i).min().unwrap_or(i)
+        }).collect::<Vec<usize>>();
+        let mut offset_rows = Vec::<usize>::with_capacity(n + 1);
+        let mut columns = Vec::<I>::new();
+        for (i, &first_i) in first.iter().enumerate() {
+            offset_rows.push(columns.len());
+            columns.extend((first_i..=i).map(I::as_indextype));
+        }
+        offset_rows.push(columns.len());
+        let mut values = vec![T::zero(); columns.len()];
+        for (i, &first_i) in first.iter().enumerate() {
+            for (&col, &val) in rhs.iter_row(i) {
+                let j = col.as_usize();
+                if j <= i {
+                    values[offset_rows[i] + j - first_i] += val;
+                }
+            }
+        }
+        let mut mirror = IndexList::<I>::new();
+        let mut mirror_rows = Vec::<I>::new();
+        for (i, &first_i) in first.iter().enumerate() {
+            for k in first_i..i {
+                mirror.push(k);
+                mirror_rows.push(I::as_indextype(i));
+            }
+        }
+        Self {
+            first,
+            offset_rows,
+            columns,
+            values,
+            mirror,
+            mirror_rows,
+            factor: None,
+        }
+    }
+
+    // Returns the profile, which is the number of entries inside the envelope below the diagonal
+    pub fn profile(&self) -> usize {
+        self.first.iter().enumerate().map(|(i, &first)| i - first).sum()
+    }
+
+    // Returns the maximum distance of the first entry of a row to the diagonal
+    pub fn bandwidth(&self) -> usize {
+        self.first.iter().enumerate().map(|(i, &first)| i - first).max().unwrap_or(0)
+    }
+
+    // Returns true if the LDL^T factorization of the current matrix is available
+    pub fn is_factorized(&self) -> bool {
+        self.factor.is_some()
+    }
+
+    // Computes the LDL^T factorization row by row
+    // No fill-in occurs outside of the profile, so the factor has the layout of the values
+    // Returns an error for a zero pivot
+    pub fn factorize(&mut self) -> Result<(), SparseMatError> {
+        self.factor = None;
+        let mut factor = self.values.clone();
+        let n = self.first.len();
+        for i in 0..n {
+            let first_i = self.first[i];
+            let offset_i = self.offset_rows[i];
+            // g_ij = a_ij - sum_k g_ik * l_jk with g_ik = l_ik * d_k
+            for j in first_i..i {
+                let first_j = self.first[j];
+                let offset_j = self.offset_rows[j];
+                let k_start = std::cmp::max(first_i, first_j);
+                let mut sum = T::zero();
+                for k in k_start..j {
+                    sum += factor[offset_i + k - first_i] * factor[offset_j + k - first_j];
+                }
+                factor[offset_i + j - first_i] -= sum;
+            }
+            // l_ij = g_ij / d_j and d_i = a_ii - sum_j l_ij * g_ij
+            let mut sum = T::zero();
+            for j in first_i..i {
+                let d_j = factor[self.offset_rows[j] + j - self.first[j]];
+                let g_ij = factor[offset_i + j - first_i];
+                let l_ij = g_ij / d_j;
+                sum += l_ij * g_ij;
+                factor[offset_i + j - first_i] = l_ij;
+            }
+            let d_i = offset_i + i - first_i;
+            factor[d_i] -= sum;
+            if factor[d_i] == T::zero() {
+                return Err(SparseMatError::new(&format!("Zero pivot in row {}", i)));
+            }
+        }
+        self.factor = Some(factor);
+        Ok(())
+    }
+
+    // Solves A * x = b with the LDL^T factorization - use factorize() first
+    pub fn solve(&self, b: &DenseVec<T>) -> Result<DenseVec<T>, SparseMatError> {
+        let factor = self.factor.as_ref().ok_or_else(|| SparseMatError::new("Matrix is not factorized - use factorize()"))?;
+        let n = self.first.len();
+        if b.dim() != n {
+            return Err(SparseMatError::new("Matrix and vector size mismatch"));
+        }
+        let mut x = (0..n).map(|i| b.get(i)).collect::<Vec<T>>();
+        // Forward substitution with L using the rows
+        for i in 0..n {
+            let row = &factor[self.offset_rows[i]..(self.offset_rows[i] + i - self.first[i])];
+            let mut sum = T::zero();
+            for (&l_ik, &x_k) in row.iter().zip(x[self.first[i]..i].iter()) {
+                sum += l_ik * x_k;
+            }
+            x[i] -= sum;
+        }
+        // Diagonal scaling
+        for (i, x_i) in x.iter_mut().enumerate() {
+            *x_i = *x_i / factor[self.offset_rows[i] + i - self.first[i]];
+        }
+        // Backward substitution with L^T using the columns, which are the rows of L
+        for i in (0..n).rev() {
+            let x_i = x[i];
+            let row = &factor[self.offset_rows[i]..(self.offset_rows[i] + i - self.first[i])];
+            for (&l_ik, x_k) in row.iter().zip(x[self.first[i]..i].iter_mut()) {
+                *x_k -= l_ik * x_i;
+            }
+        }
+        Ok(DenseVec::from_vec(x))
+    }
+}
+
+impl<'a, T, I> SparseMatrix<'a> for SparseMatSkyline<T, I>
+where T: 'a + ValueType,
+      I: 'a + IndexType {
+    type Value = T;
+    type Index = I;
+    type IterRow = IterRow<'a, T, I>;
+
+    fn iter_row(&'a self, row: usize) -> Self::IterRow {
+        let (start, end) = if row < self.first.len() {
+            (self.offset_rows[row], self.offset_rows[row + 1])
+        } else {
+            (0, 0)
+        };
+        IterRow::<T, I> {
+            mat: self,
+            row,
+            lower_iter: self.columns[start..end].iter().zip(self.values[start..end].iter()),
+            mirror_iter: self.mirror.iter_row(row),
+        }
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            first: Vec::<usize>::new(),
+            offset_rows: vec![0],
+            columns: Vec::<I>::with_capacity(cap),
+            values: Vec::<T>::with_capacity(cap),
+            mirror: IndexList::<I>::with_capacity(cap),
+            mirror_rows: Vec::<I>::with_capacity(cap),
+            factor: None,
+        }
+    }
+
+    fn n_rows(&self) -> usize {
+        self.first.len()
+    }
+
+    fn n_cols(&self) -> usize {
+        self.first.len()
+    }
+
+    fn n_non_zero_entries(&self) -> usize {
+        2 * self.values.len() - self.first.len()
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        let (i, j) = if j <= i { (i, j) } else { (j, i) };
+        let index = self.find_index(i, j);
+        if index != Self::UNSET.as_usize() {
+            self.values[index]
+        } else {
+            T::zero()
+        }
+    }
+
+    // Entries (i, j) and (j, i) share the same value
+    // Entries outside of the profile extend it
+    // The returned entry may be changed, so the factorization is dropped
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        self.factor = None;
+        let (i, j) = if j <= i { (i, j) } else { (j, i) };
+        let mut index = self.find_index(i, j);
+        if index == Self::UNSET.as_usize() {
+            self.extend_profile(i, j);
+            index = self.find_index(i, j);
+        }
+        &mut self.values[index]
+    }
+
+    // The dimension is the larger of both and each new row stores its diagonal
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        let dim = std::cmp::max(n_rows, n_cols);
+        if dim > self.first.len() {
+            self.factor = None;
+            self.extend_profile(dim - 1, dim - 1);
+        }
+    }
+
+    fn scale(&mut self, rhs: Self::Value) {
+        self.factor = None;
+        for iter in self.values.iter_mut() {
+            *iter *= rhs;
+        }
+    }
+
+    fn is_symmetric(&'a self) -> bool {
+        true
+    }
+}
+
+// Iterator over the stored entries up to the diagonal and the mirrored entries of a row
+pub struct IterRow<'a, T, I> {
+    mat: &'a SparseMatSkyline<T, I>,
+    row: usize,
+    lower_iter: std::iter::Zip<std::slice::Iter<'a, I>, std::slice::Iter<'a, T>>,
+    mirror_iter: crate::indexlist::IterRow<'a, I>,
+}
+
+impl<'a, T, I> Iterator for IterRow<'a, T, I>
+where I: IndexType {
+    type Item = (&'a I, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.lower_iter.next() {
+            Some(entry)
+        } else {
+            self.mirror_iter.next().map(|pos| {
+                let row = &self.mat.mirror_rows[pos];
+                let i = row.as_usize();
+                (row, &self.mat.values[self.mat.offset_rows[i] + self.row - self.mat.first[i]])
+            })
+        }
+    }
+}
+
+sparsemat_ops!(SparseMatSkyline);
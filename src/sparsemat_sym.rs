@@ -0,0 +1,301 @@
+use crate::types::{IndexType, ValueType};
+use crate::sparsematrix::*;
+use crate::sparsemat_crs::SparseMatCRS;
+use crate::sparsemat_skyline::SparseMatSkyline;
+use crate::vector::Vector;
+use crate::densevec::DenseVec;
+
+// A symmetric sparse matrix storing only the entries (i, j) with i <= j
+// The upper triangle is stored in compressed row form with the columns of each row sorted,
+// so entry (i, j) is found by a binary search in columns[offset_rows[i]..offset_rows[i + 1]]
+// The mirrored entries left of the diagonal in row j are the entries (i, j) with i < j of column j,
+// which are indexed by a column pointer of the strictly upper triangle:
+// offset_lower[j]..offset_lower[j + 1] are the positions of column j in rows_lower and pos_lower,
+// holding the row of each entry and its position in the data vecs
+// Example: rows [0: a00, a02], [1: a11], [2: a22] of the upper triangle
+//          offset_rows  = [0, 2, 3, 4]
+//          columns      = [0, 2, 1, 2]
+//          offset_lower = [0, 0, 0, 1]
+//          rows_lower   = [0]
+//          pos_lower    = [1]
+// The column pointer is rebuilt whenever a new entry is inserted, so the assembly entry by entry is slow
+// The row iterator returns the full logical row - first the mirrored entries, then the stored ones
+#[derive(Clone, Debug)]
+pub struct SparseMatSym<T, I> {
+    dim: usize,
+    n_diagonal: usize,
+    offset_rows: Vec<I>,
+    columns: Vec<I>,
+    values: Vec<T>,
+    offset_lower: Vec<I>,
+    rows_lower: Vec<I>,
+    pos_lower: Vec<I>,
+}
+
+impl<T, I> SparseMatSym<T, I>
+where T: ValueType,
+      I: IndexType {
+
+    // Returns the range of the stored entries of row i in the columns and values vec
+    #[inline]
+    fn row_range(&self, i: usize) -> std::ops::Range<usize> {
+        self.offset_rows[i].as_usize()..self.offset_rows[i + 1].as_usize()
+    }
+
+    // Returns the offset for the data vecs or UNSET if entry (i, j) does not exist
+    // The indices are swapped if the entry is below the diagonal
+    fn find_index(&self, i: usize, j: usize) -> usize {
+        let (i, j) = if i <= j { (i, j) } else { (j, i) };
+        if j >= self.dim {
+            return Self::UNSET.as_usize();
+        }
+        let range = self.row_range(i);
+        match self.columns[range.clone()].binary_search(&I::as_indextype(j)) {
+            Ok(pos) => range.start + pos,
+            Err(_) => Self::UNSET.as_usize(),
+        }
+    }
+
+    // Extends the matrix to the dimension dim with empty rows
+    fn resize(&mut self, dim: usize) {
+        if dim > self.dim {
+            let nnz = I::as_indextype(self.columns.len());
+            self.offset_rows.resize(dim + 1, nnz);
+            self.dim = dim;
+        }
+    }
+
+    // Inserts a new entry at (i, j) with i <= j keeping the row sorted
+    // This is very inefficient since we use insert of Vec here and rebuild the column pointer
+    fn push(&mut self, i: usize, j: usize, val: T) -> usize {
+        self.resize(j + 1);
+        let range = self.row_range(i);
+        let index = range.start + self.columns[range].partition_point(|col| col.as_usize() < j);
+        self.columns.insert(index, I::as_indextype(j));
+        self.values.insert(index, val);
+        for offset in self.offset_rows[(i + 1)..].iter_mut() {
+            *offset += I::ONE;
+        }
+        if i == j {
+            self.n_diagonal += 1;
+        }
+        self.build_lower();
+        index
+    }
+
+    // Builds the column pointer of the strictly upper triangle with a counting sort over the columns
+    fn build_lower(&mut self) {
+        let mut offset_lower = vec![0usize; self.dim + 1];
+        for i in 0..self.dim {
+            for &col in self.columns[self.row_range(i)].iter() {
+                if i < col.as_usize() {
+                    offset_lower[col.as_usize() + 1] += 1;
+                }
+            }
+        }
+        for j in 0..self.dim {
+            offset_lower[j + 1] += offset_lower[j];
+        }
+        let mut next = offset_lower.clone();
+        let mut rows_lower = vec![I::ZERO; offset_lower[self.dim]];
+        let mut pos_lower = vec![I::ZERO; offset_lower[self.dim]];
+        for i in 0..self.dim {
+            for index in self.row_range(i) {
+                let j = self.columns[index].as_usize();
+                if i < j {
+                    rows_lower[next[j]] = I::as_indextype(i);
+                    pos_lower[next[j]] = I::as_indextype(index);
+                    next[j] += 1;
+                }
+            }
+        }
+        self.offset_lower = offset_lower.into_iter().map(I::as_indextype).collect();
+        self.rows_lower = rows_lower;
+        self.pos_lower = pos_lower;
+    }
+
+    // Creates a symmetric matrix from the upper triangular part of any other sparse matrix
+    // The entries below the diagonal are ignored
+    pub fn from_upper<'b, M>(rhs: &'b M) -> Self
+    where M: SparseMatrix<'b, Value = T, Index = I>,
+          T: 'b,
+          I: 'b {
+        let mut ret = Self::with_capacity(rhs.n_non_zero_entries());
+        ret.resize(std::cmp::max(rhs.n_rows(), rhs.n_cols()));
+        let mut row = Vec::<(usize, T)>::new();
+        for i in 0..rhs.n_rows() {
+            row.extend(rhs.iter_row(i).map(|(&col, &val)| (col.as_usize(), val)).filter(|&(j, _val)| i <= j));
+            row.sort_by_key(|&(j, _val)| j);
+            for &(j, val) in row.iter() {
+                if ret.columns.len() > ret.offset_rows[i].as_usize() && ret.columns[ret.columns.len() - 1].as_usize() == j {
+                    // Add up repeated entries
+                    *ret.values.last_mut().unwrap() += val;
+                } else {
+                    ret.columns.push(I::as_indextype(j));
+                    ret.values.push(val);
+                    if i == j {
+                        ret.n_diagonal += 1;
+                    }
+                }
+            }
+            row.clear();
+            ret.offset_rows[i + 1] = I::as_indextype(ret.columns.len());
+        }
+        let nnz = I::as_indextype(ret.columns.len());
+        for offset in ret.offset_rows[(rhs.n_rows() + 1)..].iter_mut() {
+            *offset = nnz;
+        }
+        ret.build_lower();
+        ret
+    }
+
+    // Returns the number of actually stored entries
+    pub fn n_stored_entries(&self) -> usize {
+        self.values.len()
+    }
+
+    // Creates a new sparse matrix with CRS format holding both triangular parts
+    pub fn to_crs(&self) -> SparseMatCRS<T, I> {
+        SparseMatCRS::from_sparsemat(self)
+    }
+
+    // Creates a skyline matrix from the lower triangle given by the mirrored entries,
+    // which solves the system with its LDL^T factorization
+    pub fn to_skyline(&self) -> SparseMatSkyline<T, I> {
+        SparseMatSkyline::from_sparsemat(self)
+    }
+}
+
+impl<'a, T, I> SparseMatrix<'a> for SparseMatSym<T, I>
+where T: 'a + ValueType,
+      I: 'a + IndexType {
+    type Value = T;
+    type Index = I;
+    type IterRow = IterRow<'a, T, I>;
+
+    fn iter_row(&'a self, row: usize) -> Self::IterRow {
+        let (lower, upper) = if row < self.dim {
+            (self.offset_lower[row].as_usize()..self.offset_lower[row + 1].as_usize(), self.row_range(row))
+        } else {
+            (0..0, 0..0)
+        };
+        IterRow::<T, I> {
+            mat: self,
+            lower,
+            upper,
+        }
+    }
+
+    fn with_capacity(cap: usize) -> Self {
+        Self {
+            dim: 0,
+            n_diagonal: 0,
+            offset_rows: vec![I::ZERO],
+            columns: Vec::<I>::with_capacity(cap),
+            values: Vec::<T>::with_capacity(cap),
+            offset_lower: vec![I::ZERO],
+            rows_lower: Vec::<I>::with_capacity(cap),
+            pos_lower: Vec::<I>::with_capacity(cap),
+        }
+    }
+
+    fn n_rows(&self) -> usize {
+        self.dim
+    }
+
+    fn n_cols(&self) -> usize {
+        self.dim
+    }
+
+    // Returns the number of entries of the full matrix
+    fn n_non_zero_entries(&self) -> usize {
+        2 * self.values.len() - self.n_diagonal
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        let mut ret = T::zero();
+        let index = self.find_index(i, j);
+        if index != Self::UNSET.as_usize() {
+            ret = self.values[index];
+        }
+        ret
+    }
+
+    // Entries (i, j) and (j, i) share the same value
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut T {
+        let mut index = self.find_index(i, j);
+        if index == Self::UNSET.as_usize() {
+            let (i, j) = if i <= j { (i, j) } else { (j, i) };
+            index = self.push(i, j, T::zero());
+        }
+        &mut self.values[index]
+    }
+
+    // The dimension is the larger of both
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize) {
+        if std::cmp::max(n_rows, n_cols) > self.dim {
+            self.resize(std::cmp::max(n_rows, n_cols));
+            self.build_lower();
+        }
+    }
+
+    fn scale(&mut self, rhs: Self::Value) {
+        for iter in self.values.iter_mut() {
+            *iter *= rhs;
+        }
+    }
+
+    fn transpose(&'a self) -> Self {
+        self.clone()
+    }
+
+    fn is_symmetric(&'a self) -> bool {
+        true
+    }
+
+    // Each stored entry contributes to both rows, so the data is read only once
+    fn mvp<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = T> {
+        let x = (0..self.dim).map(|j| rhs.get(j)).collect::<Vec<T>>();
+        let mut ret = vec![T::zero(); self.dim];
+        for i in 0..self.dim {
+            let range = self.row_range(i);
+            for (col, &val) in self.columns[range.clone()].iter().zip(self.values[range].iter()) {
+                let j = col.as_usize();
+                ret[i] += val * x[j];
+                if i != j {
+                    ret[j] += val * x[i];
+                }
+            }
+        }
+        V::from_vec(ret)
+    }
+
+    fn mvp_transpose<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = T> {
+        self.mvp(rhs)
+    }
+}
+
+// Iterator over the mirrored entries and the stored entries of a row
+pub struct IterRow<'a, T, I> {
+    mat: &'a SparseMatSym<T, I>,
+    lower: std::ops::Range<usize>,
+    upper: std::ops::Range<usize>,
+}
+
+impl<'a, T, I> Iterator for IterRow<'a, T, I>
+where I: IndexType {
+    type Item = (&'a I, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pos) = self.lower.next() {
+            let index = self.mat.pos_lower[pos].as_usize();
+            Some((&self.mat.rows_lower[pos], &self.mat.values[index]))
+        } else {
+            self.upper.next().map(|index| (&self.mat.columns[index], &self.mat.values[index]))
+        }
+    }
+}
+
+sparsemat_ops!(SparseMatSym);
@@ -1,9 +1,11 @@
 use std::string::String;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Write, BufWriter};
 use std::fmt;
 use crate::types::{IndexType, ValueType};
 use crate::sparsevec::SparseVec;
+use crate::spyplot::SpyPlot;
+use crate::densemat::DenseMat;
 use crate::vector::Vector;
 
 #[derive(Clone, Debug)]
@@ -25,6 +27,14 @@ impl fmt::Display for SparseMatError {
     }
 }
 
+impl std::error::Error for SparseMatError {}
+
+impl From<std::io::Error> for SparseMatError {
+    fn from(error: std::io::Error) -> SparseMatError {
+        SparseMatError::new(&format!("I/O error: {}", error))
+    }
+}
+
 pub struct Iter<'a, M>
 where M: SparseMatrix<'a>,
       M::Value: ValueType,
@@ -46,7 +56,7 @@ where M: SparseMatrix<'a>,
                 None => None
         };
         // Switch to next row if necessary
-        while ret == None && self.row < (self.mat.n_rows() - 1) {
+        while ret.is_none() && self.row < (self.mat.n_rows() - 1) {
             self.row += 1;
             self.iter_row = self.mat.iter_row(self.row);
             ret = match self.iter_row.next() {
@@ -71,9 +81,9 @@ where Self: Sized + Clone {
     type IterRow: Iterator<Item = (&'a Self::Index, &'a Self::Value)>;
     fn iter_row(&'a self, row: usize) -> Self::IterRow;
 
-    fn iter(&'a self) -> Iter<Self> {
+    fn iter(&'a self) -> Iter<'a, Self> {
         Iter {
-            mat: &self,
+            mat: self,
             row: 0,
             iter_row: self.iter_row(0),
         }
@@ -116,6 +126,9 @@ where Self: Sized + Clone {
     // Scales all values by a factor
     fn scale(&mut self, rhs: Self::Value);
 
+    // Extends the matrix with empty rows and columns to at least n_rows x n_cols
+    fn extend_to(&mut self, n_rows: usize, n_cols: usize);
+
     fn empty(&self) -> bool {
         self.n_rows() == 0
     }
@@ -157,6 +170,44 @@ where Self: Sized + Clone {
         ret
     }
 
+    // Performs a matrix-vector product with the transpose of the matrix
+    // The entries of each row are scattered, so the transpose is never formed
+    fn mvp_transpose<V>(&'a self, rhs: &V) -> V
+    where V: Vector<'a, Value = Self::Value> {
+        let mut ret = V::with_capacity(self.n_cols());
+        for j in 0..self.n_cols() {
+            ret.set(j, Self::Value::zero());
+        }
+        for i in 0..self.n_rows() {
+            let x_i = rhs.get(i);
+            for (&col, &val) in self.iter_row(i) {
+                let j = col.as_usize();
+                ret.add_to(j, val * x_i);
+            }
+        }
+        ret
+    }
+
+    // Performs the fused matrix-vector product y = alpha * A * x + beta * y
+    // Like in BLAS y is overwritten for beta = 0, so NaN or Inf in y do not carry over
+    fn mvp_add<V>(&'a self, alpha: Self::Value, rhs: &V, beta: Self::Value, ret: &mut V)
+    where V: Vector<'a, Value = Self::Value> {
+        for i in 0..self.n_rows() {
+            let mut sum = Self::Value::zero();
+            for (&col, &val) in self.iter_row(i) {
+                let j = col.as_usize();
+                sum += rhs.get(j) * val;
+            }
+            let y = ret.get_mut(i);
+            if beta == Self::Value::zero() {
+                *y = alpha * sum;
+            } else {
+                *y *= beta;
+                *y += alpha * sum;
+            }
+        }
+    }
+
     // Performs an inner product with two vectors returning a scalar
     fn inner_prod<V>(&'a self, lhs: &V, rhs: &V) -> Self::Value
     where V: Vector<'a, Value = Self::Value> {
@@ -209,6 +260,55 @@ where Self: Sized + Clone {
         Ok(ret)
     }
 
+    // Computes the product with a dense matrix (SpMM)
+    fn prod_dense(&'a self, rhs: &DenseMat<Self::Value>) -> Result<DenseMat<Self::Value>, SparseMatError> {
+        self.mvp_multi(rhs)
+    }
+
+    // Multiplies the matrix with a block of vectors stored as the columns of a row-major matrix
+    // The matrix is traversed only once instead of once per vector like repeated calls of mvp
+    fn mvp_multi(&'a self, rhs: &DenseMat<Self::Value>) -> Result<DenseMat<Self::Value>, SparseMatError> {
+        if self.n_cols() != rhs.n_rows() {
+            return Err(SparseMatError::new("Dimension mismatch"));
+        }
+        let mut ret = DenseMat::<Self::Value>::zeros(self.n_rows(), rhs.n_cols());
+        for i in 0..self.n_rows() {
+            for (col, &val) in self.iter_row(i) {
+                for (r, &b) in ret.row_mut(i).iter_mut().zip(rhs.row(col.as_usize()).iter()) {
+                    *r += val * b;
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    // Returns the matrix with all zeroes as dense matrix
+    fn to_dense(&'a self) -> DenseMat<Self::Value> {
+        let mut ret = DenseMat::<Self::Value>::zeros(self.n_rows(), self.n_cols());
+        for i in 0..self.n_rows() {
+            for (col, &val) in self.iter_row(i) {
+                ret.add_to(i, col.as_usize(), val);
+            }
+        }
+        ret
+    }
+
+    // Creates a sparse matrix from the entries of a dense matrix with magnitude larger than tol
+    // The dimensions of the dense matrix are kept
+    fn from_dense(dense: &DenseMat<Self::Value>, tol: f64) -> Self {
+        let n_entries = dense.as_slice().iter().filter(|&&val| val.into().abs() > tol).count();
+        let mut ret = Self::with_capacity(n_entries);
+        ret.extend_to(dense.n_rows(), dense.n_cols());
+        for i in 0..dense.n_rows() {
+            for (j, &val) in dense.row(i).iter().enumerate() {
+                if val.into().abs() > tol {
+                    ret.set(i, j, val);
+                }
+            }
+        }
+        ret
+    }
+
     // Checks if the matrix is symmetric
     fn is_symmetric(&'a self) -> bool {
         for i in 0..self.n_rows() {
@@ -222,6 +322,32 @@ where Self: Sized + Clone {
         true
     }
 
+    // Returns the Gershgorin discs of all rows as pairs of center a_ii and radius sum_{j != i} |a_ij|
+    // Every eigenvalue lies in at least one of the discs
+    fn gershgorin_discs(&'a self) -> Vec<(f64, f64)> {
+        (0..self.n_rows()).map(|i| {
+            let mut center = 0.0f64;
+            let mut radius = 0.0f64;
+            for (&col, &val) in self.iter_row(i) {
+                let val: f64 = val.into();
+                if col.as_usize() == i {
+                    center += val;
+                } else {
+                    radius += val.abs();
+                }
+            }
+            (center, radius)
+        }).collect()
+    }
+
+    // Returns lower and upper bounds for the real parts of all eigenvalues
+    // as the union of the Gershgorin discs
+    fn gershgorin_bounds(&'a self) -> (f64, f64) {
+        self.gershgorin_discs().iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lower, upper), &(center, radius)| {
+            (lower.min(center - radius), upper.max(center + radius))
+        })
+    }
+
     // Sets value at (i, j) to val
     fn set(&mut self, i: usize, j: usize, val: Self::Value) {
         *self.get_mut(i, j) = val;
@@ -310,30 +436,66 @@ where Self: Sized + Clone {
         ret
     }
 
-    // Writes sparse matrix structure to a black / white PBM-file
-    fn to_pbm(&'a self, filename: String) {
-        let mut file = File::create(filename).unwrap();
-        file.write_all(b"P1\n").unwrap();
-        file.write_all(self.n_rows().to_string().as_bytes()).unwrap();
-        file.write_all(b" ").unwrap();
-        file.write_all(self.n_cols().to_string().as_bytes()).unwrap();
-        file.write_all(b"\n").unwrap();
-        for i in 0..self.n_rows() {
-            let mut j = Self::Index::ZERO;
-            let mut tmp = String::from("");
-            // Sort the entries first
-            let mut cols = self.iter_row(i).map(|(&c, &_v)| c).collect::<Vec<Self::Index>>();
-            cols.sort_by(|c1, c2| c1.partial_cmp(c2).unwrap());
-            for col in cols {
-                while j < col {
-                    tmp += "1";
-                    j += Self::Index::ONE;
-                }
-                tmp += "0";
-                j += Self::Index::ONE;
+    // Returns a spy plot of the matrix structure whose larger side has at most max_size pixels
+    fn spy_plot(&'a self, max_size: usize) -> Result<SpyPlot, SparseMatError> {
+        SpyPlot::new(self, max_size)
+    }
+
+    // Writes the density of the entries as grey scale PGM-file
+    fn to_pgm(&'a self, filename: &str, max_size: usize) -> Result<(), SparseMatError> {
+        self.spy_plot(max_size)?.save_pgm(filename)
+    }
+
+    // Writes the magnitude of the entries as colour PPM-file
+    fn to_ppm(&'a self, filename: &str, max_size: usize) -> Result<(), SparseMatError> {
+        self.spy_plot(max_size)?.save_ppm(filename)
+    }
+
+    // Writes the magnitude of the entries as SVG-file with axes
+    fn to_svg(&'a self, filename: &str, max_size: usize) -> Result<(), SparseMatError> {
+        self.spy_plot(max_size)?.save_svg(filename)
+    }
+
+    // Writes the matrix as graph in the DOT language of Graphviz
+    // Symmetric matrices are written as undirected graph, others as digraph
+    fn write_dot<W>(&'a self, writer: &mut W) -> Result<(), SparseMatError>
+    where W: Write {
+        crate::graph::write_dot(self, writer)
+    }
+
+    // Writes the matrix as graph to a DOT-file
+    fn to_dot(&'a self, filename: &str) -> Result<(), SparseMatError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_dot(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// Moves the entry at position k of both slices to position perm[k]
+// by following the cycles of the permutation - perm is consumed
+pub(crate) fn permute_in_place<A, B>(perm: &mut [usize], a: &mut [A], b: &mut [B])
+where A: Copy,
+      B: Copy {
+    const DONE: usize = usize::MAX;
+    for start in 0..perm.len() {
+        if perm[start] == DONE {
+            continue;
+        }
+        let mut k = start;
+        let mut val_a = a[start];
+        let mut val_b = b[start];
+        loop {
+            let target = perm[k];
+            perm[k] = DONE;
+            if target == start {
+                a[start] = val_a;
+                b[start] = val_b;
+                break;
             }
-            tmp += "\n";
-            file.write_all(tmp.as_bytes()).unwrap();
+            std::mem::swap(&mut val_a, &mut a[target]);
+            std::mem::swap(&mut val_b, &mut b[target]);
+            k = target;
         }
     }
 }
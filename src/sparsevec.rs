@@ -44,6 +44,15 @@ where T: ValueType,
         self.indices.iter().zip(self.values.iter())
     }
 
+    // Creates a sparse vector from its indices and values without any checks
+    pub(crate) fn from_parts(indices: Vec<I>, values: Vec<T>, dim: usize) -> Self {
+        Self {
+            values,
+            indices,
+            dim,
+        }
+    }
+
     pub fn n_non_zero_entries(&self) -> usize {
         self.values.len()
     }
@@ -69,7 +78,7 @@ where T: 'a + ValueType,
         let mut data = self.iter_sparse().map(|(&c, &v)| (c, v)).collect::<Vec<(I, T)>>();
         data.sort_by(|(c1, _v1), (c2, _v2)| c1.partial_cmp(c2).unwrap());
         Self::IterVal {
-            data: data,
+            data,
             pos: 0,
             index: 0,
         }
@@ -92,7 +101,7 @@ where T: 'a + ValueType,
         }
         Self {
             values: vec,
-            indices: indices,
+            indices,
             dim: len,
         }
     }
@@ -216,3 +225,52 @@ where T: ValueType,
         self.inner_prod(&rhs)
     }
 }
+
+#[cfg(feature = "serde")]
+mod serialization {
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+    use super::*;
+
+    #[derive(Serialize)]
+    struct RawRef<'a, T, I> {
+        values: &'a [T],
+        indices: &'a [I],
+        dim: usize,
+    }
+
+    #[derive(Deserialize)]
+    struct Raw<T, I> {
+        values: Vec<T>,
+        indices: Vec<I>,
+        dim: usize,
+    }
+
+    impl<T, I> Serialize for SparseVec<T, I>
+    where T: ValueType + Serialize,
+          I: IndexType + Serialize {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+            RawRef {
+                values: &self.values,
+                indices: &self.indices,
+                dim: self.dim,
+            }.serialize(serializer)
+        }
+    }
+
+    impl<'de, T, I> Deserialize<'de> for SparseVec<T, I>
+    where T: ValueType + Deserialize<'de>,
+          I: IndexType + Deserialize<'de> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            let raw = Raw::<T, I>::deserialize(deserializer)?;
+            if raw.indices.len() != raw.values.len() {
+                return Err(serde::de::Error::custom("Size mismatch of indices and values"));
+            }
+            if let Some(index) = raw.indices.iter().find(|index| index.as_usize() >= raw.dim) {
+                return Err(serde::de::Error::custom(format!("Index {} out of bounds", index)));
+            }
+            Ok(Self::from_parts(raw.indices, raw.values, raw.dim))
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs::File;
+use std::io::{Write, BufWriter};
+use crate::types::IndexType;
+use crate::sparsematrix::*;
+
+// Downsampled image of the sparsity structure of a matrix
+// The entries are binned into square blocks of rows and columns,
+// so the size of the image is bounded independent of the size of the matrix
+// For each pixel the number of entries and their largest magnitude is tracked
+#[derive(Clone, Debug)]
+pub struct SpyPlot {
+    n_rows: usize,
+    n_cols: usize,
+    nnz: usize,
+    // Number of rows and columns of the matrix per pixel
+    bin_size: usize,
+    width: usize,
+    height: usize,
+    counts: Vec<usize>,
+    magnitudes: Vec<f64>,
+}
+
+// Anchors of the colour map from small (blue) to large (red) magnitudes
+const COLOUR_MAP: [(f64, f64, f64); 5] = [
+    (0.0, 0.0, 0.5),
+    (0.0, 0.5, 1.0),
+    (0.2, 0.8, 0.2),
+    (1.0, 0.8, 0.0),
+    (0.7, 0.0, 0.0),
+];
+
+// Side length of the plot area of the SVG output in pixels
+const SVG_PLOT_SIZE: f64 = 512.0;
+const SVG_MARGIN: f64 = 48.0;
+
+impl SpyPlot {
+    // Bins the entries of the matrix into an image whose larger side has at most max_size pixels
+    pub fn new<'a, M>(mat: &'a M, max_size: usize) -> Result<Self, SparseMatError>
+    where M: SparseMatrix<'a> {
+        if max_size == 0 {
+            return Err(SparseMatError::new("Size of the spy plot must be positive"));
+        }
+        let (n_rows, n_cols) = (mat.n_rows(), mat.n_cols());
+        if n_rows == 0 || n_cols == 0 {
+            return Err(SparseMatError::new("Spy plot of an empty matrix"));
+        }
+        let bin_size = std::cmp::max(n_rows, n_cols).div_ceil(max_size);
+        let width = n_cols.div_ceil(bin_size);
+        let height = n_rows.div_ceil(bin_size);
+        let mut counts = vec![0usize; width * height];
+        let mut magnitudes = vec![0.0f64; width * height];
+        let mut nnz = 0;
+        for i in 0..n_rows {
+            let offset = (i / bin_size) * width;
+            for (col, &val) in mat.iter_row(i) {
+                let bin = offset + col.as_usize() / bin_size;
+                let val: f64 = val.into();
+                counts[bin] += 1;
+                magnitudes[bin] = magnitudes[bin].max(val.abs());
+                nnz += 1;
+            }
+        }
+        Ok(Self {
+            n_rows,
+            n_cols,
+            nnz,
+            bin_size,
+            width,
+            height,
+            counts,
+            magnitudes,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Returns the number of rows and columns of the matrix covered by a pixel
+    pub fn bin_size(&self) -> usize {
+        self.bin_size
+    }
+
+    // Returns the number of entries binned into pixel (x, y)
+    pub fn count(&self, x: usize, y: usize) -> usize {
+        self.counts[y * self.width + x]
+    }
+
+    // Returns the grey value of each pixel - white if empty and darker the more entries it holds
+    // Pixels with entries are at most 192, so single entries stay visible
+    fn grey_values(&self) -> Vec<u8> {
+        let max_count = self.counts.iter().copied().max().unwrap_or(0);
+        self.counts.iter().map(|&count| {
+            if count == 0 {
+                255
+            } else {
+                (192.0 * (1.0 - count as f64 / max_count as f64)).round() as u8
+            }
+        }).collect()
+    }
+
+    // Returns the colour of each pixel from the largest magnitude on a logarithmic scale
+    // Empty pixels are white
+    fn colours(&self) -> Vec<[u8; 3]> {
+        let log_magnitudes = self.magnitudes.iter().map(|&mag| mag.max(f64::MIN_POSITIVE).log10()).collect::<Vec<f64>>();
+        let nonempty = || log_magnitudes.iter().zip(self.counts.iter()).filter(|(_mag, &count)| count > 0).map(|(&mag, _count)| mag);
+        let min = nonempty().fold(f64::INFINITY, f64::min);
+        let max = nonempty().fold(f64::NEG_INFINITY, f64::max);
+        log_magnitudes.iter().zip(self.counts.iter()).map(|(&mag, &count)| {
+            if count == 0 {
+                return [255, 255, 255];
+            }
+            let t = if max > min { (mag - min) / (max - min) } else { 1.0 };
+            // Linear interpolation between the anchors of the colour map
+            let pos = t * (COLOUR_MAP.len() - 1) as f64;
+            let k = std::cmp::min(pos.floor() as usize, COLOUR_MAP.len() - 2);
+            let s = pos - k as f64;
+            let (c0, c1) = (COLOUR_MAP[k], COLOUR_MAP[k + 1]);
+            let mix = |a: f64, b: f64| (255.0 * (a + s * (b - a))).round() as u8;
+            [mix(c0.0, c1.0), mix(c0.1, c1.1), mix(c0.2, c1.2)]
+        }).collect()
+    }
+
+    // Writes a binary PGM image with the density of the entries
+    pub fn write_pgm<W>(&self, writer: &mut W) -> Result<(), SparseMatError>
+    where W: Write {
+        write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.grey_values())?;
+        Ok(())
+    }
+
+    // Writes a binary PPM image with the magnitude of the entries as colour map
+    pub fn write_ppm<W>(&self, writer: &mut W) -> Result<(), SparseMatError>
+    where W: Write {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let bytes = self.colours().iter().flatten().copied().collect::<Vec<u8>>();
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    // Writes an SVG image with the magnitude of the entries as colour map
+    // Columns are labelled at the top and rows on the left like the matrix is read
+    pub fn write_svg<W>(&self, writer: &mut W) -> Result<(), SparseMatError>
+    where W: Write {
+        let scale = SVG_PLOT_SIZE / std::cmp::max(self.width, self.height) as f64;
+        let (plot_width, plot_height) = (scale * self.width as f64, scale * self.height as f64);
+        writeln!(writer, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"11\">",
+                 plot_width + 2.0 * SVG_MARGIN, plot_height + 2.0 * SVG_MARGIN)?;
+        writeln!(writer, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")?;
+        writeln!(writer, "<g transform=\"translate({},{})\">", SVG_MARGIN, SVG_MARGIN)?;
+        // Entries
+        for (bin, colour) in self.colours().iter().enumerate() {
+            if self.counts[bin] > 0 {
+                let (x, y) = (bin % self.width, bin / self.width);
+                writeln!(writer, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"/>",
+                         scale * x as f64, scale * y as f64, scale, scale, colour[0], colour[1], colour[2])?;
+            }
+        }
+        // Frame and axes with ticks at the first, last and three intermediate indices
+        writeln!(writer, "<rect width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"black\"/>", plot_width, plot_height)?;
+        for col in Self::ticks(self.n_cols) {
+            let x = scale * (col as f64 + 0.5) / self.bin_size as f64;
+            writeln!(writer, "<line x1=\"{x}\" y1=\"0\" x2=\"{x}\" y2=\"-4\" stroke=\"black\"/>", x = x)?;
+            writeln!(writer, "<text x=\"{}\" y=\"-8\" text-anchor=\"middle\">{}</text>", x, col)?;
+        }
+        for row in Self::ticks(self.n_rows) {
+            let y = scale * (row as f64 + 0.5) / self.bin_size as f64;
+            writeln!(writer, "<line x1=\"0\" y1=\"{y}\" x2=\"-4\" y2=\"{y}\" stroke=\"black\"/>", y = y)?;
+            writeln!(writer, "<text x=\"-8\" y=\"{}\" text-anchor=\"end\" dominant-baseline=\"middle\">{}</text>", y, row)?;
+        }
+        writeln!(writer, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{} x {}, nnz = {}</text>",
+                 plot_width / 2.0, plot_height + 20.0, self.n_rows, self.n_cols, self.nnz)?;
+        writeln!(writer, "</g>\n</svg>")?;
+        Ok(())
+    }
+
+    // Returns the indices labelled on an axis of length n
+    fn ticks(n: usize) -> Vec<usize> {
+        let n_ticks = 5;
+        let mut ticks = (0..n_ticks).map(|k| k * (n - 1) / (n_ticks - 1)).collect::<Vec<usize>>();
+        ticks.dedup();
+        ticks
+    }
+
+    // Saves the image to a file, the format is chosen by the writer
+    fn save<F>(&self, filename: &str, write: F) -> Result<(), SparseMatError>
+    where F: Fn(&Self, &mut BufWriter<File>) -> Result<(), SparseMatError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        write(self, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn save_pgm(&self, filename: &str) -> Result<(), SparseMatError> {
+        self.save(filename, Self::write_pgm)
+    }
+
+    pub fn save_ppm(&self, filename: &str) -> Result<(), SparseMatError> {
+        self.save(filename, Self::write_ppm)
+    }
+
+    pub fn save_svg(&self, filename: &str) -> Result<(), SparseMatError> {
+        self.save(filename, Self::write_svg)
+    }
+}
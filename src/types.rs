@@ -1,3 +1,6 @@
+use std::ops::Add;
+use std::ops::Sub;
+use std::ops::Neg;
 use std::ops::AddAssign;
 use std::ops::SubAssign;
 use std::ops::MulAssign;
@@ -5,6 +8,7 @@ use std::ops::Mul;
 use std::ops::Div;
 use std::cmp::PartialEq;
 use std::cmp::PartialOrd;
+use std::hash::Hash;
 use std::iter::Sum;
 use std::fmt::Display;
 use std::fmt::Debug;
@@ -12,7 +16,7 @@ use std::mem::size_of;
 
 // Trait used for converting the index type to usize and vice versa
 pub trait IndexType
-where Self: Copy + PartialEq + AddAssign + PartialEq + PartialOrd + Display + Debug {
+where Self: Copy + PartialEq + Eq + Hash + AddAssign + PartialEq + PartialOrd + Ord + Display + Debug {
     const MAX: Self;
     const ZERO: Self;
     const ONE: Self;
@@ -67,11 +71,34 @@ where T: Copy + From<u8> + AddAssign + SubAssign + MulAssign + Mul<Output = Self
     }
 }
 
-pub trait FloatType {
+// Additional operations for floating point value types required by the solvers
+pub trait FloatType
+where Self: ValueType + Add<Output = Self> + Sub<Output = Self> + Neg<Output = Self> + PartialOrd {
+    fn from_f64(val: f64) -> Self;
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
 }
 
-impl FloatType for f32 {
-}
+macro_rules! make_floattype {
+    ( $t: ty ) => {
+        impl FloatType for $t {
+            #[inline]
+            fn from_f64(val: f64) -> $t {
+                val as $t
+            }
+
+            #[inline]
+            fn sqrt(self) -> $t {
+                <$t>::sqrt(self)
+            }
 
-impl FloatType for f64 {
+            #[inline]
+            fn abs(self) -> $t {
+                <$t>::abs(self)
+            }
+        }
+    }
 }
+
+make_floattype!(f32);
+make_floattype!(f64);